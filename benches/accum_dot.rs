@@ -59,6 +59,7 @@ impl Circuit<Fr> for MyCircuit {
                         &self.inputs,
                         Box::new(PolyOp::Einsum {
                             equation: "i,i->".to_string(),
+                            bias: None,
                         }),
                     )
                     .unwrap();
@@ -61,6 +61,7 @@ impl Circuit<Fr> for MyCircuit {
                         &self.inputs,
                         Box::new(PolyOp::Einsum {
                             equation: "ab,bc->ac".to_string(),
+                            bias: None,
                         }),
                     )
                     .unwrap();
@@ -70,6 +70,7 @@ impl Circuit<Fr> for MyCircuit {
             |region| {
                 let op = PolyOp::Einsum {
                     equation: "ij,jk->ik".to_string(),
+                    bias: None,
                 };
                 let mut region = region::RegionCtx::new(region, 0);
                 let output = config
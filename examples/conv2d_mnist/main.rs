@@ -206,6 +206,7 @@ where
                             &[self.l2_params[0].clone(), x],
                             Box::new(PolyOp::Einsum {
                                 equation: "ij,j->ik".to_string(),
+                                bias: None,
                             }),
                         )
                         .unwrap()
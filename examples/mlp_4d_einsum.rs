@@ -103,6 +103,7 @@ impl<F: PrimeField + TensorType + PartialOrd, const LEN: usize, const BITS: usiz
                             &[self.l0_params[0].clone(), self.input.clone()],
                             Box::new(PolyOp::Einsum {
                                 equation: "ab,bc->ac".to_string(),
+                                bias: None,
                             }),
                         )
                         .unwrap()
@@ -141,6 +142,7 @@ impl<F: PrimeField + TensorType + PartialOrd, const LEN: usize, const BITS: usiz
                             &[self.l2_params[0].clone(), x],
                             Box::new(PolyOp::Einsum {
                                 equation: "ab,bc->ac".to_string(),
+                                bias: None,
                             }),
                         )
                         .unwrap()
@@ -3,12 +3,8 @@ An easy-to-use implementation of the ElGamal Encryption in the form of a Halo2 C
 Huge thank you to https://github.com/timoftime/ for providing the inspiration and launching point for this <3 .
 */
 
-///
-mod add_chip;
-
 use crate::circuit::modules::poseidon::spec::PoseidonSpec;
 use crate::tensor::{Tensor, ValTensor, ValType};
-use add_chip::{AddChip, AddConfig, AddInstruction};
 use ark_std::rand::{CryptoRng, RngCore};
 use halo2_gadgets::poseidon::{
     primitives::{self as poseidon, ConstantLength},
@@ -24,24 +20,29 @@ use halo2_wrong_ecc::maingate::{
 };
 use halo2_wrong_ecc::{AssignedPoint, BaseFieldEccChip, EccConfig};
 use halo2curves::bn256::{Fq, Fr, G1Affine, G1};
+use halo2curves::ff::PrimeField;
 use halo2curves::group::cofactor::CofactorCurveAffine;
 use halo2curves::group::{Curve, Group};
 use halo2curves::CurveAffine;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::ops::{Mul, MulAssign};
 use std::rc::Rc;
 use std::vec;
 
-use super::Module;
+use super::add_chip::{AddChip, AddConfig, AddInstruction};
+use super::{load_json, save_json, Module};
 
 // Absolute offsets for public inputs.
 const C1_X: usize = 0;
 const C1_Y: usize = 1;
 const SK_H: usize = 2;
+const PK_X: usize = 2;
+const PK_Y: usize = 3;
 
 ///
-const NUMBER_OF_LIMBS: usize = 4;
-const BIT_LEN_LIMB: usize = 64;
+pub(crate) const NUMBER_OF_LIMBS: usize = 4;
+pub(crate) const BIT_LEN_LIMB: usize = 64;
 /// The number of instance columns used by the ElGamal circuit.
 pub const NUM_INSTANCE_COLUMNS: usize = 3;
 
@@ -167,6 +168,82 @@ impl ElGamalChip {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// An ElGamal keypair, used to encrypt tensors to (and decrypt them for) its holder.
+pub struct ElGamalKeypair {
+    /// The secret key.
+    pub sk: Fr,
+    /// The public key, pk = sk * generator.
+    pub pk: G1Affine,
+}
+
+impl ElGamalKeypair {
+    /// Generate a random keypair.
+    pub fn gen_random<R: CryptoRng + RngCore>(mut rng: &mut R) -> Self {
+        let sk = Fr::random(&mut rng);
+        let mut pk = G1::generator();
+        pk.mul_assign(sk);
+
+        Self {
+            sk,
+            pk: pk.to_affine(),
+        }
+    }
+
+    /// Save the keypair to a .json file.
+    pub fn save(&self, path: &std::path::PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        save_json(self, path)
+    }
+
+    /// Load a keypair from a .json file.
+    pub fn load(path: &std::path::PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        load_json(path)
+    }
+
+    /// Save the public key alone to a .json file, e.g to share it with provers encrypting to it.
+    pub fn save_pk(&self, path: &std::path::PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        save_json(&self.pk, path)
+    }
+
+    /// Load a public key saved with [ElGamalKeypair::save_pk] from a .json file.
+    pub fn load_pk(path: &std::path::PathBuf) -> Result<G1Affine, Box<dyn std::error::Error>> {
+        load_json(path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+/// Who tensors are encrypted to.
+pub enum ElGamalRecipient {
+    /// The prover, who generates the keypair and proves knowledge of the secret key (whose hash is a public input).
+    #[default]
+    Prover,
+    /// The holder of the given public key, which is a public input. The prover only knows the encryption randomness.
+    PublicKey(G1Affine),
+    /// The holder of the given public key, which is fixed in the circuit (such that it is committed to in the
+    /// verifying key). The prover only knows the encryption randomness.
+    FixedPublicKey(G1Affine),
+}
+
+impl ElGamalRecipient {
+    /// The recipient's public key, if the recipient isn't the prover.
+    pub fn pk(&self) -> Option<G1Affine> {
+        match self {
+            ElGamalRecipient::Prover => None,
+            ElGamalRecipient::PublicKey(pk) | ElGamalRecipient::FixedPublicKey(pk) => Some(*pk),
+        }
+    }
+
+    /// The number of instances each layout uses in the c1 instance column: c1 followed by the secret key hash,
+    /// the recipient's public key, or nothing.
+    pub fn num_c1_instances(&self) -> usize {
+        match self {
+            ElGamalRecipient::Prover => 3,
+            ElGamalRecipient::PublicKey(_) => 4,
+            ElGamalRecipient::FixedPublicKey(_) => 2,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// The variables used in the ElGamal circuit.
 pub struct ElGamalVariables {
@@ -180,6 +257,9 @@ pub struct ElGamalVariables {
     pub window_size: usize,
     /// The auxiliary generator used in the ECC chip.
     pub aux_generator: G1Affine,
+    /// Who the message is encrypted to.
+    #[serde(default)]
+    pub recipient: ElGamalRecipient,
 }
 
 impl Default for ElGamalVariables {
@@ -190,6 +270,7 @@ impl Default for ElGamalVariables {
             sk: Fr::zero(),
             window_size: 4,
             aux_generator: G1Affine::identity(),
+            recipient: ElGamalRecipient::default(),
         }
     }
 }
@@ -203,26 +284,59 @@ impl ElGamalVariables {
             sk,
             window_size,
             aux_generator,
+            recipient: ElGamalRecipient::default(),
         }
     }
 
     /// Generate random variables.
-    pub fn gen_random<R: CryptoRng + RngCore>(mut rng: &mut R) -> Self {
-        // get a random element from the scalar field
-        let sk = Fr::random(&mut rng);
-
-        // compute secret_key*generator to derive the public key
-        // With BN256, we create the private key from a random number. This is a private key value (sk
-        //  and a public key mapped to the G2 curve:: pk=sk.G2
-        let mut pk = G1::generator();
-        pk.mul_assign(sk);
+    pub fn gen_random<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        let keypair = ElGamalKeypair::gen_random(rng);
+        Self::gen_random_for_keypair(rng, &keypair)
+    }
 
+    /// Generate random variables (fresh randomness and auxiliary generator) for an existing keypair.
+    pub fn gen_random_for_keypair<R: CryptoRng + RngCore>(
+        mut rng: &mut R,
+        keypair: &ElGamalKeypair,
+    ) -> Self {
         Self {
             r: Fr::random(&mut rng),
-            pk: pk.to_affine(),
-            sk,
+            pk: keypair.pk,
+            sk: keypair.sk,
             window_size: 4,
             aux_generator: <G1Affine as CurveAffine>::CurveExt::random(rng).to_affine(),
+            recipient: ElGamalRecipient::Prover,
+        }
+    }
+
+    /// Generate random variables for encrypting to `recipient`. If the recipient is the prover, `keypair` (if any)
+    /// is used as the prover's keypair and a fresh keypair is generated otherwise. If the recipient isn't the
+    /// prover, the secret key is unknown (and left as zero).
+    pub fn gen_random_for_recipient<R: CryptoRng + RngCore>(
+        mut rng: &mut R,
+        recipient: ElGamalRecipient,
+        keypair: Option<&ElGamalKeypair>,
+    ) -> Self {
+        match (recipient.pk(), keypair) {
+            (None, Some(keypair)) => Self::gen_random_for_keypair(rng, keypair),
+            (None, None) => Self::gen_random(rng),
+            (Some(pk), _) => Self {
+                r: Fr::random(&mut rng),
+                pk,
+                sk: Fr::zero(),
+                window_size: 4,
+                aux_generator: <G1Affine as CurveAffine>::CurveExt::random(rng).to_affine(),
+                recipient,
+            },
+        }
+    }
+
+    /// The secret the prover proves knowledge of: the secret key if the prover is the recipient, and the
+    /// encryption randomness otherwise.
+    pub fn secret(&self) -> Fr {
+        match self.recipient {
+            ElGamalRecipient::Prover => self.sk,
+            _ => self.r,
         }
     }
 }
@@ -297,11 +411,80 @@ impl ElGamalGadget {
         msg
     }
 
-    /// Get the public inputs for the circuit.
-    pub fn get_instances(cipher: &(G1, Vec<Fr>), sk_hash: Fr) -> Vec<Vec<Fr>> {
-        let mut c1_and_sk = cipher
-            .0
-            .to_affine()
+    /// Recover c1 from its coordinates as they appear in the public inputs, where they are reduced modulo
+    /// the scalar field modulus. As the base field modulus is less than twice the scalar field modulus, each
+    /// coordinate has at most two candidate lifts, of which we pick the pair that lies on the curve.
+    pub fn c1_from_instances(x: Fr, y: Fr) -> Option<G1> {
+        // the scalar field modulus as a base field element
+        let modulus = Fq::from_repr((-Fr::one()).to_repr()).unwrap() + Fq::one();
+        let lifts = |c: Fr| {
+            let low = Fq::from_repr(c.to_repr()).unwrap();
+            let high = low + modulus;
+            // if high wrapped around the base field modulus it is less than the scalar field modulus
+            let high_is_valid = bool::from(Fr::from_repr(high.to_repr()).is_none());
+            let mut candidates = vec![low];
+            if high_is_valid {
+                candidates.push(high);
+            }
+            candidates
+        };
+
+        lifts(x)
+            .into_iter()
+            .cartesian_product(lifts(y))
+            .find_map(|(x, y)| Option::<G1Affine>::from(G1Affine::from_xy(x, y)))
+            .map(G1::from)
+    }
+
+    /// Decrypts the ciphertexts of consecutive module layouts, encrypting `lens` elements each, from the c1 and
+    /// ciphertext instance columns of a proof using `keypair`. Each layout has its own c1, followed by the instances
+    /// of [Self::recipient_instances], which must match the keypair.
+    pub fn decrypt_instances(
+        c1_instances: &[Fr],
+        ciphertexts: &[Fr],
+        lens: &[usize],
+        recipient: ElGamalRecipient,
+        keypair: &ElGamalKeypair,
+    ) -> Result<Vec<Vec<Fr>>, Box<dyn std::error::Error>> {
+        let stride = recipient.num_c1_instances();
+        let mut decrypted = vec![];
+        let mut offset = 0;
+        for (i, len) in lens.iter().enumerate() {
+            let layout = c1_instances
+                .get(stride * i..stride * (i + 1))
+                .ok_or("the proof contains fewer ElGamal layouts than expected")?;
+            let c1 = Self::c1_from_instances(layout[C1_X], layout[C1_Y])
+                .ok_or("the proof's c1 instances do not correspond to a curve point")?;
+            let is_keypairs = match recipient {
+                ElGamalRecipient::Prover => layout[SK_H] == Self::hash_sk(keypair.sk),
+                ElGamalRecipient::PublicKey(_) => {
+                    let pk = Self::c1_from_instances(layout[PK_X], layout[PK_Y]).ok_or(
+                        "the proof's public key instances do not correspond to a curve point",
+                    )?;
+                    pk.to_affine() == keypair.pk
+                }
+                ElGamalRecipient::FixedPublicKey(pk) => pk == keypair.pk,
+            };
+            if !is_keypairs {
+                return Err(format!(
+                    "layout {} was encrypted to a different key than the keypair's",
+                    i
+                )
+                .into());
+            }
+            let c2 = ciphertexts
+                .get(offset..offset + len)
+                .ok_or("the proof contains fewer ciphertexts than expected")?;
+            offset += len;
+
+            decrypted.push(Self::decrypt(&(c1, c2.to_vec()), keypair.sk));
+        }
+        Ok(decrypted)
+    }
+
+    /// The coordinates of a point, reduced modulo the scalar field modulus as they appear in the public inputs.
+    pub(crate) fn point_instances(point: G1Affine) -> Vec<Fr> {
+        point
             .coordinates()
             .map(|c| {
                 let x = Integer::from_fe(*c.x(), Self::rns());
@@ -309,11 +492,35 @@ impl ElGamalGadget {
 
                 vec![x.native(), y.native()]
             })
-            .unwrap();
+            .unwrap()
+    }
+
+    /// The public inputs following c1 in the c1 instance column: the hash of the secret key if the prover is the
+    /// recipient, the recipient's public key if it is a public input, and nothing otherwise.
+    pub fn recipient_instances(variables: &ElGamalVariables) -> Vec<Fr> {
+        match variables.recipient {
+            ElGamalRecipient::Prover => vec![Self::hash_sk(variables.sk)],
+            ElGamalRecipient::PublicKey(pk) => Self::point_instances(pk),
+            ElGamalRecipient::FixedPublicKey(_) => vec![],
+        }
+    }
+
+    /// Get the public inputs for the circuit, `recipient_instances` follow c1 in the c1 instance column
+    /// (see [Self::recipient_instances]).
+    pub fn get_instances(cipher: &(G1, Vec<Fr>), recipient_instances: Vec<Fr>) -> Vec<Vec<Fr>> {
+        let mut c1_and_recipient = Self::point_instances(cipher.0.to_affine());
 
-        c1_and_sk.push(sk_hash);
+        c1_and_recipient.extend(recipient_instances);
 
-        vec![c1_and_sk, cipher.1.clone()]
+        vec![c1_and_recipient, cipher.1.clone()]
+    }
+
+    /// The recipient of the loaded variables.
+    fn recipient(&self) -> ElGamalRecipient {
+        self.variables
+            .as_ref()
+            .map(|v| v.recipient)
+            .unwrap_or_default()
     }
 
     /// Hash the secret key to be used as a public input.
@@ -381,6 +588,47 @@ impl ElGamalGadget {
         Ok([s, c1])
     }
 
+    /// Computes c1 = r * generator and s = r * pk from the encryption randomness `r`, where pk is either a
+    /// (public) witness or a constant depending on the recipient.
+    pub(crate) fn verify_randomness(
+        &self,
+        mut layouter: impl Layouter<Fr>,
+        config: &ElGamalConfig,
+        r: &AssignedCell<Fr, Fr>,
+    ) -> Result<[AssignedPoint<Fq, Fr, NUMBER_OF_LIMBS, BIT_LEN_LIMB>; 3], plonk::Error> {
+        let mut chip = ElGamalChip::new(config.clone());
+
+        let variables = match self.variables {
+            Some(ref variables) => variables,
+            None => panic!("variables not loaded"),
+        };
+
+        layouter.assign_region(
+            || "obtain_s_from_r",
+            |region| {
+                let offset = 0;
+                let ctx = &mut RegionCtx::new(region, offset);
+
+                chip.ecc
+                    .assign_aux_generator(ctx, Value::known(variables.aux_generator))?;
+                chip.ecc.assign_aux(ctx, variables.window_size, 1)?;
+
+                let g = chip.ecc.assign_constant(ctx, G1Affine::generator())?;
+                let pk = match variables.recipient {
+                    ElGamalRecipient::FixedPublicKey(pk) => chip.ecc.assign_constant(ctx, pk)?,
+                    _ => chip.ecc.assign_point(ctx, Value::known(variables.pk))?,
+                };
+
+                // compute c1 = randomness*generator
+                let c1 = chip.ecc.mul(ctx, &g, r, variables.window_size)?;
+                // compute s = randomness*pk
+                let s = chip.ecc.mul(ctx, &pk, r, variables.window_size)?;
+
+                Ok([s, c1, pk])
+            },
+        )
+    }
+
     pub(crate) fn verify_encryption(
         &self,
         mut layouter: impl Layouter<Fr>,
@@ -443,9 +691,9 @@ impl Module<Fr> for ElGamalGadget {
     fn instance_increment_module(&self) -> Vec<usize> {
         // in order
         // 1. empty maingate instance
-        // 2. c1, sk_hash
+        // 2. c1, sk_hash (or recipient pk)
         // 3. c2
-        vec![0, 3, 0]
+        vec![0, self.recipient().num_c1_instances(), 0]
     }
 
     fn run(input: Self::RunInputs) -> Result<Vec<Vec<Fr>>, Box<dyn std::error::Error>> {
@@ -457,7 +705,10 @@ impl Module<Fr> for ElGamalGadget {
         let cipher = Self::encrypt(var.pk, input, var.r);
         // keep 1 empty (maingate instance variable).
         let mut public_inputs: Vec<Vec<Fr>> = vec![vec![]];
-        public_inputs.extend(Self::get_instances(&cipher, Self::hash_sk(var.sk)));
+        public_inputs.extend(Self::get_instances(
+            &cipher,
+            Self::recipient_instances(&var),
+        ));
 
         log::trace!("run (N={:?}) took: {:?}", len, start_time.elapsed());
 
@@ -529,13 +780,41 @@ impl Module<Fr> for ElGamalGadget {
             self.config.config_range(layouter)?;
         }
 
-        let (msg_var, sk_var) = self.layout_inputs(layouter, inputs)?;
+        // the secret is the sk if the prover is the recipient, and the randomness otherwise
+        let (msg_var, secret_var) = self.layout_inputs(layouter, inputs)?;
 
-        let [s, c1] = self.verify_secret(
-            layouter.namespace(|| "verify_secret"),
-            &self.config,
-            &sk_var,
-        )?;
+        let (s, c1, recipient_cells) = match self.recipient() {
+            ElGamalRecipient::Prover => {
+                let [s, c1] = self.verify_secret(
+                    layouter.namespace(|| "verify_secret"),
+                    &self.config,
+                    &secret_var,
+                )?;
+                // Force the public input to be the hash of the secret key so that we can ascertain decryption can happen
+                let sk_hash = self.verify_sk_hash(
+                    layouter.namespace(|| "verify_sk_hash"),
+                    &self.config,
+                    &secret_var,
+                )?;
+                (s, c1, vec![(SK_H, sk_hash)])
+            }
+            recipient => {
+                let [s, c1, pk] = self.verify_randomness(
+                    layouter.namespace(|| "verify_randomness"),
+                    &self.config,
+                    &secret_var,
+                )?;
+                // Force the public input to be the recipient's public key so that the verifier knows who can decrypt
+                let recipient_cells = match recipient {
+                    ElGamalRecipient::PublicKey(_) => vec![
+                        (PK_X, pk.x().native().clone()),
+                        (PK_Y, pk.y().native().clone()),
+                    ],
+                    _ => vec![],
+                };
+                (s, c1, recipient_cells)
+            }
+        };
 
         for (i, m) in msg_var.iter().enumerate() {
             let c2 = self.verify_encryption(
@@ -552,13 +831,6 @@ impl Module<Fr> for ElGamalGadget {
             )?;
         }
 
-        // Force the public input to be the hash of the secret key so that we can ascertain decryption can happen
-        let sk_hash = self.verify_sk_hash(
-            layouter.namespace(|| "verify_sk_hash"),
-            &self.config,
-            &sk_var,
-        )?;
-
         layouter
             .constrain_instance(
                 c1.x().native().cell(),
@@ -569,13 +841,16 @@ impl Module<Fr> for ElGamalGadget {
                 c1.y().native().cell(),
                 self.config.ciphertext_c1_exp_col,
                 C1_Y + row_offsets[1],
-            ))
-            .and(layouter.constrain_instance(
-                sk_hash.cell(),
-                self.config.ciphertext_c1_exp_col,
-                SK_H + row_offsets[1],
             ))?;
 
+        for (offset, cell) in recipient_cells {
+            layouter.constrain_instance(
+                cell.cell(),
+                self.config.ciphertext_c1_exp_col,
+                offset + row_offsets[1],
+            )?;
+        }
+
         let assigned_input: Tensor<ValType<Fr>> =
             msg_var.iter().map(|e| ValType::from(e.clone())).into();
 
@@ -628,11 +903,11 @@ mod tests {
         ) -> Result<(), Error> {
             let mut chip = ElGamalGadget::new(config);
             chip.load_variables(self.variables.clone());
-            let sk: Tensor<ValType<Fr>> =
-                Tensor::new(Some(&[Value::known(self.variables.sk).into()]), &[1]).unwrap();
+            let secret: Tensor<ValType<Fr>> =
+                Tensor::new(Some(&[Value::known(self.variables.secret()).into()]), &[1]).unwrap();
             chip.layout(
                 &mut layouter,
-                &[self.message.clone(), sk.into()],
+                &[self.message.clone(), secret.into()],
                 vec![0; NUM_INSTANCE_COLUMNS],
             )?;
             Ok(())
@@ -658,6 +933,23 @@ mod tests {
         assert_eq!(decrypted_msg, msg);
     }
 
+    #[test]
+    pub fn test_decrypt_from_instances() {
+        let mut rng = test_rng();
+
+        let keypair = ElGamalKeypair::gen_random(&mut rng);
+        let var = ElGamalVariables::gen_random_for_keypair(&mut rng, &keypair);
+
+        let msg = (0..4).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+        let instances = ElGamalGadget::run((msg.clone(), var)).unwrap();
+        let c1 = ElGamalGadget::c1_from_instances(instances[1][C1_X], instances[1][C1_Y]).unwrap();
+
+        let decrypted_msg = ElGamalGadget::decrypt(&(c1, instances[2].clone()), keypair.sk);
+
+        assert_eq!(decrypted_msg, msg);
+    }
+
     #[test]
     pub fn test_circuit() {
         let mut rng = test_rng();
@@ -683,4 +975,111 @@ mod tests {
         let res = MockProver::run(17, &circuit, public_inputs).unwrap();
         res.assert_satisfied_par();
     }
+
+    #[test]
+    pub fn test_circuit_to_recipient_pk() {
+        let mut rng = test_rng();
+
+        let keypair = ElGamalKeypair::gen_random(&mut rng);
+
+        for recipient in [
+            ElGamalRecipient::PublicKey(keypair.pk),
+            ElGamalRecipient::FixedPublicKey(keypair.pk),
+        ] {
+            let var = ElGamalVariables::gen_random_for_recipient(&mut rng, recipient, None);
+            // the prover doesn't know the secret key
+            assert_eq!(var.sk, Fr::zero());
+
+            let msg = (0..2).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+            let public_inputs: Vec<Vec<Fr>> =
+                ElGamalGadget::run((msg.clone(), var.clone())).unwrap();
+            assert_eq!(public_inputs[1].len(), recipient.num_c1_instances());
+
+            // the recipient can decrypt
+            let c1 =
+                ElGamalGadget::c1_from_instances(public_inputs[1][C1_X], public_inputs[1][C1_Y])
+                    .unwrap();
+            let decrypted_msg = ElGamalGadget::decrypt(&(c1, public_inputs[2].clone()), keypair.sk);
+            assert_eq!(decrypted_msg, msg);
+
+            let message: Tensor<ValType<Fr>> =
+                msg.into_iter().map(|m| Value::known(m).into()).into();
+
+            let circuit = EncryptytionCircuit {
+                message: message.into(),
+                variables: var,
+            };
+
+            let res = MockProver::run(17, &circuit, public_inputs).unwrap();
+            res.assert_satisfied_par();
+        }
+    }
+
+    #[test]
+    pub fn test_decrypt_several_tensors_from_instances() {
+        use crate::graph::modules::{ElGamalResult, GraphModules, ELGAMAL};
+        use crate::graph::registry::{ModuleContext, ModuleRegistry, ProverInputs};
+        use crate::graph::Visibility;
+
+        let mut rng = test_rng();
+        let keypair = ElGamalKeypair::gen_random(&mut rng);
+        let recipient = ElGamalRecipient::PublicKey(keypair.pk);
+
+        let tensors: Vec<Tensor<i128>> = vec![
+            Tensor::new(Some(&[1, 2, 3]), &[3]).unwrap(),
+            Tensor::new(Some(&[4, 5]), &[2]).unwrap(),
+        ];
+        // two module layouts (e.g of the inputs and of the outputs), the first encrypting both tensors
+        let mut c1_instances = vec![];
+        let mut ciphertexts = vec![];
+        let mut lens = vec![];
+        for layout in [&tensors[..], &tensors[1..]] {
+            let res = GraphModules::forward(
+                &ModuleRegistry::default(),
+                layout,
+                Visibility::Encrypted,
+                &ModuleContext {
+                    elgamal_recipient: recipient,
+                    ..Default::default()
+                },
+                &ProverInputs::default(),
+            )
+            .unwrap();
+            let instances = res
+                .parse::<ElGamalResult>(ELGAMAL)
+                .unwrap()
+                .unwrap()
+                .ciphertexts;
+            c1_instances.extend(instances[1].clone());
+            ciphertexts.extend(instances[2].clone());
+            lens.push(layout.iter().map(|t| t.len()).sum());
+        }
+        assert_eq!(c1_instances.len(), 2 * recipient.num_c1_instances());
+
+        let decrypted = ElGamalGadget::decrypt_instances(
+            &c1_instances,
+            &ciphertexts,
+            &lens,
+            recipient,
+            &keypair,
+        )
+        .unwrap();
+        let expected = |values: &[u64]| values.iter().map(|v| Fr::from(*v)).collect::<Vec<_>>();
+        assert_eq!(
+            decrypted,
+            vec![expected(&[1, 2, 3, 4, 5]), expected(&[4, 5])]
+        );
+
+        // the public key in the instances isn't the other keypair's
+        let other = ElGamalKeypair::gen_random(&mut rng);
+        assert!(ElGamalGadget::decrypt_instances(
+            &c1_instances,
+            &ciphertexts,
+            &lens,
+            recipient,
+            &other
+        )
+        .is_err());
+    }
 }
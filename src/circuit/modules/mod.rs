@@ -4,17 +4,42 @@ pub mod poseidon;
 ///
 pub mod elgamal;
 
+///
+pub mod schnorr;
+
 ///
 pub mod planner;
+
+/// A single addition gate shared by the modules
+pub(crate) mod add_chip;
 use halo2_proofs::{
     circuit::Layouter,
     plonk::{ConstraintSystem, Error},
 };
 use halo2curves::ff::PrimeField;
 pub use planner::*;
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::tensor::{TensorType, ValTensor};
 
+/// Save the keys, signatures and trees the modules are run with to a .json file.
+pub fn save_json<T: Serialize + ?Sized>(
+    value: &T,
+    path: &std::path::PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let serialized = serde_json::to_string(value)?;
+    std::fs::write(path, serialized)?;
+    Ok(())
+}
+
+/// Load a value saved with [save_json] from a .json file.
+pub fn load_json<T: DeserializeOwned>(
+    path: &std::path::PathBuf,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let serialized = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&serialized)?)
+}
+
 /// Module trait used to extend ezkl functionality
 pub trait Module<F: PrimeField + TensorType + PartialOrd> {
     /// Config
@@ -53,6 +53,8 @@ pub struct ModuleLayouter<'a, F: Field, CS: Assignment<F> + 'a> {
     current_module: usize,
     /// num_constants
     total_constants: usize,
+    /// The number of rows of the longest table.
+    table_rows: usize,
 }
 
 impl<'a, F: Field, CS: Assignment<F> + 'a> fmt::Debug for ModuleLayouter<'a, F, CS> {
@@ -70,16 +72,128 @@ impl<'a, F: Field, CS: Assignment<F>> ModuleLayouter<'a, F, CS> {
         let ret = ModuleLayouter {
             cs,
             constants,
-            regions: HashMap::from([(0, HashMap::default()), (1, HashMap::default())]),
+            regions: HashMap::from([
+                (0, HashMap::default()),
+                (1, HashMap::default()),
+                (2, HashMap::default()),
+                (3, HashMap::default()),
+                (4, HashMap::default()),
+            ]),
             columns: HashMap::default(),
             region_idx: HashMap::default(),
             table_columns: vec![],
             current_module: 0,
             total_constants: 0,
+            table_rows: 0,
             _marker: PhantomData,
         };
         Ok(ret)
     }
+
+    /// The number of rows used by the regions, constants and tables laid out so far.
+    pub fn num_rows(&self) -> usize {
+        self.columns
+            .values()
+            .copied()
+            .chain([self.total_constants, self.table_rows])
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// An [`Assignment`] that discards all assignments, such that a layout can be run on a [`ModuleLayouter`] only to
+/// measure the rows it uses (see [`ModuleLayouter::num_rows`]).
+#[derive(Debug, Default)]
+pub struct DummyAssignment;
+
+impl SyncDeps for DummyAssignment {}
+
+impl<F: Field> Assignment<F> for DummyAssignment {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn annotate_column<A, AR>(&mut self, _: A, _: Column<Any>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+    }
+
+    fn exit_region(&mut self) {}
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        _: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        _: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_challenge(&self, _: Challenge) -> Value<F> {
+        Value::unknown()
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
 }
 
 impl<'a, F: Field, CS: Assignment<F> + 'a + SyncDeps> Layouter<F> for ModuleLayouter<'a, F, CS> {
@@ -101,7 +215,7 @@ impl<'a, F: Field, CS: Assignment<F> + 'a + SyncDeps> Layouter<F> for ModuleLayo
                 .unwrap_or_else(|| panic!("Invalid module name"))
                 .parse::<usize>()
                 .unwrap_or_else(|_| panic!("Invalid module name"));
-            assert!(self.regions.contains_key(&index), "module does not exist");
+            // entering a module that does not exist yet (e.g a user-defined module's) creates it
             self.current_module = index;
         }
 
@@ -228,6 +342,8 @@ impl<'a, F: Field, CS: Assignment<F> + 'a + SyncDeps> Layouter<F> for ModuleLayo
             }
         };
 
+        self.table_rows = cmp::max(self.table_rows, first_unused);
+
         // Record these columns so that we can prevent them from being used again.
         for column in default_and_assigned.keys() {
             self.table_columns.push(*column);
@@ -444,10 +560,11 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a + SyncDeps> RegionLayouter<F>
 mod tests {
     use halo2curves::pasta::vesta;
 
-    use super::ModulePlanner;
+    use super::{DummyAssignment, ModuleLayouter, ModulePlanner};
     use halo2_proofs::{
+        circuit::{Layouter, Value},
         dev::MockProver,
-        plonk::{Advice, Circuit, Column, Error},
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
     };
 
     #[test]
@@ -496,4 +613,51 @@ mod tests {
             Error::NotEnoughColumnsForConstants,
         ));
     }
+
+    #[test]
+    fn dummy_layout_counts_rows() {
+        let mut cs = ConstraintSystem::<vesta::Scalar>::default();
+        let a = cs.advice_column();
+        let b = cs.advice_column();
+
+        let mut assignment = DummyAssignment;
+        let mut layouter =
+            ModuleLayouter::<vesta::Scalar, _>::new(&mut assignment, vec![]).unwrap();
+        for (column, len) in [(a, 3), (b, 2), (a, 4)] {
+            layouter
+                .assign_region(
+                    || "region",
+                    |mut region| {
+                        for offset in 0..len {
+                            region.assign_advice(
+                                || "",
+                                column,
+                                offset,
+                                || Value::<vesta::Scalar>::unknown(),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+                .unwrap();
+        }
+        // regions stack on the columns they share
+        assert_eq!(layouter.num_rows(), 7);
+
+        // a new module starts back at the first row
+        layouter
+            .assign_region(|| "_new_module", |_| Ok(()))
+            .unwrap();
+        layouter
+            .assign_region(
+                || "region",
+                |mut region| {
+                    region
+                        .assign_advice(|| "", b, 0, || Value::<vesta::Scalar>::unknown())
+                        .map(|_| ())
+                },
+            )
+            .unwrap();
+        assert_eq!(layouter.num_rows(), 7);
+    }
 }
@@ -4,6 +4,10 @@ is already implemented in halo2_gadgets, there is no wrapper chip that makes it
 Thanks to https://github.com/summa-dev/summa-solvency/blob/master/src/chips/poseidon/hash.rs for the inspiration (and also helping us understand how to use this).
 */
 
+pub mod cipher;
+pub mod length_prefixed;
+pub mod membership;
+pub mod merkle;
 pub mod poseidon_params;
 pub mod spec;
 
@@ -181,12 +185,36 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
         input: &[ValTensor<Fp>],
         row_offset: Vec<usize>,
     ) -> Result<ValTensor<Fp>, Error> {
-        let (mut input_cells, zero_val) = self.layout_inputs(layouter, input)?;
+        let (input_cells, zero_val) = self.layout_inputs(layouter, input)?;
         // extract the values from the input cells
         let assigned_input: Tensor<ValType<Fp>> =
             input_cells.iter().map(|e| ValType::from(e.clone())).into();
-        let len = assigned_input.len();
 
+        let output = self.hash_tree(layouter, input_cells, &zero_val)?;
+
+        constrain_output(&self.config, layouter, &output, row_offset[0])?;
+
+        Ok(assigned_input.into())
+    }
+
+    ///
+    fn run(message: Vec<Fp>) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        Ok(vec![Self::hash_tree_native(message)])
+    }
+}
+
+impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, const L: usize>
+    PoseidonChip<S, WIDTH, RATE, L>
+{
+    /// Hashes the cells in blocks of L (zero padding the last block), repeating over the resulting
+    /// digests until a single cell remains
+    pub(crate) fn hash_tree(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        mut input_cells: Vec<AssignedCell<Fp, Fp>>,
+        zero_val: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let len = input_cells.len();
         let start_time = instant::Instant::now();
 
         // do the Tree dance baby
@@ -197,6 +225,7 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
                 .map(|(i, block)| {
                     let _start_time = instant::Instant::now();
 
+                    // you may need to 0 pad the inputs so they fit
                     let mut block = block.to_vec();
                     let remainder = block.len() % L;
 
@@ -204,18 +233,7 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
                         block.extend(vec![zero_val.clone(); L - remainder].into_iter());
                     }
 
-                    let pow5_chip = Pow5Chip::construct(self.config.pow5_config.clone());
-                    // initialize the hasher
-                    let hasher = Hash::<_, _, S, ConstantLength<L>, WIDTH, RATE>::init(
-                        pow5_chip,
-                        layouter.namespace(|| "block_hasher"),
-                    )?;
-
-                    // you may need to 0 pad the inputs so they fit
-                    let hash = hasher.hash(
-                        layouter.namespace(|| "hash"),
-                        block.to_vec().try_into().map_err(|_| Error::Synthesis)?,
-                    );
+                    let hash = hash_block::<S, WIDTH, RATE, L>(&self.config, layouter, block);
 
                     if i == 0 {
                         log::trace!("block (L={:?}) took: {:?}", L, _start_time.elapsed());
@@ -231,33 +249,11 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
         let duration = start_time.elapsed();
         log::trace!("layout (N={:?}) took: {:?}", len, duration);
 
-        let result = Tensor::from(input_cells.iter().map(|e| ValType::from(e.clone())));
-
-        let output = match result[0].clone() {
-            ValType::PrevAssigned(v) => v,
-            _ => panic!(),
-        };
-
-        layouter.assign_region(
-            || "constrain output",
-            |mut region| {
-                let expected_var = region.assign_advice_from_instance(
-                    || "pub input anchor",
-                    self.config.instance,
-                    row_offset[0],
-                    self.config.hash_inputs[0],
-                    0,
-                )?;
-
-                region.constrain_equal(output.cell(), expected_var.cell())
-            },
-        )?;
-
-        Ok(assigned_input.into())
+        input_cells.pop().ok_or(Error::Synthesis)
     }
 
-    ///
-    fn run(message: Vec<Fp>) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+    /// Native counterpart of [Self::hash_tree], returns the root of the tree (empty for an empty message)
+    pub(crate) fn hash_tree_native(message: Vec<Fp>) -> Vec<Fp> {
         let mut hash_inputs = message;
         let len = hash_inputs.len();
 
@@ -273,14 +269,7 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
                     if remainder != 0 {
                         block.extend(vec![Fp::ZERO; L - remainder].iter());
                     }
-                    halo2_gadgets::poseidon::primitives::Hash::<
-                        _,
-                        S,
-                        ConstantLength<L>,
-                        { WIDTH },
-                        { RATE },
-                    >::init()
-                    .hash(block.clone().try_into().unwrap())
+                    hash_block_native::<S, WIDTH, RATE, L>(block)
                 })
                 .collect();
 
@@ -290,10 +279,71 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
         let duration = start_time.elapsed();
         log::trace!("run (N={:?}) took: {:?}", len, duration);
 
-        Ok(vec![hash_inputs])
+        hash_inputs
     }
 }
 
+/// Hashes a block of N cells using the pow5 chip, returning the cell containing the digest
+pub(crate) fn hash_block<
+    S: Spec<Fp, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+    const N: usize,
+>(
+    config: &PoseidonConfig<WIDTH, RATE>,
+    layouter: &mut impl Layouter<Fp>,
+    block: Vec<AssignedCell<Fp, Fp>>,
+) -> Result<AssignedCell<Fp, Fp>, Error> {
+    let pow5_chip = Pow5Chip::construct(config.pow5_config.clone());
+    // initialize the hasher
+    let hasher = Hash::<_, _, S, ConstantLength<N>, WIDTH, RATE>::init(
+        pow5_chip,
+        layouter.namespace(|| "block_hasher"),
+    )?;
+
+    hasher.hash(
+        layouter.namespace(|| "hash"),
+        block.try_into().map_err(|_| Error::Synthesis)?,
+    )
+}
+
+/// Native counterpart of [hash_block]
+pub(crate) fn hash_block_native<
+    S: Spec<Fp, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+    const N: usize,
+>(
+    block: Vec<Fp>,
+) -> Fp {
+    halo2_gadgets::poseidon::primitives::Hash::<_, S, ConstantLength<N>, { WIDTH }, { RATE }>::init(
+    )
+    .hash(block.try_into().unwrap())
+}
+
+/// Constrains the cell containing a digest to be equal to the value at `row` of the instance column
+pub(crate) fn constrain_output<const WIDTH: usize, const RATE: usize>(
+    config: &PoseidonConfig<WIDTH, RATE>,
+    layouter: &mut impl Layouter<Fp>,
+    output: &AssignedCell<Fp, Fp>,
+    row: usize,
+) -> Result<(), Error> {
+    layouter.assign_region(
+        || "constrain output",
+        |mut region| {
+            let expected_var = region.assign_advice_from_instance(
+                || "pub input anchor",
+                config.instance,
+                row,
+                config.hash_inputs[0],
+                0,
+            )?;
+
+            region.constrain_equal(output.cell(), expected_var.cell())
+        },
+    )
+}
+
 #[allow(unused)]
 mod tests {
 
@@ -0,0 +1,444 @@
+/*
+A symmetric stream cipher built from the Poseidon hash. Much cheaper than ElGamal encryption (see
+[crate::circuit::modules::elgamal]) as it doesn't require any non-native arithmetic, at the cost of the prover and the
+recipient sharing the key.
+
+For a key k and a (public) nonce n, the i-th element of the message is encrypted as c_i = m_i + H2(H2(k, n), i).
+The key is committed to through H1(k), such that the recipient can check the ciphertexts were produced with the key
+they hold. Each layout uses the instances [H1(k), n, c_0, c_1, ...].
+*/
+
+use ark_std::rand::{CryptoRng, RngCore};
+use halo2_gadgets::poseidon::primitives::*;
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::{circuit::*, plonk::*};
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::modules::add_chip::{AddChip, AddConfig, AddInstruction};
+use crate::circuit::modules::{load_json, save_json, Module};
+use crate::tensor::{Tensor, ValTensor, ValType};
+
+use super::{constrain_output, hash_block, hash_block_native, PoseidonChip, PoseidonConfig};
+
+// Offsets of the public inputs of each layout.
+const KEY_H: usize = 0;
+const NONCE: usize = 1;
+const CIPHERTEXT: usize = 2;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+/// The key shared by the prover and the recipient of the ciphertexts, used to encrypt every symencrypted tensor
+pub struct PoseidonCipherKey(pub Fp);
+
+impl PoseidonCipherKey {
+    /// Generate a random key.
+    pub fn gen_random<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        Self(Fp::random(rng))
+    }
+
+    /// Save the key to a .json file.
+    pub fn save(&self, path: &std::path::PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        save_json(self, path)
+    }
+
+    /// Load a key from a .json file.
+    pub fn load(path: &std::path::PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        load_json(path)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The variables used by the Poseidon cipher
+pub struct PoseidonCipherVariables {
+    /// The shared key.
+    pub key: Fp,
+    /// The nonce, which is a public input.
+    pub nonce: Fp,
+}
+
+impl PoseidonCipherVariables {
+    /// Generate a random key and nonce.
+    pub fn gen_random<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        Self::gen_random_for_key(rng, Fp::random(&mut *rng))
+    }
+
+    /// Generate a random nonce for a given key.
+    pub fn gen_random_for_key<R: CryptoRng + RngCore>(rng: &mut R, key: Fp) -> Self {
+        Self {
+            key,
+            nonce: Fp::random(rng),
+        }
+    }
+
+    /// The variables used to encrypt the `i`-th tensor of a module layout, such that the tensors sharing a key
+    /// are never encrypted with the same keystream.
+    pub fn for_tensor(&self, i: usize) -> Self {
+        Self {
+            key: self.key,
+            nonce: self.nonce + Fp::from(i as u64),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Configuration of the PoseidonCipherChip
+pub struct PoseidonCipherConfig<const WIDTH: usize, const RATE: usize> {
+    /// The underlying Poseidon config
+    pub poseidon: PoseidonConfig<WIDTH, RATE>,
+    add: AddConfig,
+}
+
+/// PoseidonCipherChip encrypts tensors with a Poseidon keystream under a key committed to by its hash
+#[derive(Debug, Clone)]
+pub struct PoseidonCipherChip<
+    S: Spec<Fp, WIDTH, RATE> + Sync,
+    const WIDTH: usize,
+    const RATE: usize,
+> {
+    chip: PoseidonChip<S, WIDTH, RATE, 2>,
+    add: AddChip,
+}
+
+impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize>
+    PoseidonCipherChip<S, WIDTH, RATE>
+{
+    /// Hash the key to be used as a public input.
+    pub fn hash_key(key: Fp) -> Fp {
+        hash_block_native::<S, WIDTH, RATE, 1>(vec![key])
+    }
+
+    /// The first `len` elements of the keystream for a key and nonce.
+    fn keystream(key: Fp, nonce: Fp, len: usize) -> Vec<Fp> {
+        let seed = hash_block_native::<S, WIDTH, RATE, 2>(vec![key, nonce]);
+        (0..len)
+            .map(|i| hash_block_native::<S, WIDTH, RATE, 2>(vec![seed, Fp::from(i as u64)]))
+            .collect()
+    }
+
+    /// Encrypt a message.
+    pub fn encrypt(key: Fp, nonce: Fp, msg: Vec<Fp>) -> Vec<Fp> {
+        let keystream = Self::keystream(key, nonce, msg.len());
+        msg.iter().zip(keystream).map(|(m, k)| *m + k).collect()
+    }
+
+    /// Decrypt ciphertexts produced with `key` and `nonce`.
+    pub fn decrypt(key: Fp, nonce: Fp, ciphertexts: &[Fp]) -> Vec<Fp> {
+        let keystream = Self::keystream(key, nonce, ciphertexts.len());
+        ciphertexts
+            .iter()
+            .zip(keystream)
+            .map(|(c, k)| *c - k)
+            .collect()
+    }
+
+    /// Decrypt the instances of a single layout, i.e the key hash and nonce followed by the ciphertexts.
+    /// Errors if the instances weren't produced with `key`.
+    pub fn decrypt_instances(
+        key: Fp,
+        instances: &[Fp],
+    ) -> Result<Vec<Fp>, Box<dyn std::error::Error>> {
+        if instances.len() < CIPHERTEXT {
+            return Err("missing the key hash and nonce instances".into());
+        }
+        if instances[KEY_H] != Self::hash_key(key) {
+            return Err("the ciphertexts were encrypted under a different key".into());
+        }
+        Ok(Self::decrypt(
+            key,
+            instances[NONCE],
+            &instances[CIPHERTEXT..],
+        ))
+    }
+
+    /// Decrypt the instances of several tensors laid out one after the other (e.g the cipher instance column of a
+    /// proof), where `lens` is the number of elements of each tensor. Errors if any tensor wasn't encrypted with `key`
+    /// or if the instances don't hold exactly these tensors.
+    pub fn decrypt_tensors(
+        key: Fp,
+        instances: &[Fp],
+        lens: &[usize],
+    ) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        let expected = lens.iter().map(|len| CIPHERTEXT + len).sum::<usize>();
+        if instances.len() != expected {
+            return Err(format!(
+                "expected {} cipher instances for tensors of lengths {:?}, got {}",
+                expected,
+                lens,
+                instances.len()
+            )
+            .into());
+        }
+        let mut offset = 0;
+        let mut decrypted = vec![];
+        for (i, len) in lens.iter().enumerate() {
+            let end = offset + CIPHERTEXT + len;
+            decrypted.push(
+                Self::decrypt_instances(key, &instances[offset..end])
+                    .map_err(|e| format!("tensor {}: {}", i, e))?,
+            );
+            offset = end;
+        }
+        Ok(decrypted)
+    }
+}
+
+impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize> Module<Fp>
+    for PoseidonCipherChip<S, WIDTH, RATE>
+{
+    type Config = PoseidonCipherConfig<WIDTH, RATE>;
+    type InputAssignments = (Vec<AssignedCell<Fp, Fp>>, AssignedCell<Fp, Fp>);
+    type RunInputs = (Vec<Fp>, PoseidonCipherVariables);
+
+    fn name(&self) -> &'static str {
+        "PoseidonCipher"
+    }
+
+    fn instance_increment_input(&self, var_len: Vec<usize>) -> Vec<usize> {
+        // the key hash and nonce, followed by one ciphertext per message element
+        vec![var_len[0] + CIPHERTEXT]
+    }
+
+    fn instance_increment_module(&self) -> Vec<usize> {
+        vec![0]
+    }
+
+    /// Constructs a new PoseidonCipherChip
+    fn new(config: Self::Config) -> Self {
+        Self {
+            chip: PoseidonChip::new(config.poseidon),
+            add: AddChip::construct(config.add),
+        }
+    }
+
+    /// Configuration of the PoseidonCipherChip, that of the underlying PoseidonChip and an addition gate
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let poseidon = PoseidonChip::<S, WIDTH, RATE, 2>::configure(meta);
+
+        let sum = meta.advice_column();
+        meta.enable_equality(sum);
+        let add = AddChip::configure(meta, poseidon.hash_inputs[0], poseidon.hash_inputs[1], sum);
+
+        PoseidonCipherConfig { poseidon, add }
+    }
+
+    /// Assigns the message and the key
+    fn layout_inputs(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        inputs: &[ValTensor<Fp>],
+    ) -> Result<Self::InputAssignments, Error> {
+        assert_eq!(inputs.len(), 2);
+        let (msg_var, _) = self.chip.layout_inputs(layouter, &inputs[..1])?;
+
+        let key = match inputs[1].get_inner_tensor().map_err(|_| Error::Synthesis)?[0] {
+            ValType::Value(v) => v,
+            _ => panic!("wrong input type"),
+        };
+        let key_var = layouter.assign_region(
+            || "key",
+            |mut region| region.assign_advice(|| "key", self.chip.config.hash_inputs[0], 0, || key),
+        )?;
+
+        Ok((msg_var, key_var))
+    }
+
+    /// Encrypts the message with the keystream derived from the key and the nonce at `row_offsets`, constraining the
+    /// key hash and ciphertexts to equal the instances
+    fn layout(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        inputs: &[ValTensor<Fp>],
+        row_offsets: Vec<usize>,
+    ) -> Result<ValTensor<Fp>, Error> {
+        let config = &self.chip.config;
+        let (msg_var, key_var) = self.layout_inputs(layouter, inputs)?;
+
+        let start_time = instant::Instant::now();
+
+        let key_hash = hash_block::<S, WIDTH, RATE, 1>(config, layouter, vec![key_var.clone()])?;
+        constrain_output(config, layouter, &key_hash, KEY_H + row_offsets[0])?;
+
+        let nonce_var = layouter.assign_region(
+            || "nonce",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "nonce",
+                    config.instance,
+                    NONCE + row_offsets[0],
+                    config.hash_inputs[0],
+                    0,
+                )
+            },
+        )?;
+        let seed = hash_block::<S, WIDTH, RATE, 2>(config, layouter, vec![key_var, nonce_var])?;
+
+        for (i, m) in msg_var.iter().enumerate() {
+            let counter = layouter.assign_region(
+                || "counter",
+                |mut region| {
+                    region.assign_advice_from_constant(
+                        || "counter",
+                        config.hash_inputs[0],
+                        0,
+                        Fp::from(i as u64),
+                    )
+                },
+            )?;
+            let keystream =
+                hash_block::<S, WIDTH, RATE, 2>(config, layouter, vec![seed.clone(), counter])?;
+            let c = self
+                .add
+                .add(layouter.namespace(|| "encrypt"), m, &keystream)?;
+            layouter.constrain_instance(
+                c.cell(),
+                config.instance,
+                CIPHERTEXT + i + row_offsets[0],
+            )?;
+        }
+
+        log::trace!(
+            "cipher layout (N={:?}) took: {:?}",
+            msg_var.len(),
+            start_time.elapsed()
+        );
+
+        let assigned_input: Tensor<ValType<Fp>> =
+            msg_var.iter().map(|e| ValType::from(e.clone())).into();
+
+        Ok(assigned_input.into())
+    }
+
+    ///
+    fn run(input: Self::RunInputs) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        let (msg, var) = input;
+        let mut instances = vec![Self::hash_key(var.key), var.nonce];
+        instances.extend(Self::encrypt(var.key, var.nonce, msg));
+        Ok(vec![instances])
+    }
+}
+
+#[allow(unused)]
+mod tests {
+
+    use super::super::{
+        spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH},
+        NUM_INSTANCE_COLUMNS,
+    };
+    use super::*;
+
+    use ark_std::test_rng;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Circuit, ConstraintSystem},
+    };
+
+    const WIDTH: usize = POSEIDON_WIDTH;
+    const RATE: usize = POSEIDON_RATE;
+
+    type CipherChip = PoseidonCipherChip<PoseidonSpec, WIDTH, RATE>;
+
+    struct CipherCircuit {
+        message: ValTensor<Fp>,
+        variables: PoseidonCipherVariables,
+    }
+
+    impl Circuit<Fp> for CipherCircuit {
+        type Config = PoseidonCipherConfig<WIDTH, RATE>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            let empty_val: Vec<ValType<Fp>> = vec![Value::<Fp>::unknown().into()];
+            let message: Tensor<ValType<Fp>> = empty_val.into_iter().into();
+
+            Self {
+                message: message.into(),
+                variables: PoseidonCipherVariables::default(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            CipherChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = CipherChip::new(config);
+            let key: Tensor<ValType<Fp>> =
+                Tensor::new(Some(&[Value::known(self.variables.key).into()]), &[1]).unwrap();
+            chip.layout(
+                &mut layouter,
+                &[self.message.clone(), key.into()],
+                vec![0; NUM_INSTANCE_COLUMNS],
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poseidon_cipher_decrypt() {
+        let mut rng = test_rng();
+
+        let var = PoseidonCipherVariables::gen_random(&mut rng);
+        let msg = (0..5).map(|_| Fp::random(&mut rng)).collect::<Vec<_>>();
+
+        let instances = CipherChip::run((msg.clone(), var.clone())).unwrap();
+        assert_eq!(instances[0].len(), msg.len() + 2);
+        assert_eq!(
+            CipherChip::decrypt_instances(var.key, &instances[0]).unwrap(),
+            msg
+        );
+        // a different key is rejected
+        assert!(CipherChip::decrypt_instances(Fp::random(&mut rng), &instances[0]).is_err());
+        // and a different nonce gives a different keystream
+        assert_ne!(
+            CipherChip::run((msg.clone(), var.for_tensor(1))).unwrap()[0][CIPHERTEXT..],
+            instances[0][CIPHERTEXT..]
+        );
+    }
+
+    #[test]
+    fn poseidon_cipher_decrypt_tensors() {
+        let mut rng = test_rng();
+
+        let key = PoseidonCipherKey::gen_random(&mut rng);
+        let var = PoseidonCipherVariables::gen_random_for_key(&mut rng, key.0);
+        let msgs = [3, 1]
+            .iter()
+            .map(|len| (0..*len).map(|_| Fp::random(&mut rng)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut instances = vec![];
+        for (i, msg) in msgs.iter().enumerate() {
+            instances.extend(CipherChip::run((msg.clone(), var.for_tensor(i))).unwrap()[0].clone());
+        }
+        assert_eq!(
+            CipherChip::decrypt_tensors(key.0, &instances, &[3, 1]).unwrap(),
+            msgs
+        );
+        // the lengths must account for every instance
+        assert!(CipherChip::decrypt_tensors(key.0, &instances, &[3]).is_err());
+        assert!(CipherChip::decrypt_tensors(key.0, &instances, &[2, 2]).is_err());
+    }
+
+    #[test]
+    fn poseidon_cipher_circuit() {
+        let mut rng = test_rng();
+
+        let var = PoseidonCipherVariables::gen_random(&mut rng);
+        let msg = (0..5).map(|_| Fp::random(&mut rng)).collect::<Vec<_>>();
+        let instances = CipherChip::run((msg.clone(), var.clone())).unwrap();
+
+        let message: Tensor<ValType<Fp>> = msg.into_iter().map(|m| Value::known(m).into()).into();
+
+        let circuit = CipherCircuit {
+            message: message.into(),
+            variables: var,
+        };
+        let prover = halo2_proofs::dev::MockProver::run(11, &circuit, instances).unwrap();
+        assert_eq!(prover.verify(), Ok(()))
+    }
+}
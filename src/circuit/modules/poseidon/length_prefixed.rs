@@ -0,0 +1,208 @@
+/*
+A length-prefixed variant of the flat [super::PoseidonChip] tree. The flat tree zero pads the last block of each
+level, so that messages which only differ by trailing zeros can hash to the same digest (e.g `[a, 0, 0]` and
+`[a, 0, 0, 0]` for L = 2). Prefixing the message with its length (fixed in the circuit as a constant) rules out such
+padding collisions.
+*/
+
+use halo2_gadgets::poseidon::primitives::*;
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::{circuit::*, plonk::*};
+
+use crate::circuit::modules::Module;
+use crate::tensor::{Tensor, ValTensor, ValType};
+
+use super::{constrain_output, InputAssignments, PoseidonChip, PoseidonConfig};
+
+/// PoseidonLengthPrefixedChip hashes the length of a tensor followed by its elements using the flat Poseidon tree
+#[derive(Debug, Clone)]
+pub struct PoseidonLengthPrefixedChip<
+    S: Spec<Fp, WIDTH, RATE> + Sync,
+    const WIDTH: usize,
+    const RATE: usize,
+    const L: usize,
+> {
+    chip: PoseidonChip<S, WIDTH, RATE, L>,
+}
+
+impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, const L: usize>
+    Module<Fp> for PoseidonLengthPrefixedChip<S, WIDTH, RATE, L>
+{
+    type Config = PoseidonConfig<WIDTH, RATE>;
+    type InputAssignments = InputAssignments;
+    type RunInputs = Vec<Fp>;
+
+    fn name(&self) -> &'static str {
+        "PoseidonLengthPrefixed"
+    }
+
+    fn instance_increment_input(&self, _: Vec<usize>) -> Vec<usize> {
+        vec![1]
+    }
+
+    fn instance_increment_module(&self) -> Vec<usize> {
+        vec![0]
+    }
+
+    /// Constructs a new PoseidonLengthPrefixedChip
+    fn new(config: Self::Config) -> Self {
+        Self {
+            chip: PoseidonChip::new(config),
+        }
+    }
+
+    /// Configuration of the PoseidonLengthPrefixedChip, which is that of the underlying PoseidonChip
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        PoseidonChip::<S, WIDTH, RATE, L>::configure(meta)
+    }
+
+    fn layout_inputs(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        message: &[ValTensor<Fp>],
+    ) -> Result<Self::InputAssignments, Error> {
+        self.chip.layout_inputs(layouter, message)
+    }
+
+    /// Prepends a constant cell holding the length of the message to the input cells and hashes the
+    /// result with the flat tree, constraining the digest to equal the instance at `row_offset`
+    fn layout(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        input: &[ValTensor<Fp>],
+        row_offset: Vec<usize>,
+    ) -> Result<ValTensor<Fp>, Error> {
+        let (input_cells, zero_val) = self.layout_inputs(layouter, input)?;
+        // extract the values from the input cells
+        let assigned_input: Tensor<ValType<Fp>> =
+            input_cells.iter().map(|e| ValType::from(e.clone())).into();
+
+        let len = input_cells.len();
+        let prefix = layouter.assign_region(
+            || "length prefix",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "length",
+                    self.chip.config.hash_inputs[0],
+                    0,
+                    Fp::from(len as u64),
+                )
+            },
+        )?;
+
+        let prefixed = std::iter::once(prefix).chain(input_cells).collect();
+        let output = self.chip.hash_tree(layouter, prefixed, &zero_val)?;
+
+        constrain_output(&self.chip.config, layouter, &output, row_offset[0])?;
+
+        Ok(assigned_input.into())
+    }
+
+    ///
+    fn run(message: Vec<Fp>) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        let prefixed = std::iter::once(Fp::from(message.len() as u64))
+            .chain(message)
+            .collect();
+        Ok(vec![PoseidonChip::<S, WIDTH, RATE, L>::hash_tree_native(
+            prefixed,
+        )])
+    }
+}
+
+#[allow(unused)]
+mod tests {
+
+    use super::super::{
+        spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH},
+        NUM_INSTANCE_COLUMNS,
+    };
+    use super::*;
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2curves::ff::Field;
+
+    const WIDTH: usize = POSEIDON_WIDTH;
+    const RATE: usize = POSEIDON_RATE;
+
+    struct LengthPrefixedCircuit<const L: usize> {
+        message: ValTensor<Fp>,
+    }
+
+    impl<const L: usize> Circuit<Fp> for LengthPrefixedCircuit<L> {
+        type Config = PoseidonConfig<WIDTH, RATE>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            let empty_val: Vec<ValType<Fp>> = vec![Value::<Fp>::unknown().into()];
+            let message: Tensor<ValType<Fp>> = empty_val.into_iter().into();
+
+            Self {
+                message: message.into(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> PoseidonConfig<WIDTH, RATE> {
+            PoseidonLengthPrefixedChip::<PoseidonSpec, WIDTH, RATE, L>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: PoseidonConfig<WIDTH, RATE>,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip: PoseidonLengthPrefixedChip<PoseidonSpec, WIDTH, RATE, L> =
+                PoseidonLengthPrefixedChip::new(config);
+            chip.layout(
+                &mut layouter,
+                &[self.message.clone()],
+                vec![0; NUM_INSTANCE_COLUMNS],
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poseidon_length_prefixed_hash() {
+        let rng = rand::rngs::OsRng;
+
+        let message = [Fp::random(rng), Fp::random(rng), Fp::random(rng)];
+        let output =
+            PoseidonLengthPrefixedChip::<PoseidonSpec, WIDTH, RATE, 2>::run(message.to_vec())
+                .unwrap();
+
+        let message: Tensor<ValType<Fp>> =
+            message.into_iter().map(|m| Value::known(m).into()).into();
+
+        let k = 10;
+        let circuit = LengthPrefixedCircuit::<2> {
+            message: message.into(),
+        };
+        let prover = halo2_proofs::dev::MockProver::run(k, &circuit, output).unwrap();
+        assert_eq!(prover.verify(), Ok(()))
+    }
+
+    #[test]
+    fn poseidon_length_prefixed_no_padding_collision() {
+        let rng = rand::rngs::OsRng;
+
+        let a = Fp::random(rng);
+        // the flat tree zero pads [a, 0, 0] to [a, 0, 0, 0]...
+        let flat = |m: Vec<Fp>| PoseidonChip::<PoseidonSpec, WIDTH, RATE, 2>::run(m).unwrap();
+        assert_eq!(
+            flat(vec![a, Fp::ZERO, Fp::ZERO]),
+            flat(vec![a, Fp::ZERO, Fp::ZERO, Fp::ZERO])
+        );
+        // ... whereas the length prefix separates the two
+        let prefixed = |m: Vec<Fp>| {
+            PoseidonLengthPrefixedChip::<PoseidonSpec, WIDTH, RATE, 2>::run(m).unwrap()
+        };
+        assert_ne!(
+            prefixed(vec![a, Fp::ZERO, Fp::ZERO]),
+            prefixed(vec![a, Fp::ZERO, Fp::ZERO, Fp::ZERO])
+        );
+    }
+}
@@ -0,0 +1,417 @@
+/*
+Proves that a tensor is a row of a committed dataset. The dataset is committed to through the root of a binary
+Poseidon Merkle tree (see [super::merkle::PoseidonMerkleChip]) whose leaves are the flat Poseidon digests of its rows
+(see [super::PoseidonChip]). The tensor is hashed the same way and its authentication path is hashed up to the root,
+which is the only public input: the position of the row in the dataset, and the siblings along its path, stay private.
+*/
+
+use halo2_gadgets::poseidon::primitives::*;
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::{circuit::*, plonk::*};
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::modules::{load_json, save_json, Module};
+use crate::tensor::{Tensor, ValTensor, ValType};
+
+use super::merkle::PoseidonMerkleChip;
+use super::{
+    constrain_output, hash_block, hash_block_native, InputAssignments, PoseidonChip, PoseidonConfig,
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The authentication path of a leaf of a Merkle tree
+pub struct MerklePath {
+    /// The index of the leaf, whose bits tell whether the node at each level is a left or right child.
+    pub index: usize,
+    /// The sibling of the node at each level, from the leaves up.
+    pub siblings: Vec<Fp>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The Merkle tree committing to a dataset, with every level from the leaves to the root
+pub struct DatasetTree {
+    /// The levels of the tree, from the (hashed) leaves to the root.
+    pub levels: Vec<Vec<Fp>>,
+}
+
+impl DatasetTree {
+    /// The root of the tree, i.e the public input of each membership proof.
+    pub fn root(&self) -> Fp {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .cloned()
+            .unwrap_or(Fp::ZERO)
+    }
+
+    /// The length of the authentication paths of the tree.
+    pub fn depth(&self) -> usize {
+        self.levels.len().saturating_sub(1)
+    }
+
+    /// The authentication path of a leaf, if it is in the tree.
+    pub fn path(&self, leaf: Fp) -> Option<MerklePath> {
+        let index = self.levels.first()?.iter().position(|x| *x == leaf)?;
+        let siblings = self.levels[..self.depth()]
+            .iter()
+            .enumerate()
+            .map(|(l, level)| level[(index >> l) ^ 1])
+            .collect();
+        Some(MerklePath { index, siblings })
+    }
+
+    /// Save the tree to a .json file.
+    pub fn save(&self, path: &std::path::PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        save_json(self, path)
+    }
+
+    /// Load a tree from a .json file.
+    pub fn load(path: &std::path::PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        load_json(path)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The variables used by the PoseidonMembershipChip
+pub struct PoseidonMembershipVariables {
+    /// The depth of the dataset tree, which fixes the shape of the circuit.
+    pub depth: usize,
+    /// The authentication path of each tensor, in order.
+    pub paths: Vec<MerklePath>,
+}
+
+#[derive(Debug, Clone)]
+/// Configuration of the PoseidonMembershipChip
+pub struct PoseidonMembershipConfig<const WIDTH: usize, const RATE: usize> {
+    /// The underlying Poseidon config
+    pub poseidon: PoseidonConfig<WIDTH, RATE>,
+    bit: Column<Advice>,
+    q_swap: Selector,
+}
+
+/// PoseidonMembershipChip proves that tensors are rows of a dataset committed to by a public Merkle root
+#[derive(Debug, Clone)]
+pub struct PoseidonMembershipChip<
+    S: Spec<Fp, WIDTH, RATE> + Sync,
+    const WIDTH: usize,
+    const RATE: usize,
+    const L: usize,
+> {
+    chip: PoseidonChip<S, WIDTH, RATE, L>,
+    bit: Column<Advice>,
+    q_swap: Selector,
+    variables: Option<PoseidonMembershipVariables>,
+}
+
+impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, const L: usize>
+    PoseidonMembershipChip<S, WIDTH, RATE, L>
+{
+    /// Load the variables into the chip.
+    pub fn load_variables(&mut self, variables: PoseidonMembershipVariables) {
+        self.variables = Some(variables);
+    }
+
+    /// The flat Poseidon digest of a row.
+    fn digest(row: Vec<Fp>) -> Fp {
+        PoseidonChip::<S, WIDTH, RATE, L>::hash_tree_native(row)
+            .pop()
+            .unwrap_or(Fp::ZERO)
+    }
+
+    /// The leaf a row is committed to through.
+    pub fn leaf(row: Vec<Fp>) -> Fp {
+        hash_block_native::<S, WIDTH, RATE, 1>(vec![Self::digest(row)])
+    }
+
+    /// Builds the tree committing to the rows of a dataset.
+    pub fn tree(rows: Vec<Vec<Fp>>) -> DatasetTree {
+        let digests = rows.into_iter().map(Self::digest).collect();
+        DatasetTree {
+            levels: PoseidonMerkleChip::<S, WIDTH, RATE>::tree(digests),
+        }
+    }
+
+    /// The root obtained by hashing the authentication path of a row.
+    pub fn root_from_path(row: Vec<Fp>, path: &MerklePath) -> Fp {
+        path.siblings
+            .iter()
+            .enumerate()
+            .fold(Self::leaf(row), |node, (l, sibling)| {
+                let pair = if (path.index >> l) & 1 == 1 {
+                    vec![*sibling, node]
+                } else {
+                    vec![node, *sibling]
+                };
+                hash_block_native::<S, WIDTH, RATE, 2>(pair)
+            })
+    }
+
+    /// Orders a node and its sibling into (left, right) depending on the (boolean) bit of the node's index
+    fn swap(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        node: &AssignedCell<Fp, Fp>,
+        sibling: Value<Fp>,
+        bit: Value<Fp>,
+    ) -> Result<(AssignedCell<Fp, Fp>, AssignedCell<Fp, Fp>), Error> {
+        let config = &self.chip.config;
+        layouter.assign_region(
+            || "swap",
+            |mut region| {
+                self.q_swap.enable(&mut region, 0)?;
+                node.copy_advice(|| "node", &mut region, config.hash_inputs[0], 0)?;
+                region.assign_advice(|| "sibling", config.hash_inputs[1], 0, || sibling)?;
+                region.assign_advice(|| "bit", self.bit, 0, || bit)?;
+
+                let left = node
+                    .value()
+                    .zip(sibling)
+                    .zip(bit)
+                    .map(|((n, s), b)| *n + b * (s - *n));
+                let right = node.value().zip(sibling).map(|(n, s)| *n + s) - left;
+
+                let left = region.assign_advice(|| "left", config.hash_inputs[0], 1, || left)?;
+                let right = region.assign_advice(|| "right", config.hash_inputs[1], 1, || right)?;
+                Ok((left, right))
+            },
+        )
+    }
+}
+
+impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, const L: usize>
+    Module<Fp> for PoseidonMembershipChip<S, WIDTH, RATE, L>
+{
+    type Config = PoseidonMembershipConfig<WIDTH, RATE>;
+    type InputAssignments = InputAssignments;
+    type RunInputs = (Vec<Fp>, MerklePath);
+
+    fn name(&self) -> &'static str {
+        "PoseidonMembership"
+    }
+
+    fn instance_increment_input(&self, _: Vec<usize>) -> Vec<usize> {
+        vec![1]
+    }
+
+    fn instance_increment_module(&self) -> Vec<usize> {
+        vec![0]
+    }
+
+    /// Constructs a new PoseidonMembershipChip
+    fn new(config: Self::Config) -> Self {
+        Self {
+            chip: PoseidonChip::new(config.poseidon),
+            bit: config.bit,
+            q_swap: config.q_swap,
+            variables: None,
+        }
+    }
+
+    /// Configuration of the underlying PoseidonChip, along with a gate ordering a node and its sibling
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let poseidon = PoseidonChip::<S, WIDTH, RATE, L>::configure(meta);
+
+        let bit = meta.advice_column();
+        let q_swap = meta.selector();
+        let (a, b) = (poseidon.hash_inputs[0], poseidon.hash_inputs[1]);
+        meta.create_gate("Conditional swap", |meta| {
+            let q_swap = meta.query_selector(q_swap);
+            let node = meta.query_advice(a, Rotation::cur());
+            let sibling = meta.query_advice(b, Rotation::cur());
+            let bit = meta.query_advice(bit, Rotation::cur());
+            let left = meta.query_advice(a, Rotation::next());
+            let right = meta.query_advice(b, Rotation::next());
+            let one = Expression::Constant(Fp::ONE);
+
+            Constraints::with_selector(
+                q_swap,
+                [
+                    ("bit is boolean", bit.clone() * (one - bit.clone())),
+                    (
+                        "left = bit ? sibling : node",
+                        left.clone() - node.clone() - bit * (sibling.clone() - node.clone()),
+                    ),
+                    ("right is the other one", left + right - node - sibling),
+                ],
+            )
+        });
+
+        PoseidonMembershipConfig {
+            poseidon,
+            bit,
+            q_swap,
+        }
+    }
+
+    fn layout_inputs(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        message: &[ValTensor<Fp>],
+    ) -> Result<Self::InputAssignments, Error> {
+        self.chip.layout_inputs(layouter, message)
+    }
+
+    /// Hashes the input cells into a leaf and then hashes the authentication path at `row_offset` up to the root,
+    /// which is constrained to equal the instance at `row_offset`
+    fn layout(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        input: &[ValTensor<Fp>],
+        row_offset: Vec<usize>,
+    ) -> Result<ValTensor<Fp>, Error> {
+        let variables = self.variables.clone().unwrap_or_default();
+        let path = variables
+            .paths
+            .get(row_offset[0])
+            .cloned()
+            .unwrap_or_default();
+
+        let (input_cells, zero_val) = self.layout_inputs(layouter, input)?;
+        // extract the values from the input cells
+        let assigned_input: Tensor<ValType<Fp>> =
+            input_cells.iter().map(|e| ValType::from(e.clone())).into();
+
+        let start_time = instant::Instant::now();
+
+        let config = &self.chip.config;
+        let digest = self.chip.hash_tree(layouter, input_cells, &zero_val)?;
+        let mut node = hash_block::<S, WIDTH, RATE, 1>(config, layouter, vec![digest])?;
+
+        // the shape of the circuit only depends on the depth of the tree
+        for l in 0..variables.depth {
+            let sibling = Value::known(path.siblings.get(l).cloned().unwrap_or(Fp::ZERO));
+            let bit = Value::known(Fp::from(((path.index >> l) & 1) as u64));
+            let (left, right) = self.swap(layouter, &node, sibling, bit)?;
+            node = hash_block::<S, WIDTH, RATE, 2>(config, layouter, vec![left, right])?;
+        }
+
+        log::trace!(
+            "membership layout (N={:?}, depth={:?}) took: {:?}",
+            assigned_input.len(),
+            variables.depth,
+            start_time.elapsed()
+        );
+
+        constrain_output(config, layouter, &node, row_offset[0])?;
+
+        Ok(assigned_input.into())
+    }
+
+    ///
+    fn run(input: Self::RunInputs) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        let (row, path) = input;
+        Ok(vec![vec![Self::root_from_path(row, &path)]])
+    }
+}
+
+#[allow(unused)]
+mod tests {
+
+    use super::super::{
+        spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH},
+        NUM_INSTANCE_COLUMNS,
+    };
+    use super::*;
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2curves::ff::Field;
+
+    const WIDTH: usize = POSEIDON_WIDTH;
+    const RATE: usize = POSEIDON_RATE;
+    const L: usize = 3;
+
+    type MembershipChip = PoseidonMembershipChip<PoseidonSpec, WIDTH, RATE, L>;
+
+    struct MembershipCircuit {
+        message: ValTensor<Fp>,
+        variables: PoseidonMembershipVariables,
+    }
+
+    impl Circuit<Fp> for MembershipCircuit {
+        type Config = PoseidonMembershipConfig<WIDTH, RATE>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            let empty_val: Vec<ValType<Fp>> = vec![Value::<Fp>::unknown().into()];
+            let message: Tensor<ValType<Fp>> = empty_val.into_iter().into();
+
+            Self {
+                message: message.into(),
+                variables: PoseidonMembershipVariables::default(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            MembershipChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let mut chip = MembershipChip::new(config);
+            chip.load_variables(self.variables.clone());
+            chip.layout(
+                &mut layouter,
+                &[self.message.clone()],
+                vec![0; NUM_INSTANCE_COLUMNS],
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poseidon_membership_path() {
+        let rng = rand::rngs::OsRng;
+
+        let rows = (0..5)
+            .map(|_| (0..4).map(|_| Fp::random(rng)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let tree = MembershipChip::tree(rows.clone());
+        // 5 rows are padded to 8 leaves
+        assert_eq!(tree.depth(), 3);
+
+        for row in rows {
+            let path = tree.path(MembershipChip::leaf(row.clone())).unwrap();
+            assert_eq!(MembershipChip::root_from_path(row, &path), tree.root());
+        }
+
+        let outsider = (0..4).map(|_| Fp::random(rng)).collect::<Vec<_>>();
+        assert!(tree.path(MembershipChip::leaf(outsider)).is_none());
+    }
+
+    #[test]
+    fn poseidon_membership_circuit() {
+        let rng = rand::rngs::OsRng;
+
+        let rows = (0..6)
+            .map(|_| (0..4).map(|_| Fp::random(rng)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let tree = MembershipChip::tree(rows.clone());
+
+        let row = rows[3].clone();
+        let path = tree.path(MembershipChip::leaf(row.clone())).unwrap();
+        let output = MembershipChip::run((row.clone(), path.clone())).unwrap();
+        assert_eq!(output[0][0], tree.root());
+
+        let message: Tensor<ValType<Fp>> = row.into_iter().map(|m| Value::known(m).into()).into();
+
+        let k = 11;
+        let circuit = MembershipCircuit {
+            message: message.into(),
+            variables: PoseidonMembershipVariables {
+                depth: tree.depth(),
+                paths: vec![path],
+            },
+        };
+        let prover = halo2_proofs::dev::MockProver::run(k, &circuit, output).unwrap();
+        assert_eq!(prover.verify(), Ok(()))
+    }
+}
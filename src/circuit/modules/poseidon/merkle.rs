@@ -0,0 +1,227 @@
+/*
+A binary Poseidon Merkle tree over the elements of a tensor. Unlike the flat [super::PoseidonChip] tree, the root
+can later be opened at an individual element (e.g a single weight) by supplying its authentication path.
+Leaves and internal nodes are hashed with different (constant) lengths, such that an internal node can never be
+passed off as a leaf.
+*/
+
+use halo2_gadgets::poseidon::primitives::*;
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::{circuit::*, plonk::*};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+
+use crate::circuit::modules::Module;
+use crate::tensor::{Tensor, ValTensor, ValType};
+
+use super::{
+    constrain_output, hash_block, hash_block_native, InputAssignments, PoseidonChip, PoseidonConfig,
+};
+
+/// PoseidonMerkleChip commits to a tensor through the root of a binary Poseidon Merkle tree over its elements
+#[derive(Debug, Clone)]
+pub struct PoseidonMerkleChip<
+    S: Spec<Fp, WIDTH, RATE> + Sync,
+    const WIDTH: usize,
+    const RATE: usize,
+> {
+    chip: PoseidonChip<S, WIDTH, RATE, 2>,
+}
+
+impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize>
+    PoseidonMerkleChip<S, WIDTH, RATE>
+{
+    /// Computes every level of the tree, from the (hashed) leaves to the root.
+    /// The leaf level is zero padded to the next power of two.
+    pub fn tree(message: Vec<Fp>) -> Vec<Vec<Fp>> {
+        let mut level: Vec<Fp> = message
+            .par_iter()
+            .map(|x| hash_block_native::<S, WIDTH, RATE, 1>(vec![*x]))
+            .collect();
+        level.resize(level.len().next_power_of_two(), Fp::ZERO);
+
+        let mut levels = vec![level.clone()];
+        while level.len() > 1 {
+            level = level
+                .par_chunks(2)
+                .map(|pair| hash_block_native::<S, WIDTH, RATE, 2>(pair.to_vec()))
+                .collect();
+            levels.push(level.clone());
+        }
+        levels
+    }
+}
+
+impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize> Module<Fp>
+    for PoseidonMerkleChip<S, WIDTH, RATE>
+{
+    type Config = PoseidonConfig<WIDTH, RATE>;
+    type InputAssignments = InputAssignments;
+    type RunInputs = Vec<Fp>;
+
+    fn name(&self) -> &'static str {
+        "PoseidonMerkle"
+    }
+
+    fn instance_increment_input(&self, _: Vec<usize>) -> Vec<usize> {
+        vec![1]
+    }
+
+    fn instance_increment_module(&self) -> Vec<usize> {
+        vec![0]
+    }
+
+    /// Constructs a new PoseidonMerkleChip
+    fn new(config: Self::Config) -> Self {
+        Self {
+            chip: PoseidonChip::new(config),
+        }
+    }
+
+    /// Configuration of the PoseidonMerkleChip, which is that of the underlying PoseidonChip
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        PoseidonChip::<S, WIDTH, RATE, 2>::configure(meta)
+    }
+
+    fn layout_inputs(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        message: &[ValTensor<Fp>],
+    ) -> Result<Self::InputAssignments, Error> {
+        self.chip.layout_inputs(layouter, message)
+    }
+
+    /// Hashes each of the input cells into a leaf and then hashes pairs of nodes up to the root,
+    /// which is constrained to equal the instance at `row_offset`
+    fn layout(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        input: &[ValTensor<Fp>],
+        row_offset: Vec<usize>,
+    ) -> Result<ValTensor<Fp>, Error> {
+        let (input_cells, zero_val) = self.layout_inputs(layouter, input)?;
+        // extract the values from the input cells
+        let assigned_input: Tensor<ValType<Fp>> =
+            input_cells.iter().map(|e| ValType::from(e.clone())).into();
+
+        let start_time = instant::Instant::now();
+
+        let mut level = input_cells
+            .into_iter()
+            .map(|cell| hash_block::<S, WIDTH, RATE, 1>(&self.chip.config, layouter, vec![cell]))
+            .collect::<Result<Vec<_>, Error>>()?;
+        level.resize(level.len().next_power_of_two(), zero_val);
+
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| {
+                    hash_block::<S, WIDTH, RATE, 2>(&self.chip.config, layouter, pair.to_vec())
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+        }
+
+        log::trace!(
+            "merkle layout (N={:?}) took: {:?}",
+            assigned_input.len(),
+            start_time.elapsed()
+        );
+
+        let root = level.pop().ok_or(Error::Synthesis)?;
+        constrain_output(&self.chip.config, layouter, &root, row_offset[0])?;
+
+        Ok(assigned_input.into())
+    }
+
+    ///
+    fn run(message: Vec<Fp>) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        if message.is_empty() {
+            return Ok(vec![vec![]]);
+        }
+        let root = Self::tree(message).pop().unwrap_or_default();
+        Ok(vec![root])
+    }
+}
+
+#[allow(unused)]
+mod tests {
+
+    use super::super::{
+        spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH},
+        NUM_INSTANCE_COLUMNS,
+    };
+    use super::*;
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2curves::ff::Field;
+
+    const WIDTH: usize = POSEIDON_WIDTH;
+    const RATE: usize = POSEIDON_RATE;
+
+    type MerkleChip = PoseidonMerkleChip<PoseidonSpec, WIDTH, RATE>;
+
+    struct MerkleCircuit {
+        message: ValTensor<Fp>,
+    }
+
+    impl Circuit<Fp> for MerkleCircuit {
+        type Config = PoseidonConfig<WIDTH, RATE>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            let empty_val: Vec<ValType<Fp>> = vec![Value::<Fp>::unknown().into()];
+            let message: Tensor<ValType<Fp>> = empty_val.into_iter().into();
+
+            Self {
+                message: message.into(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> PoseidonConfig<WIDTH, RATE> {
+            MerkleChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: PoseidonConfig<WIDTH, RATE>,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = MerkleChip::new(config);
+            chip.layout(
+                &mut layouter,
+                &[self.message.clone()],
+                vec![0; NUM_INSTANCE_COLUMNS],
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poseidon_merkle_root() {
+        let rng = rand::rngs::OsRng;
+
+        let message = (0..5).map(|_| Fp::random(rng)).collect::<Vec<_>>();
+        let output = MerkleChip::run(message.clone()).unwrap();
+
+        // 5 leaves are padded to 8, giving 4 levels
+        let tree = MerkleChip::tree(message.clone());
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree[0].len(), 8);
+        assert_eq!(tree[3], output[0]);
+
+        let message: Tensor<ValType<Fp>> =
+            message.into_iter().map(|m| Value::known(m).into()).into();
+
+        let k = 11;
+        let circuit = MerkleCircuit {
+            message: message.into(),
+        };
+        let prover = halo2_proofs::dev::MockProver::run(k, &circuit, output).unwrap();
+        assert_eq!(prover.verify(), Ok(()))
+    }
+}
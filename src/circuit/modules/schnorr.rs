@@ -0,0 +1,631 @@
+/*
+Verification of Schnorr signatures over the Poseidon hash of a tensor, in the form of a Halo2 Chip. This lets a prover
+show that a (private) tensor was signed by a given key, e.g that of a trusted sensor or oracle, without revealing it.
+
+Signatures are over the bn256 G1 curve, whose scalar field is the native field of the circuit, such that the
+challenge can be computed with the native Poseidon hash. For a keypair (sk, pk = sk * G) and a message digest m, a
+signature (R, s) is valid if s * G = R + e * pk where e = H5(R, pk, m) (with point coordinates reduced modulo the
+scalar field modulus, as they appear in the public inputs). The public key is a public input.
+*/
+
+use crate::circuit::modules::elgamal::{ElGamalGadget, BIT_LEN_LIMB, NUMBER_OF_LIMBS};
+use crate::circuit::modules::poseidon::spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH};
+use crate::circuit::modules::poseidon::{
+    hash_block, hash_block_native, PoseidonChip as ModulePoseidonChip,
+    PoseidonConfig as ModulePoseidonConfig,
+};
+use crate::tensor::{Tensor, ValTensor, ValType};
+use ark_std::rand::{CryptoRng, RngCore};
+use halo2_gadgets::poseidon::{Pow5Chip as PoseidonChip, Pow5Config as PoseidonConfig};
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Instance};
+use halo2_wrong_ecc::integer::rns::Rns;
+use halo2_wrong_ecc::maingate::{
+    MainGate, MainGateConfig, RangeChip, RangeConfig, RangeInstructions, RegionCtx,
+};
+use halo2_wrong_ecc::{AssignedPoint, BaseFieldEccChip, EccConfig};
+use halo2curves::bn256::{Fq, Fr, G1Affine, G1};
+use halo2curves::group::cofactor::CofactorCurveAffine;
+use halo2curves::group::{Curve, Group};
+use halo2curves::CurveAffine;
+use serde::{Deserialize, Serialize};
+use std::ops::Mul;
+
+use super::{load_json, save_json, Module};
+
+// Offsets for the public inputs of each signature.
+const PK_X: usize = 0;
+const PK_Y: usize = 1;
+
+/// The number of instance columns used by the Schnorr circuit.
+pub const NUM_INSTANCE_COLUMNS: usize = 2;
+
+#[derive(Debug, Clone)]
+/// Configuration for the Schnorr gadget.
+pub struct SchnorrConfig {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+    poseidon_config: PoseidonConfig<Fr, POSEIDON_WIDTH, POSEIDON_RATE>,
+    poseidon_inputs: Vec<Column<Advice>>,
+    message_col: Column<Advice>,
+    pk_col: Column<Instance>,
+}
+
+impl SchnorrConfig {
+    fn config_range(&self, layouter: &mut impl Layouter<Fr>) -> Result<(), Error> {
+        let range_chip = RangeChip::<Fr>::new(self.range_config.clone());
+        range_chip.load_table(layouter)?;
+        Ok(())
+    }
+
+    fn ecc_chip_config(&self) -> EccConfig {
+        EccConfig::new(self.range_config.clone(), self.main_gate_config.clone())
+    }
+
+    /// The config of the Poseidon module sharing this gadget's Poseidon chip, used to hash messages
+    fn hash_config(&self) -> ModulePoseidonConfig<POSEIDON_WIDTH, POSEIDON_RATE> {
+        ModulePoseidonConfig {
+            hash_inputs: self.poseidon_inputs.clone(),
+            instance: self.pk_col,
+            pow5_config: self.poseidon_config.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// A Schnorr keypair, e.g that of a sensor or oracle attesting to the inputs of a model.
+pub struct SchnorrKeypair {
+    /// The secret key.
+    pub sk: Fr,
+    /// The public key, pk = sk * generator.
+    pub pk: G1Affine,
+}
+
+impl SchnorrKeypair {
+    /// Generate a random keypair.
+    pub fn gen_random<R: CryptoRng + RngCore>(mut rng: &mut R) -> Self {
+        let sk = Fr::random(&mut rng);
+        Self {
+            sk,
+            pk: G1Affine::generator().mul(sk).to_affine(),
+        }
+    }
+
+    /// Sign a message digest.
+    pub fn sign_digest<R: CryptoRng + RngCore>(
+        &self,
+        mut rng: &mut R,
+        digest: Fr,
+    ) -> SchnorrSignature {
+        let k = Fr::random(&mut rng);
+        let r = G1Affine::generator().mul(k).to_affine();
+        let e = challenge(r, self.pk, digest);
+        SchnorrSignature {
+            pk: self.pk,
+            r,
+            s: k + e * self.sk,
+        }
+    }
+
+    /// Save the keypair to a .json file.
+    pub fn save(&self, path: &std::path::PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        save_json(self, path)
+    }
+
+    /// Load a keypair from a .json file.
+    pub fn load(path: &std::path::PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        load_json(path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+/// A Schnorr signature, along with the public key it verifies against.
+pub struct SchnorrSignature {
+    /// The public key of the signer.
+    pub pk: G1Affine,
+    /// The commitment to the signing nonce, R = k * generator.
+    pub r: G1Affine,
+    /// The response, s = k + e * sk.
+    pub s: Fr,
+}
+
+impl Default for SchnorrSignature {
+    fn default() -> Self {
+        Self {
+            pk: G1Affine::generator(),
+            r: G1Affine::generator(),
+            s: Fr::zero(),
+        }
+    }
+}
+
+impl SchnorrSignature {
+    /// Whether the signature is valid for a message digest.
+    pub fn verify_digest(&self, digest: Fr) -> bool {
+        let e = challenge(self.r, self.pk, digest);
+        G1Affine::generator().mul(self.s) == G1::from(self.r) + self.pk.mul(e)
+    }
+
+    /// Save signatures to a .json file.
+    pub fn save_all(
+        signatures: &[Self],
+        path: &std::path::PathBuf,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        save_json(signatures, path)
+    }
+
+    /// Load signatures from a .json file.
+    pub fn load_all(path: &std::path::PathBuf) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+        load_json(path)
+    }
+}
+
+/// The challenge e = H5(R, pk, digest), the point coordinates being reduced as they appear in the public inputs.
+fn challenge(r: G1Affine, pk: G1Affine, digest: Fr) -> Fr {
+    let mut preimage = ElGamalGadget::point_instances(r);
+    preimage.extend(ElGamalGadget::point_instances(pk));
+    preimage.push(digest);
+    hash_block_native::<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, 5>(preimage)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The variables used in the Schnorr circuit.
+pub struct SchnorrVariables {
+    /// The signature of each tensor, in order.
+    pub signatures: Vec<SchnorrSignature>,
+    /// The window size used in the ECC chip.
+    pub window_size: usize,
+    /// The auxiliary generator used in the ECC chip.
+    pub aux_generator: G1Affine,
+}
+
+impl Default for SchnorrVariables {
+    fn default() -> Self {
+        Self {
+            signatures: vec![],
+            window_size: 4,
+            aux_generator: G1Affine::generator(),
+        }
+    }
+}
+
+impl SchnorrVariables {
+    /// Generate variables (a random auxiliary generator) for verifying `signatures`.
+    pub fn gen_random<R: CryptoRng + RngCore>(
+        rng: &mut R,
+        signatures: Vec<SchnorrSignature>,
+    ) -> Self {
+        Self {
+            signatures,
+            window_size: 4,
+            aux_generator: <G1Affine as CurveAffine>::CurveExt::random(rng).to_affine(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A gadget verifying Schnorr signatures over the Poseidon hash of tensors. The digest of a tensor is the root of
+/// the flat Poseidon tree with blocks of L elements (see [ModulePoseidonChip]).
+pub struct SchnorrGadget<const L: usize> {
+    /// The configuration for this gadget.
+    config: SchnorrConfig,
+    /// The variables used in this gadget.
+    variables: Option<SchnorrVariables>,
+    /// The position of the tensor whose signature the next layout verifies.
+    tensor: usize,
+}
+
+impl<const L: usize> SchnorrGadget<L> {
+    /// Load the variables into the gadget.
+    pub fn load_variables(&mut self, variables: SchnorrVariables) {
+        self.variables = Some(variables);
+    }
+
+    /// Select the tensor (by its position among the signed tensors) whose signature the next layout verifies.
+    pub fn select_tensor(&mut self, tensor: usize) {
+        self.tensor = tensor;
+    }
+
+    /// The signature of the selected tensor. Without witnesses (e.g when generating keys) there are no signatures,
+    /// and any signature lays out the same circuit.
+    fn signature(&self) -> Result<SchnorrSignature, Error> {
+        let signatures = &self.variables()?.signatures;
+        if signatures.is_empty() {
+            return Ok(SchnorrSignature::default());
+        }
+        signatures.get(self.tensor).copied().ok_or_else(|| {
+            log::error!("missing the signature of signed tensor {}", self.tensor);
+            Error::Synthesis
+        })
+    }
+
+    fn variables(&self) -> Result<&SchnorrVariables, Error> {
+        self.variables.as_ref().ok_or_else(|| {
+            log::error!("schnorr variables not loaded");
+            Error::Synthesis
+        })
+    }
+
+    /// The digest a message is signed through.
+    pub fn digest(msg: Vec<Fr>) -> Fr {
+        ModulePoseidonChip::<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, L>::hash_tree_native(msg)
+            .pop()
+            .unwrap_or(Fr::zero())
+    }
+
+    /// Sign a message with a keypair.
+    pub fn sign<R: CryptoRng + RngCore>(
+        keypair: &SchnorrKeypair,
+        rng: &mut R,
+        msg: Vec<Fr>,
+    ) -> SchnorrSignature {
+        keypair.sign_digest(rng, Self::digest(msg))
+    }
+
+    /// Checks s * generator = R + e * pk in the circuit, where e is computed from the message digest.
+    /// Returns the assigned public key.
+    pub(crate) fn verify_signature(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        signature: &SchnorrSignature,
+        s: &AssignedCell<Fr, Fr>,
+        digest: AssignedCell<Fr, Fr>,
+    ) -> Result<AssignedPoint<Fq, Fr, NUMBER_OF_LIMBS, BIT_LEN_LIMB>, Error> {
+        let variables = self.variables()?;
+        let mut ecc = BaseFieldEccChip::<G1Affine, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::new(
+            self.config.ecc_chip_config(),
+        );
+
+        let (r, pk) = layouter.assign_region(
+            || "assign_signature",
+            |region| {
+                let ctx = &mut RegionCtx::new(region, 0);
+                let r = ecc.assign_point(ctx, Value::known(signature.r))?;
+                let pk = ecc.assign_point(ctx, Value::known(signature.pk))?;
+                Ok((r, pk))
+            },
+        )?;
+
+        // compute the challenge e = H5(R, pk, digest)
+        let e = hash_block::<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, 5>(
+            &self.config.hash_config(),
+            layouter,
+            vec![
+                r.x().native().clone(),
+                r.y().native().clone(),
+                pk.x().native().clone(),
+                pk.y().native().clone(),
+                digest,
+            ],
+        )?;
+
+        layouter.assign_region(
+            || "verify_signature",
+            |region| {
+                let ctx = &mut RegionCtx::new(region, 0);
+
+                ecc.assign_aux_generator(ctx, Value::known(variables.aux_generator))?;
+                ecc.assign_aux(ctx, variables.window_size, 1)?;
+
+                let g = ecc.assign_constant(ctx, G1Affine::generator())?;
+                // s * generator
+                let lhs = ecc.mul(ctx, &g, s, variables.window_size)?;
+                // R + e * pk
+                let e_pk = ecc.mul(ctx, &pk, &e, variables.window_size)?;
+                let rhs = ecc.add(ctx, &r, &e_pk)?;
+
+                ecc.assert_equal(ctx, &lhs, &rhs)
+            },
+        )?;
+
+        Ok(pk)
+    }
+}
+
+impl<const L: usize> Module<Fr> for SchnorrGadget<L> {
+    type Config = SchnorrConfig;
+    type InputAssignments = (
+        Vec<AssignedCell<Fr, Fr>>,
+        AssignedCell<Fr, Fr>,
+        AssignedCell<Fr, Fr>,
+    );
+    type RunInputs = (Vec<Fr>, SchnorrSignature);
+
+    fn new(config: Self::Config) -> Self {
+        Self {
+            config,
+            variables: None,
+            tensor: 0,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let main_gate_config = MainGate::<Fr>::configure(meta);
+        let advices = main_gate_config.advices();
+
+        let fixed_columns = [
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+        ];
+
+        meta.enable_constant(fixed_columns[3]);
+
+        let rc_a = fixed_columns[0..2].try_into().unwrap();
+        let rc_b = fixed_columns[2..4].try_into().unwrap();
+
+        let rns = Rns::<Fq, Fr, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::construct();
+
+        let overflow_bit_lens = rns.overflow_lengths();
+        let composition_bit_lens = vec![BIT_LEN_LIMB / NUMBER_OF_LIMBS];
+
+        let range_config = RangeChip::<Fr>::configure(
+            meta,
+            &main_gate_config,
+            composition_bit_lens,
+            overflow_bit_lens,
+        );
+
+        let poseidon_inputs = advices[1..3].to_vec();
+        let poseidon_config = PoseidonChip::configure::<PoseidonSpec>(
+            meta,
+            advices[1..3].try_into().unwrap(),
+            advices[0],
+            rc_a,
+            rc_b,
+        );
+
+        let pk_col = meta.instance_column();
+        meta.enable_equality(pk_col);
+
+        SchnorrConfig {
+            main_gate_config,
+            range_config,
+            poseidon_config,
+            poseidon_inputs,
+            message_col: advices[1],
+            pk_col,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Schnorr"
+    }
+
+    fn instance_increment_input(&self, _: Vec<usize>) -> Vec<usize> {
+        // in order
+        // 1. empty maingate instance
+        // 2. pk of each signature
+        vec![0, 2]
+    }
+
+    fn instance_increment_module(&self) -> Vec<usize> {
+        vec![0, 0]
+    }
+
+    fn run(input: Self::RunInputs) -> Result<Vec<Vec<Fr>>, Box<dyn std::error::Error>> {
+        let (msg, signature) = input;
+        if !signature.verify_digest(Self::digest(msg)) {
+            return Err("invalid signature".into());
+        }
+        // keep 1 empty (maingate instance variable).
+        Ok(vec![vec![], ElGamalGadget::point_instances(signature.pk)])
+    }
+
+    /// Assigns the message, the response s of the signature and a zero cell used to pad the message when hashing it
+    fn layout_inputs(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        inputs: &[ValTensor<Fr>],
+    ) -> Result<Self::InputAssignments, Error> {
+        if inputs.len() != 2 {
+            log::error!("expected the message and s, got {} inputs", inputs.len());
+            return Err(Error::Synthesis);
+        }
+        let message = inputs[0].clone();
+        let s = match inputs[1]
+            .get_inner_tensor()
+            .map_err(|_| Error::Synthesis)?
+            .first()
+        {
+            Some(ValType::Value(v)) => *v,
+            _ => {
+                log::error!("wrong input type for s, must be an unassigned value");
+                return Err(Error::Synthesis);
+            }
+        };
+
+        layouter.assign_region(
+            || "message",
+            |mut region| {
+                let msg_var = message
+                    .get_inner_tensor()
+                    .map_err(|_| Error::Synthesis)?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| match value {
+                        ValType::Value(v) => region.assign_advice(
+                            || format!("load message_{}", i),
+                            self.config.message_col,
+                            i,
+                            || *v,
+                        ),
+                        ValType::PrevAssigned(v) => Ok(v.clone()),
+                        _ => {
+                            log::error!("wrong input type for message_{}", i);
+                            Err(Error::Synthesis)
+                        }
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                let s_var =
+                    region.assign_advice(|| "s", self.config.message_col, message.len(), || s)?;
+
+                let zero_var = region.assign_advice_from_constant(
+                    || "zero",
+                    self.config.message_col,
+                    message.len() + 1,
+                    Fr::zero(),
+                )?;
+
+                Ok((msg_var, s_var, zero_var))
+            },
+        )
+    }
+
+    /// Verifies the signature of the selected tensor (see [SchnorrGadget::select_tensor]) over the message, and
+    /// constrains the public key it verifies against to the instances at `row_offsets`
+    fn layout(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        inputs: &[ValTensor<Fr>],
+        row_offsets: Vec<usize>,
+    ) -> Result<ValTensor<Fr>, Error> {
+        let start_time = instant::Instant::now();
+
+        // if all equivalent to 0, then we are in the first row of the circuit
+        if row_offsets.iter().all(|&x| x == 0) {
+            self.config.config_range(layouter)?;
+        }
+
+        let message = inputs.first().ok_or_else(|| {
+            log::error!("no message to verify the signature of");
+            Error::Synthesis
+        })?;
+        let signature = self.signature()?;
+        let s: Tensor<ValType<Fr>> = Tensor::new(Some(&[Value::known(signature.s).into()]), &[1])
+            .map_err(|_| Error::Synthesis)?;
+
+        let (msg_var, s_var, zero_var) =
+            self.layout_inputs(layouter, &[message.clone(), s.into()])?;
+
+        let digest = ModulePoseidonChip::<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, L>::new(
+            self.config.hash_config(),
+        )
+        .hash_tree(layouter, msg_var.clone(), &zero_var)?;
+
+        let pk = self.verify_signature(layouter, &signature, &s_var, digest)?;
+
+        // Force the public input to be the public key so that the verifier knows who signed the message
+        layouter
+            .constrain_instance(
+                pk.x().native().cell(),
+                self.config.pk_col,
+                PK_X + row_offsets[1],
+            )
+            .and(layouter.constrain_instance(
+                pk.y().native().cell(),
+                self.config.pk_col,
+                PK_Y + row_offsets[1],
+            ))?;
+
+        let assigned_input: Tensor<ValType<Fr>> =
+            msg_var.iter().map(|e| ValType::from(e.clone())).into();
+
+        log::trace!(
+            "layout (N={:?}) took: {:?}",
+            msg_var.len(),
+            start_time.elapsed()
+        );
+
+        Ok(assigned_input.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::test_rng;
+    use halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, plonk::Circuit};
+
+    const L: usize = 2;
+
+    struct SignatureCircuit {
+        message: ValTensor<Fr>,
+        variables: SchnorrVariables,
+        tensor: usize,
+    }
+
+    impl Circuit<Fr> for SignatureCircuit {
+        type Config = SchnorrConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            let empty_val: Vec<ValType<Fr>> = vec![Value::<Fr>::unknown().into()];
+            let message: Tensor<ValType<Fr>> = empty_val.into_iter().into();
+
+            Self {
+                message: message.into(),
+                variables: SchnorrVariables::default(),
+                tensor: self.tensor,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> SchnorrConfig {
+            SchnorrGadget::<L>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: SchnorrConfig,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut chip = SchnorrGadget::<L>::new(config);
+            chip.load_variables(self.variables.clone());
+            chip.select_tensor(self.tensor);
+            chip.layout(
+                &mut layouter,
+                &[self.message.clone()],
+                vec![0; NUM_INSTANCE_COLUMNS],
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    pub fn test_sign_verify() {
+        let mut rng = test_rng();
+
+        let keypair = SchnorrKeypair::gen_random(&mut rng);
+        let msg = (0..5).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+        let signature = SchnorrGadget::<L>::sign(&keypair, &mut rng, msg.clone());
+        assert!(SchnorrGadget::<L>::run((msg.clone(), signature)).is_ok());
+
+        // a different message is rejected
+        let mut tampered = msg;
+        tampered[0] += Fr::one();
+        assert!(SchnorrGadget::<L>::run((tampered, signature)).is_err());
+    }
+
+    #[test]
+    pub fn test_circuit() {
+        let mut rng = test_rng();
+
+        let keypair = SchnorrKeypair::gen_random(&mut rng);
+        let msg = (0..3).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let signature = SchnorrGadget::<L>::sign(&keypair, &mut rng, msg.clone());
+
+        let public_inputs = SchnorrGadget::<L>::run((msg.clone(), signature)).unwrap();
+
+        let message: Tensor<ValType<Fr>> = msg.into_iter().map(|m| Value::known(m).into()).into();
+        let circuit = SignatureCircuit {
+            message: message.into(),
+            variables: SchnorrVariables::gen_random(&mut rng, vec![signature]),
+            tensor: 0,
+        };
+
+        let res = MockProver::run(17, &circuit, public_inputs.clone()).unwrap();
+        res.assert_satisfied_par();
+
+        // there is no signature for a second tensor
+        let circuit = SignatureCircuit {
+            tensor: 1,
+            ..circuit
+        };
+        assert!(MockProver::run(17, &circuit, public_inputs).is_err());
+    }
+}
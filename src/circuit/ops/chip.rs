@@ -66,26 +66,43 @@ impl From<String> for CheckMode {
     }
 }
 
+/// Whether a [Tolerance] bounds the percentage error or the absolute error.
+#[derive(Clone, Default, Debug, PartialEq, PartialOrd, Serialize, Deserialize, Copy)]
+pub enum ToleranceKind {
+    /// the error relative to the expected value, as a percentage
+    #[default]
+    Percentage,
+    /// the absolute error, in the (dequantized) units of the value
+    Absolute,
+}
+
 #[allow(missing_docs)]
 /// An enum representing the tolerance we can accept for the accumulated arguments, either absolute or percentage
 #[derive(Clone, Default, Debug, PartialEq, PartialOrd, Serialize, Deserialize, Copy)]
 pub struct Tolerance {
     pub val: f32,
     pub scales: (usize, usize),
+    #[serde(default)]
+    pub kind: ToleranceKind,
 }
 
 impl FromStr for Tolerance {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Ok(val) = s.parse::<f32>() {
+        let (val, kind) = match s.strip_prefix("abs:") {
+            Some(val) => (val, ToleranceKind::Absolute),
+            None => (s, ToleranceKind::Percentage),
+        };
+        if let Ok(val) = val.parse::<f32>() {
             Ok(Tolerance {
                 val,
                 scales: (1, 1),
+                kind,
             })
         } else {
             Err(
-                "Invalid tolerance value provided. It should expressed as a percentage (f32)."
+                "Invalid tolerance value provided. It should expressed as a percentage (f32), or as an absolute error prefixed with `abs:`."
                     .to_string(),
             )
         }
@@ -121,7 +138,10 @@ impl<'source> FromPyObject<'source> for CheckMode {
 /// Converts Tolerance into a PyObject (Required for Tolerance to be compatible with Python)
 impl IntoPy<PyObject> for Tolerance {
     fn into_py(self, py: Python) -> PyObject {
-        (self.val, self.scales).to_object(py)
+        match self.kind {
+            ToleranceKind::Percentage => (self.val, self.scales).to_object(py),
+            ToleranceKind::Absolute => (self.val, self.scales, "abs").to_object(py),
+        }
     }
 }
 
@@ -130,7 +150,17 @@ impl IntoPy<PyObject> for Tolerance {
 impl<'source> FromPyObject<'source> for Tolerance {
     fn extract(ob: &'source PyAny) -> PyResult<Self> {
         if let Ok((val, scales)) = ob.extract::<(f32, (usize, usize))>() {
-            Ok(Tolerance { val, scales })
+            Ok(Tolerance {
+                val,
+                scales,
+                kind: ToleranceKind::Percentage,
+            })
+        } else if let Ok((val, scales, "abs")) = ob.extract::<(f32, (usize, usize), &str)>() {
+            Ok(Tolerance {
+                val,
+                scales,
+                kind: ToleranceKind::Absolute,
+            })
         } else {
             Err(PyValueError::new_err("Invalid tolerance value provided. "))
         }
@@ -154,6 +184,8 @@ pub struct BaseConfig<F: PrimeField + TensorType + PartialOrd> {
     pub selectors: BTreeMap<(BaseOp, usize), Selector>,
     /// [Selector]s generated when configuring the layer. We use a [BTreeMap] as we expect to configure many lookup ops.
     pub lookup_selectors: BTreeMap<(LookupOp, usize), Selector>,
+    /// The column of `lookup_input` each lookup argument reads from, keyed by the index of the argument in the constraint system.
+    pub lookup_columns: BTreeMap<usize, usize>,
     ///
     pub tables: BTreeMap<LookupOp, Table<F>>,
     /// Activate sanity checks
@@ -171,6 +203,7 @@ impl<F: PrimeField + TensorType + PartialOrd> BaseConfig<F> {
             lookup_output: VarTensor::dummy(col_size),
             selectors: BTreeMap::new(),
             lookup_selectors: BTreeMap::new(),
+            lookup_columns: BTreeMap::new(),
             tables: BTreeMap::new(),
             check_mode: CheckMode::SAFE,
             _marker: PhantomData,
@@ -271,6 +304,7 @@ impl<F: PrimeField + TensorType + PartialOrd> BaseConfig<F> {
         Self {
             selectors,
             lookup_selectors: BTreeMap::new(),
+            lookup_columns: BTreeMap::new(),
             inputs: inputs.to_vec(),
             lookup_input: VarTensor::Empty,
             lookup_output: VarTensor::Empty,
@@ -307,7 +341,7 @@ impl<F: PrimeField + TensorType + PartialOrd> BaseConfig<F> {
         for x in 0..input.num_cols() {
             let qlookup = cs.complex_selector();
             selectors.insert((nl.clone(), x), qlookup);
-            let _ = cs.lookup(Op::<F>::as_string(nl), |cs| {
+            let lookup_index = cs.lookup(Op::<F>::as_string(nl), |cs| {
                 let qlookup = cs.query_selector(qlookup);
                 let not_qlookup = Expression::Constant(<F as Field>::ONE) - qlookup.clone();
                 let (default_x, default_y): (F, F) = nl.default_pair();
@@ -334,6 +368,7 @@ impl<F: PrimeField + TensorType + PartialOrd> BaseConfig<F> {
                     ),
                 ]
             });
+            self.lookup_columns.insert(lookup_index, x);
         }
         self.lookup_selectors.extend(selectors);
         // if we haven't previously initialized the input/output, do so now
@@ -1,7 +1,7 @@
 use std::any::Any;
 
 use crate::{
-    circuit::{self, layouts, Tolerance},
+    circuit::{self, layouts, Tolerance, ToleranceKind},
     graph::scale_to_multiplier,
     tensor::{self, Tensor, TensorError, TensorType, ValTensor},
 };
@@ -106,14 +106,23 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
                 scales.0,
                 scales.1,
             )?,
-            HybridOp::RangeCheck(tol) => layouts::range_check_percent(
-                config,
-                region,
-                values[..].try_into()?,
-                tol.scales.0,
-                tol.scales.1,
-                tol.val,
-            )?,
+            HybridOp::RangeCheck(tol) => match tol.kind {
+                ToleranceKind::Percentage => layouts::range_check_percent(
+                    config,
+                    region,
+                    values[..].try_into()?,
+                    tol.scales.0,
+                    tol.scales.1,
+                    tol.val,
+                )?,
+                ToleranceKind::Absolute => layouts::range_check_abs(
+                    config,
+                    region,
+                    values[..].try_into()?,
+                    tol.scales.0,
+                    tol.val,
+                )?,
+            },
         }))
     }
 
@@ -152,13 +161,20 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
             HybridOp::RangeCheck(tol) => {
                 let mut lookups = vec![];
                 if tol.val > 0.0 {
-                    let scale = tol.scales.0 * tol.scales.1;
-                    lookups.extend([
-                        LookupOp::Recip { scale },
-                        LookupOp::GreaterThan {
-                            a: circuit::utils::F32((tol.val * scale as f32) / 100.0),
-                        },
-                    ]);
+                    match tol.kind {
+                        ToleranceKind::Percentage => {
+                            let scale = tol.scales.0 * tol.scales.1;
+                            lookups.extend([
+                                LookupOp::Recip { scale },
+                                LookupOp::GreaterThan {
+                                    a: circuit::utils::F32((tol.val * scale as f32) / 100.0),
+                                },
+                            ]);
+                        }
+                        ToleranceKind::Absolute => lookups.push(LookupOp::GreaterThan {
+                            a: circuit::utils::F32(tol.val * tol.scales.0 as f32),
+                        }),
+                    }
                 }
                 lookups
             }
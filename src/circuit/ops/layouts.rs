@@ -703,6 +703,45 @@ pub fn iff<F: PrimeField + TensorType + PartialOrd>(
     Ok(output)
 }
 
+/// Constrains the values to be one-hot along `axis`, i.e to be boolean and to sum to 1 along the axis.
+/// Returns the assigned values.
+pub fn one_hot_check<F: PrimeField + TensorType + PartialOrd>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 1],
+    axis: usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let unit: ValTensor<F> =
+        Tensor::from(vec![region.assign_constant(&config.inputs[1], F::from(1))?].into_iter())
+            .into();
+    region.next();
+
+    // make sure the values are boolean
+    let assigned = region.assign(&config.inputs[1], &values[0])?;
+    for i in 0..assigned.len() {
+        let (x, y) = config.inputs[1].cartesian_coord(region.offset() + i);
+        let selector = config.selectors.get(&(BaseOp::IsBoolean, x));
+        region.enable(selector, y)?;
+    }
+
+    region.increment(assigned.len());
+
+    // make sure they sum to 1 along the axis
+    let sums = sum_axes(config, region, &[assigned.clone()], &[axis])?;
+    let diff = pairwise(config, region, &[sums, unit], BaseOp::Sub)?;
+
+    region.assign(&config.inputs[1], &diff)?;
+    for i in 0..diff.len() {
+        let (x, y) = config.inputs[1].cartesian_coord(region.offset() + i);
+        let selector = config.selectors.get(&(BaseOp::IsZero, x));
+        region.enable(selector, y)?;
+    }
+
+    region.increment(diff.len());
+
+    Ok(assigned)
+}
+
 /// Negation operation accumulated layout
 pub fn neg<F: PrimeField + TensorType + PartialOrd>(
     config: &BaseConfig<F>,
@@ -1397,7 +1436,7 @@ pub fn nonlinearity<F: PrimeField + TensorType + PartialOrd>(
     for i in 0..x.len() {
         let (x, y) = config.lookup_input.cartesian_coord(region.offset() + i);
         let selector = config.lookup_selectors.get(&(nl.clone(), x));
-        region.enable(selector, y)?;
+        region.enable_lookup(nl, selector, y)?;
     }
 
     output.reshape(x.dims())?;
@@ -1706,6 +1745,77 @@ pub fn softmax<F: PrimeField + TensorType + PartialOrd>(
     Ok(softmax)
 }
 
+/// Checks that the absolute error between the expected public output and the actual output value
+/// is within `tol`, expressed in the (dequantized) units of the output, by bounding the difference
+/// between the two from above and below.
+pub fn range_check_abs<F: PrimeField + TensorType + PartialOrd>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 2],
+    scale: usize,
+    tol: f32,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    if tol == 0.0 {
+        // regular equality constraint
+        return range_check(config, region, values, 0);
+    }
+
+    // Calculate the difference between the expected output and actual output
+    let diff = pairwise(config, region, values, BaseOp::Sub)?;
+
+    // the tolerance in fixed point
+    let tol = tol * scale as f32;
+
+    // Use the greater than look up table to check if the difference is within the tolerance for upper bound
+    let upper_bound = nonlinearity(
+        config,
+        region,
+        &[diff.clone()],
+        &LookupOp::GreaterThan { a: utils::F32(tol) },
+    )?;
+
+    // Negate the difference
+    let neg_diff = neg(config, region, &[diff])?;
+
+    // Use the greater than look up table to check if the difference is within the tolerance for lower bound
+    let lower_bound = nonlinearity(
+        config,
+        region,
+        &[neg_diff],
+        &LookupOp::GreaterThan { a: utils::F32(tol) },
+    )?;
+
+    // Add the lower_bound and upper_bound
+    let sum = pairwise(config, region, &[lower_bound, upper_bound], BaseOp::Add)?;
+
+    // Assign the sum tensor to the inputs
+    region.assign(&config.inputs[1], &sum)?;
+
+    // Constrain the sum to be all zeros
+    let (x, y) = config.output.cartesian_coord(region.offset());
+    let selector = config.selectors.get(&(BaseOp::IsZero, x));
+    region.enable(selector, y)?;
+
+    region.increment(sum.len());
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        let is_assigned = !Into::<Tensor<i32>>::into(sum.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let int_evals = &[
+                Tensor::new(Some(&values[0].get_int_evals()?), values[0].dims())?,
+                Tensor::new(Some(&values[1].get_int_evals()?), values[1].dims())?,
+            ];
+            let ref_range_check_abs: Tensor<i128> =
+                tensor::ops::nonlinearities::range_check_abs(int_evals, tol);
+            let output_int_evals = Tensor::new(Some(&sum.get_int_evals()?), values[0].dims())?;
+            assert_eq!(output_int_evals, ref_range_check_abs)
+        }
+    }
+    Ok(sum)
+}
+
 /// Checks that the percent error between the expected public output and the actual output value
 /// is within the percent error expressed by the `tol` input, where `tol == 1.0` means the percent
 /// error tolerance is 1 percent.
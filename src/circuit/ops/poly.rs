@@ -11,6 +11,7 @@ use super::{base::BaseOp, *};
 pub enum PolyOp<F: PrimeField + TensorType + PartialOrd> {
     Einsum {
         equation: String,
+        bias: Option<ValTensor<F>>,
     },
     Conv {
         kernel: ValTensor<F>,
@@ -60,6 +61,9 @@ pub enum PolyOp<F: PrimeField + TensorType + PartialOrd> {
         end: usize,
     },
     Iff,
+    OneHotCheck {
+        axis: usize,
+    },
     Resize {
         scale_factor: Vec<usize>,
     },
@@ -75,6 +79,7 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for PolyOp<F> {
         let name = match &self {
             PolyOp::Resize { .. } => "RESIZE",
             PolyOp::Iff => "IFF",
+            PolyOp::OneHotCheck { .. } => "ONEHOTCHECK",
             PolyOp::Einsum { .. } => "EINSUM",
             PolyOp::Identity => "IDENTITY",
             PolyOp::Reshape(_) => "RESHAPE",
@@ -103,7 +108,23 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for PolyOp<F> {
         let res = match &self {
             PolyOp::Resize { scale_factor } => tensor::ops::resize(&inputs[0], scale_factor),
             PolyOp::Iff => tensor::ops::iff(&inputs[0], &inputs[1], &inputs[2]),
-            PolyOp::Einsum { equation } => tensor::ops::einsum(equation, &inputs),
+            PolyOp::OneHotCheck { axis } => {
+                if 1 != inputs.len() {
+                    return Err(TensorError::DimMismatch("one hot check inputs".to_string()));
+                }
+                tensor::ops::one_hot_check(&inputs[0], *axis)
+            }
+            PolyOp::Einsum { equation, bias } => {
+                let res = tensor::ops::einsum(equation, &inputs)?;
+                if let Some(bias) = bias {
+                    // a bias without integer values (e.g an instance) can't be folded into the forward pass
+                    let values = bias.get_int_evals().map_err(|_| TensorError::WrongMethod)?;
+                    let bias = Tensor::new(Some(&values), bias.dims())?;
+                    tensor::ops::add(&[res, bias])
+                } else {
+                    Ok(res)
+                }
+            }
             PolyOp::Gather { dim, index } => tensor::ops::gather(&inputs[0], *dim, index),
             PolyOp::Identity => Ok(inputs[0].clone()),
             PolyOp::Reshape(new_dims) => {
@@ -218,8 +239,16 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for PolyOp<F> {
                 layouts::resize(config, region, values[..].try_into()?, scale_factor)?
             }
             PolyOp::Iff => layouts::iff(config, region, values[..].try_into()?)?,
-            PolyOp::Einsum { equation } => {
-                layouts::einsum(config, region, &mut values, equation)?
+            PolyOp::OneHotCheck { axis } => {
+                layouts::one_hot_check(config, region, values[..].try_into()?, *axis)?
+            }
+            PolyOp::Einsum { equation, bias } => {
+                let res = layouts::einsum(config, region, &mut values, equation)?;
+                if let Some(bias) = bias {
+                    layouts::pairwise(config, region, &[res, bias.clone()], BaseOp::Add)?
+                } else {
+                    res
+                }
             }
             PolyOp::Gather { dim, index } => {
                 tensor::ops::gather(&values[0].get_inner_tensor()?, *dim, index)?.into()
@@ -316,11 +345,13 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for PolyOp<F> {
         match self {
             PolyOp::Resize { .. } => in_scales[0],
             PolyOp::Iff => in_scales[1],
+            PolyOp::OneHotCheck { .. } => in_scales[0],
             PolyOp::Einsum { .. } => {
                 let mut scale = in_scales[0];
                 for s in in_scales.iter().skip(1) {
                     scale += *s;
                 }
+                // the bias is only attached when it matches this scale (see [crate::graph::ParsedNodes::fuse_einsum_bias])
                 scale
             }
             PolyOp::Gather { .. } => in_scales[0],
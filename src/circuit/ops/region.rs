@@ -1,9 +1,11 @@
 use halo2_proofs::{
-    circuit::Region,
+    circuit::{Region, Value},
     plonk::{Error, Selector},
 };
+use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
 
+use super::lookup::LookupOp;
 use crate::tensor::{TensorType, ValTensor, ValType, VarTensor};
 use halo2curves::ff::PrimeField;
 
@@ -12,6 +14,8 @@ use halo2curves::ff::PrimeField;
 pub struct RegionCtx<'a, F: PrimeField + TensorType + PartialOrd> {
     region: Arc<Mutex<Option<Region<'a, F>>>>,
     offset: usize,
+    total_constants: usize,
+    lookups: BTreeMap<LookupOp, usize>,
 }
 
 impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
@@ -19,14 +23,24 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
     pub fn new(region: Region<'a, F>, offset: usize) -> RegionCtx<'a, F> {
         let region = Arc::new(Mutex::new(Some(region)));
 
-        RegionCtx { region, offset }
+        RegionCtx {
+            region,
+            offset,
+            total_constants: 0,
+            lookups: BTreeMap::new(),
+        }
     }
     /// Create a new region context from a wrapped region
     pub fn from_wrapped_region(
         region: Arc<Mutex<Option<Region<'a, F>>>>,
         offset: usize,
     ) -> RegionCtx<'a, F> {
-        RegionCtx { region, offset }
+        RegionCtx {
+            region,
+            offset,
+            total_constants: 0,
+            lookups: BTreeMap::new(),
+        }
     }
     /// Get the region
     pub fn region(&self) -> Arc<Mutex<Option<Region<'a, F>>>> {
@@ -37,7 +51,12 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
     pub fn new_dummy(offset: usize) -> RegionCtx<'a, F> {
         let region = Arc::new(Mutex::new(None));
 
-        RegionCtx { region, offset }
+        RegionCtx {
+            region,
+            offset,
+            total_constants: 0,
+            lookups: BTreeMap::new(),
+        }
     }
 
     /// Get the offset
@@ -45,14 +64,35 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         self.offset
     }
 
+    /// The number of constant cells assigned using this context
+    pub fn total_constants(&self) -> usize {
+        self.total_constants
+    }
+
+    /// The number of lookups performed using this context, per [LookupOp]
+    pub fn lookups(&self) -> &BTreeMap<LookupOp, usize> {
+        &self.lookups
+    }
+
+    /// Counts the constant cells assigned along with `values`. This is the only place constants are counted.
+    fn count_constants<'v>(&mut self, values: impl IntoIterator<Item = &'v ValType<F>>) {
+        self.total_constants += values
+            .into_iter()
+            .filter(|v| matches!(v, ValType::Constant(_)))
+            .count();
+    }
+
     /// Assign a constant value
     pub fn assign_constant(&mut self, var: &VarTensor, value: F) -> Result<ValType<F>, Error> {
+        let constant = ValType::Constant(value);
+        self.count_constants([&constant]);
         let mut lock = self.region.lock().unwrap();
         if let Some(region) = lock.as_mut() {
             let cell = var.assign_constant(region, self.offset, value)?;
             Ok(cell.into())
         } else {
-            Ok(value.into())
+            // like the assigned cell, the result is copied rather than re-assigned as a constant when used
+            Ok(Value::known(value).into())
         }
     }
     /// Assign a valtensor to a vartensor
@@ -61,6 +101,9 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         var: &VarTensor,
         values: &ValTensor<F>,
     ) -> Result<ValTensor<F>, Error> {
+        if let ValTensor::Value { inner, .. } = values {
+            self.count_constants(inner.iter());
+        }
         let mut lock = self.region.lock().unwrap();
         var.assign(&mut lock, self.offset, values)
     }
@@ -71,6 +114,9 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         values: &ValTensor<F>,
         check_mode: &crate::circuit::CheckMode,
     ) -> Result<(ValTensor<F>, usize), Error> {
+        if let ValTensor::Value { inner, .. } = values {
+            self.count_constants(inner.iter());
+        }
         let mut lock = self.region.lock().unwrap();
         var.assign_with_duplication(&mut lock, self.offset, values, check_mode)
     }
@@ -85,6 +131,18 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         }
     }
 
+    /// Enable a lookup selector for `op`, recording the lookup into the table for `op`.
+    /// Lookup selectors should only be enabled through here so that every lookup is counted.
+    pub fn enable_lookup(
+        &mut self,
+        op: &LookupOp,
+        selector: Option<&Selector>,
+        y: usize,
+    ) -> Result<(), Error> {
+        *self.lookups.entry(op.clone()).or_insert(0) += 1;
+        self.enable(selector, y)
+    }
+
     /// Increment the offset by 1
     pub fn next(&mut self) {
         self.offset += 1
@@ -63,6 +63,7 @@ mod matmul {
                                 &self.inputs.clone(),
                                 Box::new(PolyOp::Einsum {
                                     equation: "ij,jk->ik".to_string(),
+                                    bias: None,
                                 }),
                             )
                             .map_err(|_| Error::Synthesis)
@@ -139,6 +140,7 @@ mod matmul_col_overflow {
                                 &self.inputs.clone(),
                                 Box::new(PolyOp::Einsum {
                                     equation: "ij,jk->ik".to_string(),
+                                    bias: None,
                                 }),
                             )
                             .map_err(|_| Error::Synthesis)
@@ -216,6 +218,7 @@ mod dot {
                                 &self.inputs.clone(),
                                 Box::new(PolyOp::Einsum {
                                     equation: "i,i->".to_string(),
+                                    bias: None,
                                 }),
                             )
                             .map_err(|_| Error::Synthesis)
@@ -289,6 +292,7 @@ mod dot_col_overflow {
                                 &self.inputs.clone(),
                                 Box::new(PolyOp::Einsum {
                                     equation: "i,i->".to_string(),
+                                    bias: None,
                                 }),
                             )
                             .map_err(|_| Error::Synthesis)
@@ -502,6 +506,7 @@ mod composition {
                                 &self.inputs.clone(),
                                 Box::new(PolyOp::Einsum {
                                     equation: "i,i->".to_string(),
+                                    bias: None,
                                 }),
                             )
                             .unwrap();
@@ -511,6 +516,7 @@ mod composition {
                                 &self.inputs.clone(),
                                 Box::new(PolyOp::Einsum {
                                     equation: "i,i->".to_string(),
+                                    bias: None,
                                 }),
                             )
                             .unwrap();
@@ -520,6 +526,7 @@ mod composition {
                                 &self.inputs.clone(),
                                 Box::new(PolyOp::Einsum {
                                     equation: "i,i->".to_string(),
+                                    bias: None,
                                 }),
                             )
                             .map_err(|_| Error::Synthesis)
@@ -1522,6 +1529,7 @@ mod matmul_relu {
                     let mut region = RegionCtx::new(region, 0);
                     let op = PolyOp::Einsum {
                         equation: "ij,jk->ik".to_string(),
+                        bias: None,
                     };
                     let output = config
                         .base_config
@@ -1565,7 +1573,7 @@ mod matmul_relu {
 
 #[cfg(test)]
 mod rangecheckpercent {
-    use crate::circuit::Tolerance;
+    use crate::circuit::{Tolerance, ToleranceKind};
     use crate::{circuit, tensor::Tensor};
     use halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner, Value},
@@ -1632,6 +1640,7 @@ mod rangecheckpercent {
                                 Box::new(HybridOp::RangeCheck(Tolerance {
                                     val: RANGE,
                                     scales: (SCALE, SCALE),
+                                    kind: ToleranceKind::Percentage,
                                 })),
                             )
                             .map_err(|_| Error::Synthesis)
@@ -1691,6 +1700,124 @@ mod rangecheckpercent {
     }
 }
 
+#[cfg(test)]
+mod rangecheckabs {
+    use crate::circuit::{Tolerance, ToleranceKind};
+    use crate::{circuit, tensor::Tensor};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2curves::pasta::Fp;
+
+    const RANGE: f32 = 0.25; // absolute error tolerance
+    const K: usize = 18;
+    const LEN: usize = 1;
+    const SCALE: usize = i128::pow(2, 3) as usize;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: PrimeField + TensorType + PartialOrd> {
+        input: ValTensor<F>,
+        output: ValTensor<F>,
+        _marker: PhantomData<F>,
+    }
+
+    impl<F: PrimeField + TensorType + PartialOrd> Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN);
+            let b = VarTensor::new_advice(cs, K, LEN);
+            let output = VarTensor::new_advice(cs, K, LEN);
+            let mut config = Self::Config::configure(cs, &[a, b.clone()], &output, CheckMode::SAFE);
+            // set up a new GreaterThan table
+            let nl = &LookupOp::GreaterThan {
+                a: circuit::utils::F32(RANGE * SCALE as f32),
+            };
+            config.configure_lookup(cs, &b, &output, 16, nl).unwrap();
+            config
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0);
+                        config
+                            .layout(
+                                &mut region,
+                                &[self.output.clone(), self.input.clone()],
+                                Box::new(HybridOp::RangeCheck(Tolerance {
+                                    val: RANGE,
+                                    scales: (SCALE, SCALE),
+                                    kind: ToleranceKind::Absolute,
+                                })),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[allow(clippy::assertions_on_constants)]
+    fn test_range_check_abs() {
+        // Successful cases: outputs near zero are fine as long as they are within 2 (= 0.25 * 8) in fixed point
+        {
+            let inp = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(0_u64))]), &[1]).unwrap();
+            let out = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(2_u64))]), &[1]).unwrap();
+            let circuit = MyCircuit::<Fp> {
+                input: ValTensor::from(inp),
+                output: ValTensor::from(out),
+                _marker: PhantomData,
+            };
+            let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+            prover.assert_satisfied();
+        }
+        {
+            let inp = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(200_u64))]), &[1]).unwrap();
+            let out = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(199_u64))]), &[1]).unwrap();
+            let circuit = MyCircuit::<Fp> {
+                input: ValTensor::from(inp),
+                output: ValTensor::from(out),
+                _marker: PhantomData,
+            };
+            let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+            prover.assert_satisfied();
+        }
+
+        // Unsuccessful case
+        {
+            let inp = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(100_u64))]), &[1]).unwrap();
+            let out = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(103_u64))]), &[1]).unwrap();
+            let circuit = MyCircuit::<Fp> {
+                input: ValTensor::from(inp),
+                output: ValTensor::from(out),
+                _marker: PhantomData,
+            };
+            let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+}
+
 #[cfg(test)]
 mod relu {
     use super::*;
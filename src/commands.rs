@@ -92,7 +92,7 @@ pub enum CalibrationTarget {
     /// Optimizes for reducing cpu and memory usage
     #[default]
     Resources,
-    /// Optimizes for numerical accuracy given the fixed point representation
+    /// Optimizes for numerical accuracy against the float model on the calibration data
     Accuracy,
 }
 
@@ -131,6 +131,58 @@ impl<'source> FromPyObject<'source> for CalibrationTarget {
     }
 }
 
+#[derive(
+    clap::ValueEnum, Debug, Default, Copy, Clone, Serialize, Deserialize, PartialEq, PartialOrd,
+)]
+/// Determines how hashed inputs, params and outputs are committed to with Poseidon
+pub enum HashMode {
+    /// Hashes the elements in blocks, repeating over the resulting digests until a single digest remains
+    #[default]
+    Flat,
+    /// Commits to the root of a binary Merkle tree over the elements, which can be opened at individual elements
+    Merkle,
+    /// Prefixes the elements with their number before hashing as in [HashMode::Flat], ruling out padding collisions
+    LengthPrefixed,
+}
+
+impl From<&str> for HashMode {
+    fn from(s: &str) -> Self {
+        match s {
+            "flat" => HashMode::Flat,
+            "merkle" => HashMode::Merkle,
+            "length-prefixed" => HashMode::LengthPrefixed,
+            _ => panic!("invalid hash mode"),
+        }
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Converts HashMode into a PyObject (Required for HashMode to be compatible with Python)
+impl IntoPy<PyObject> for HashMode {
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            HashMode::Flat => "flat".to_object(py),
+            HashMode::Merkle => "merkle".to_object(py),
+            HashMode::LengthPrefixed => "length-prefixed".to_object(py),
+        }
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Obtains HashMode from PyObject (Required for HashMode to be compatible with Python)
+impl<'source> FromPyObject<'source> for HashMode {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let trystr = <PyString as PyTryFrom>::try_from(ob)?;
+        let strval = trystr.to_string();
+        match strval.to_lowercase().as_str() {
+            "flat" => Ok(HashMode::Flat),
+            "merkle" => Ok(HashMode::Merkle),
+            "length-prefixed" => Ok(HashMode::LengthPrefixed),
+            _ => Err(PyValueError::new_err("Invalid value for HashMode")),
+        }
+    }
+}
+
 /// Parameters specific to a proving run
 #[derive(Debug, Copy, Args, Deserialize, Serialize, Clone, Default, PartialEq, PartialOrd)]
 pub struct RunArgs {
@@ -158,9 +210,22 @@ pub struct RunArgs {
     /// Flags whether outputs are public, private, hashed
     #[arg(long, default_value = "public")]
     pub output_visibility: Visibility,
-    /// Flags whether params are public, private, hashed
+    /// Flags whether params are public (committed to in the verifying key), private or hashed
     #[arg(long, default_value = "private")]
     pub param_visibility: Visibility,
+    /// How hashed inputs, params and outputs are committed to: flat, merkle, or length-prefixed
+    #[arg(long, default_value = "flat")]
+    #[serde(default)]
+    pub hash_mode: HashMode,
+    /// The depth of the Merkle tree of the dataset that inputs with `member` visibility belong to (see `build-dataset-tree`)
+    #[arg(long, default_value = "0")]
+    #[serde(default)]
+    pub membership_depth: usize,
+    /// Proves the model's accuracy on a test set: the one-hot labels of the rows are taken as an extra (last) input, and
+    /// the model's only output becomes the number of rows whose argmax matches their label
+    #[arg(long, default_value = "false", action = clap::ArgAction::Set)]
+    #[serde(default)]
+    pub prove_accuracy: bool,
     /// the number of constraints the circuit might use. If not specified, this will be calculated using a 'dummy layout' pass.
     #[arg(long)]
     pub allocated_constraints: Option<usize>,
@@ -204,6 +269,9 @@ pub enum Commands {
         /// proving arguments
         #[clap(flatten)]
         args: RunArgs,
+        /// The path to save a json report of the resources consumed by each node to (optional)
+        #[arg(long)]
+        cost_path: Option<PathBuf>,
     },
 
     #[cfg(feature = "render")]
@@ -241,6 +309,48 @@ pub enum Commands {
         batch_size: Option<usize>,
         #[arg(long)]
         settings_path: PathBuf,
+        /// Path to a sidecar .json file to store every node's integer and dequantized output in (optional)
+        #[arg(long)]
+        activations_path: Option<PathBuf>,
+        /// Path to an ElGamal keypair (see `gen-elgamal-keypair`) to encrypt encrypted inputs, params and outputs to,
+        /// if the settings encrypt them to the prover. If not specified a fresh keypair is generated (and stored in the witness).
+        #[arg(long, conflicts_with = "elgamal_pk_path")]
+        elgamal_keypair_path: Option<PathBuf>,
+        /// Path to the public key (.json) of the recipient to encrypt encrypted inputs, params and outputs to, if the
+        /// settings encrypt them to a recipient (see `gen-settings --elgamal-pk-path`). Overrides the public key of the
+        /// settings if it is a public input, and must match it if it is fixed in the circuit.
+        #[arg(long)]
+        elgamal_pk_path: Option<PathBuf>,
+        /// Path to the key (see `gen-cipher-key`) shared with the recipient of the symencrypted inputs, params and
+        /// outputs, which are all encrypted under it. If not specified a fresh key is generated (and stored in the witness).
+        #[arg(long)]
+        cipher_key_path: Option<PathBuf>,
+        /// Path to the signatures of the inputs with `signed` visibility (see `sign-inputs`), required if there are any
+        #[arg(long)]
+        input_signatures_path: Option<PathBuf>,
+        /// Path to the tree of the dataset the inputs with `member` visibility are rows of (see `build-dataset-tree`), required if there are any
+        #[arg(long)]
+        dataset_tree_path: Option<PathBuf>,
+    },
+
+    /// Compares the activations stored by gen-witness against the float activations of the model run by tract
+    #[command(arg_required_else_help = true)]
+    CompareActivations {
+        /// The path to the .json data file the activations were generated from
+        #[arg(short = 'D', long)]
+        data: PathBuf,
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: PathBuf,
+        /// The path to the activations .json file generated by gen-witness
+        #[arg(long)]
+        activations_path: PathBuf,
+        /// The path to the circuit settings .json file
+        #[arg(long)]
+        settings_path: PathBuf,
+        /// The path to save a json report of the error at each node to (optional)
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
     },
 
     /// Produces the proving hyperparameters, from run-args
@@ -255,6 +365,23 @@ pub enum Commands {
         /// proving arguments
         #[clap(flatten)]
         args: RunArgs,
+        /// Comma separated tolerances for each of the model's outputs, overriding the global tolerance (e.g. `1.0,abs:0.05`)
+        #[arg(long, value_delimiter = ',')]
+        output_tolerances: Vec<Tolerance>,
+        /// Comma separated visibilities for each of the model's inputs, overriding the global input visibility (e.g. `public,private`)
+        #[arg(long, value_delimiter = ',')]
+        input_visibilities: Vec<Visibility>,
+        /// Comma separated visibilities for each of the model's outputs, overriding the global output visibility (e.g. `hashed,public`)
+        #[arg(long, value_delimiter = ',')]
+        output_visibilities: Vec<Visibility>,
+        /// The path to the public key (.json) that values with `encrypted` visibility are encrypted to.
+        /// If unset, they are encrypted to a keypair generated by (and known to) the prover.
+        #[arg(long)]
+        elgamal_pk_path: Option<PathBuf>,
+        /// Fixes the public key of `elgamal_pk_path` in the circuit (committing to it in the verifying key),
+        /// rather than making it a public input
+        #[arg(long, requires = "elgamal_pk_path")]
+        elgamal_fixed_pk: bool,
     },
 
     /// Calibrates the proving scale, lookup bits and logrows from a circuit settings file.
@@ -273,6 +400,28 @@ pub enum Commands {
         #[arg(long = "target", default_value = "resources")]
         /// Target for calibration.
         target: CalibrationTarget,
+        /// If set, additionally searches for per-node scales that reduce logrows while keeping the
+        /// maximum absolute error on the (dequantized) outputs within this budget.
+        #[arg(long)]
+        max_error: Option<f32>,
+    },
+
+    /// Reports the error of the quantized model's outputs against the float model across a dataset
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(arg_required_else_help = true)]
+    Accuracy {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: PathBuf,
+        /// The path to the .json dataset file
+        #[arg(short = 'D', long)]
+        data: PathBuf,
+        /// The path to the circuit settings .json file
+        #[arg(long)]
+        settings_path: PathBuf,
+        /// The path to save a json report of the per-output error statistics to (optional)
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
     },
 
     /// Generates a dummy SRS
@@ -356,6 +505,21 @@ pub enum Commands {
         settings_path: PathBuf,
     },
 
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Estimates pk and vk sizes, proof size and proving time from circuit params, without running setup
+    #[command(arg_required_else_help = true)]
+    Estimate {
+        /// The path to load circuit params from
+        #[arg(long)]
+        settings_path: PathBuf,
+        /// The log_2 size of the local MSM and FFT benchmark used to calibrate the proving time estimate
+        #[arg(long, default_value = "12")]
+        bench_logrows: u32,
+        /// The path to save the estimate to as json (optional)
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
     #[cfg(not(target_arch = "wasm32"))]
     /// Fuzzes the proof pipeline with random inputs, random parameters, and random keys
     #[command(arg_required_else_help = true)]
@@ -542,6 +706,23 @@ pub enum Commands {
         srs_path: PathBuf,
     },
 
+    /// Verifies a chain of proofs, each of whose hashed outputs are the hashed inputs of the next, returning accept or reject
+    #[command(arg_required_else_help = true)]
+    VerifyChain {
+        /// The paths to the proof files, in the order of the chain
+        #[arg(long)]
+        proof_paths: Vec<PathBuf>,
+        /// The paths to load the circuit params of each proof from
+        #[arg(long)]
+        settings_paths: Vec<PathBuf>,
+        /// The paths to the verification key file of each proof
+        #[arg(long)]
+        vk_paths: Vec<PathBuf>,
+        /// The kzg srs path
+        #[arg(long)]
+        srs_path: PathBuf,
+    },
+
     /// Verifies an aggregate proof, returning accept or reject
     #[command(arg_required_else_help = true)]
     VerifyAggr {
@@ -591,4 +772,120 @@ pub enum Commands {
         #[arg(long)]
         proof_path: PathBuf,
     },
+
+    /// Generates an ElGamal keypair, used to encrypt inputs, params and outputs with `encrypted` visibility
+    #[command(name = "gen-elgamal-keypair", arg_required_else_help = true)]
+    GenElgamalKeypair {
+        /// Path to save the keypair (.json) to
+        #[arg(short = 'O', long)]
+        output: PathBuf,
+        /// Path to additionally save the public key (.json) to, e.g to share it with provers encrypting to it (optional)
+        #[arg(long)]
+        pk_output: Option<PathBuf>,
+    },
+    /// Decrypts the ciphertexts of the inputs, params and outputs with `encrypted` visibility contained in a proof's instances
+    #[command(name = "elgamal-decrypt", arg_required_else_help = true)]
+    ElgamalDecrypt {
+        /// The path to the proof file
+        #[arg(long)]
+        proof_path: PathBuf,
+        /// The path to the circuit settings file the proof was generated with
+        #[arg(short = 'S', long)]
+        settings_path: PathBuf,
+        /// The path to the ElGamal keypair the witness was encrypted to
+        #[arg(long)]
+        keypair_path: PathBuf,
+        /// Path to save the decrypted (quantized) values of the encrypted inputs, params and outputs to, as a .json file (optional)
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+    /// Generates a key for the Poseidon cipher, used to encrypt inputs, params and outputs with `symencrypted` visibility
+    #[command(name = "gen-cipher-key", arg_required_else_help = true)]
+    GenCipherKey {
+        /// Path to save the key (.json) to
+        #[arg(short = 'O', long)]
+        output: PathBuf,
+    },
+    /// Decrypts the ciphertexts of the inputs, params and outputs with `symencrypted` visibility contained in a proof's instances
+    #[command(name = "decrypt", arg_required_else_help = true)]
+    Decrypt {
+        /// The path to the proof file
+        #[arg(long)]
+        proof_path: PathBuf,
+        /// The path to the circuit settings file the proof was generated with
+        #[arg(short = 'S', long)]
+        settings_path: PathBuf,
+        /// The path to the key the witness was encrypted under (see `gen-witness --cipher-key-path`)
+        #[arg(long)]
+        key_path: PathBuf,
+        /// Path to save the decrypted (quantized) values of each symencrypted tensor to, as a .json file (optional)
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+    /// Generates a Schnorr keypair, used to sign inputs with `signed` visibility
+    #[command(name = "gen-schnorr-keypair", arg_required_else_help = true)]
+    GenSchnorrKeypair {
+        /// Path to save the keypair (.json) to
+        #[arg(short = 'O', long)]
+        output: PathBuf,
+    },
+    /// Signs the (quantized) inputs with `signed` visibility of a data file, e.g on behalf of the sensor or oracle producing them
+    #[command(name = "sign-inputs", arg_required_else_help = true)]
+    SignInputs {
+        /// The path to the .json data file
+        #[arg(short = 'D', long)]
+        data: PathBuf,
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: PathBuf,
+        /// The path to the circuit settings .json file
+        #[arg(short = 'S', long)]
+        settings_path: PathBuf,
+        /// The path to the Schnorr keypair to sign with
+        #[arg(long)]
+        keypair_path: PathBuf,
+        /// Path to save the signatures (.json) to, to be passed to gen-witness
+        #[arg(short = 'O', long, default_value = "signatures.json")]
+        output: PathBuf,
+    },
+    /// Builds the Poseidon Merkle tree committing to a dataset, whose root inputs with `member` visibility are proven against
+    #[command(name = "build-dataset-tree", arg_required_else_help = true)]
+    BuildDatasetTree {
+        /// The path to the .json dataset file, holding one (flattened) row per input
+        #[arg(short = 'D', long)]
+        data: PathBuf,
+        /// The path to the circuit settings .json file, whose scale the rows are quantized at
+        #[arg(short = 'S', long)]
+        settings_path: PathBuf,
+        /// Path to save the tree (.json) to, to be passed to gen-witness
+        #[arg(short = 'O', long, default_value = "dataset_tree.json")]
+        output: PathBuf,
+    },
+    /// Computes the hashes identifying a model: the hash of its hashed params (as it appears in proof instances), and
+    /// the hashes of its settings and verification key
+    #[command(arg_required_else_help = true)]
+    Fingerprint {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: PathBuf,
+        /// The path to the circuit settings .json file
+        #[arg(short = 'S', long)]
+        settings_path: PathBuf,
+        /// The path to the verification key file
+        #[arg(long)]
+        vk_path: PathBuf,
+        /// Path to save the fingerprint (.json) to
+        #[arg(short = 'O', long, default_value = "fingerprint.json")]
+        output: PathBuf,
+    },
+    /// Prints the KZG commitments to the inputs and outputs with `kzgcommit` visibility contained in a proof
+    #[command(name = "get-kzg-commitments", arg_required_else_help = true)]
+    GetKzgCommitments {
+        /// The path to the proof file
+        #[arg(long)]
+        proof_path: PathBuf,
+        /// The path to the circuit settings file the proof was generated with
+        #[arg(short = 'S', long)]
+        settings_path: PathBuf,
+    },
 }
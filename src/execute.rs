@@ -1,4 +1,8 @@
-use crate::circuit::CheckMode;
+use crate::circuit::modules::elgamal::{ElGamalGadget, ElGamalKeypair, ElGamalRecipient};
+use crate::circuit::modules::poseidon::cipher::PoseidonCipherKey;
+use crate::circuit::modules::poseidon::membership::DatasetTree;
+use crate::circuit::modules::schnorr::{SchnorrKeypair, SchnorrSignature};
+use crate::circuit::{CheckMode, Tolerance};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::commands::{CalibrationTarget, StrategyType};
 use crate::commands::{Cli, Commands, RunArgs};
@@ -8,10 +12,24 @@ use crate::eth::{
     setup_eth_backend, test_on_chain_inputs, verify_proof_via_solidity,
     verify_proof_with_data_attestation,
 };
+use crate::fieldutils::{felt_to_i128, i128_to_felt};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graph::accuracy::AccuracyReport;
+use crate::graph::activations::Activations;
+use crate::graph::chain::check_hash_chain;
+use crate::graph::fingerprint::{hash_bytes, ModelFingerprint};
 use crate::graph::input::GraphInput;
+use crate::graph::modules::{
+    ModulePoseidonCipher, ModulePoseidonMembership, ModuleSchnorr, CIPHER, ELGAMAL,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::graph::Visibility;
-use crate::graph::{scale_to_multiplier, GraphCircuit, GraphSettings, GraphWitness, Model};
+use crate::graph::{
+    quantize_float, scale_to_multiplier, GraphCircuit, GraphSettings, GraphWitness, Model,
+    VarVisibility,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::pfsys::estimate::{CircuitShape, Estimate, MicroBenchmark};
 use crate::pfsys::evm::aggregation::{AggregationCircuit, PoseidonTranscript};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::pfsys::evm::evm_verify;
@@ -20,10 +38,14 @@ use crate::pfsys::evm::{
     aggregation::gen_aggregation_evm_verifier, single::gen_evm_verifier, DeploymentCode, YulCode,
 };
 use crate::pfsys::{create_keys, load_srs, load_vk, save_params, save_pk, Snark, TranscriptType};
-use crate::pfsys::{create_proof_circuit, gen_srs, save_vk, verify_proof_circuit};
+use crate::pfsys::{
+    create_proof_circuit, gen_srs, read_advice_commitments, save_vk, verify_proof_circuit,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use gag::Gag;
 use halo2_proofs::dev::VerifyFailure;
+#[cfg(not(target_arch = "wasm32"))]
+use halo2_proofs::plonk::ConstraintSystem;
 use halo2_proofs::plonk::{Circuit, ProvingKey, VerifyingKey};
 use halo2_proofs::poly::commitment::Params;
 use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
@@ -44,7 +66,7 @@ use instant::Instant;
 use itertools::Itertools;
 #[cfg(not(target_arch = "wasm32"))]
 use log::debug;
-use log::{info, trace};
+use log::{error, info, trace, warn};
 #[cfg(feature = "render")]
 use plotters::prelude::*;
 #[cfg(not(target_arch = "wasm32"))]
@@ -65,6 +87,8 @@ use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Duration;
 use thiserror::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use tract_onnx::prelude::{Graph, TypedFact, TypedOp};
 
 /// A wrapper for tensor related errors.
 #[derive(Debug, Error)]
@@ -72,6 +96,12 @@ pub enum ExecutionError {
     /// Shape mismatch in a operation
     #[error("verification failed")]
     VerifyError(Vec<VerifyFailure>),
+    /// A proof of a chain failed to verify
+    #[error("proof {0} of the chain failed to verify")]
+    ChainVerifyError(usize),
+    /// A chain wasn't given settings and a verification key for each proof
+    #[error("got {0} proofs, {1} settings and {2} verification keys for the chain")]
+    ChainLength(usize, usize, usize),
 }
 
 /// Run an ezkl command with given args
@@ -95,7 +125,11 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             settings_path,
         ),
         Commands::GenSrs { srs_path, logrows } => gen_srs_cmd(srs_path, logrows as u32),
-        Commands::Table { model, args } => table(model, args),
+        Commands::Table {
+            model,
+            args,
+            cost_path,
+        } => table(model, args, cost_path),
         #[cfg(feature = "render")]
         Commands::RenderCircuit {
             model,
@@ -106,14 +140,41 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             model,
             settings_path,
             args,
-        } => gen_circuit_settings(model, settings_path, args),
+            output_tolerances,
+            input_visibilities,
+            output_visibilities,
+            elgamal_pk_path,
+            elgamal_fixed_pk,
+        } => {
+            let elgamal_recipient = match elgamal_pk_path {
+                None => ElGamalRecipient::Prover,
+                Some(path) => {
+                    let pk = ElGamalKeypair::load_pk(&path)?;
+                    if elgamal_fixed_pk {
+                        ElGamalRecipient::FixedPublicKey(pk)
+                    } else {
+                        ElGamalRecipient::PublicKey(pk)
+                    }
+                }
+            };
+            gen_circuit_settings(
+                model,
+                settings_path,
+                args,
+                output_tolerances,
+                input_visibilities,
+                output_visibilities,
+                elgamal_recipient,
+            )
+        }
         #[cfg(not(target_arch = "wasm32"))]
         Commands::CalibrateSettings {
             model,
             settings_path,
             data,
             target,
-        } => calibrate(model, data, settings_path, target),
+            max_error,
+        } => calibrate(model, data, settings_path, target, max_error),
         Commands::GenWitness {
             data,
             model,
@@ -121,7 +182,34 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             scale,
             batch_size,
             settings_path,
-        } => gen_witness(model, data, Some(output), scale, batch_size, settings_path).map(|_| ()),
+            activations_path,
+            elgamal_keypair_path,
+            elgamal_pk_path,
+            cipher_key_path,
+            input_signatures_path,
+            dataset_tree_path,
+        } => gen_witness(
+            model,
+            data,
+            Some(output),
+            scale,
+            batch_size,
+            settings_path,
+            activations_path,
+            elgamal_keypair_path,
+            elgamal_pk_path,
+            cipher_key_path,
+            input_signatures_path,
+            dataset_tree_path,
+        )
+        .map(|_| ()),
+        Commands::CompareActivations {
+            data,
+            model,
+            activations_path,
+            settings_path,
+            output,
+        } => compare_activations(model, data, activations_path, settings_path, output),
         Commands::Mock {
             model,
             witness,
@@ -187,6 +275,19 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             pk_path,
         } => setup(model, srs_path, settings_path, vk_path, pk_path),
         #[cfg(not(target_arch = "wasm32"))]
+        Commands::Accuracy {
+            model,
+            data,
+            settings_path,
+            output,
+        } => accuracy(model, data, settings_path, output).map(|_| ()),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Estimate {
+            settings_path,
+            bench_logrows,
+            output,
+        } => estimate(settings_path, bench_logrows, output),
+        #[cfg(not(target_arch = "wasm32"))]
         Commands::Prove {
             witness,
             model,
@@ -240,6 +341,12 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             vk_path,
             srs_path,
         } => verify(proof_path, settings_path, vk_path, srs_path),
+        Commands::VerifyChain {
+            proof_paths,
+            settings_paths,
+            vk_paths,
+            srs_path,
+        } => verify_chain(proof_paths, settings_paths, vk_paths, srs_path),
         Commands::VerifyAggr {
             proof_path,
             vk_path,
@@ -264,6 +371,67 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             .await
         }
         Commands::PrintProofHex { proof_path } => print_proof_hex(proof_path),
+        Commands::GetKzgCommitments {
+            proof_path,
+            settings_path,
+        } => {
+            let commitments = kzg_commitments(proof_path, settings_path)?;
+            for (i, tensor_commitments) in commitments.iter().enumerate() {
+                info!("committed tensor {}: {:?}", i, tensor_commitments);
+            }
+            Ok(())
+        }
+        Commands::GenElgamalKeypair { output, pk_output } => gen_elgamal_keypair(output, pk_output),
+        Commands::ElgamalDecrypt {
+            proof_path,
+            settings_path,
+            keypair_path,
+            output,
+        } => {
+            let decrypted = elgamal_decrypt(proof_path, settings_path, keypair_path)?;
+            for (i, values) in decrypted.iter().enumerate() {
+                info!("decrypted group {}: {:?}", i, values);
+            }
+            if let Some(output) = output {
+                serde_json::to_writer(&File::create(output)?, &decrypted)?;
+            }
+            Ok(())
+        }
+        Commands::GenCipherKey { output } => gen_cipher_key(output),
+        Commands::Decrypt {
+            proof_path,
+            settings_path,
+            key_path,
+            output,
+        } => {
+            let decrypted = decrypt(proof_path, settings_path, key_path)?;
+            for (i, values) in decrypted.iter().enumerate() {
+                info!("decrypted tensor {}: {:?}", i, values);
+            }
+            if let Some(output) = output {
+                serde_json::to_writer(&File::create(output)?, &decrypted)?;
+            }
+            Ok(())
+        }
+        Commands::GenSchnorrKeypair { output } => gen_schnorr_keypair(output),
+        Commands::SignInputs {
+            data,
+            model,
+            settings_path,
+            keypair_path,
+            output,
+        } => sign_inputs(model, data, settings_path, keypair_path, Some(output)).map(|_| ()),
+        Commands::BuildDatasetTree {
+            data,
+            settings_path,
+            output,
+        } => build_dataset_tree(data, settings_path, Some(output)).map(|_| ()),
+        Commands::Fingerprint {
+            model,
+            settings_path,
+            vk_path,
+            output,
+        } => fingerprint(model, settings_path, vk_path, Some(output)).map(|_| ()),
     }
 }
 
@@ -396,9 +564,18 @@ pub(crate) fn gen_srs_cmd(srs_path: PathBuf, logrows: u32) -> Result<(), Box<dyn
     Ok(())
 }
 
-pub(crate) fn table(model: PathBuf, run_args: RunArgs) -> Result<(), Box<dyn Error>> {
+pub(crate) fn table(
+    model: PathBuf,
+    run_args: RunArgs,
+    cost_path: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
     let model = Model::from_run_args(&run_args, &model)?;
     info!("\n {}", model.table_nodes());
+    let layout = model.dummy_layout(&run_args, &model.graph.input_shapes())?;
+    info!("\n {}", layout.table_costs());
+    if let Some(cost_path) = cost_path {
+        serde_json::to_writer(&File::create(cost_path)?, &layout.node_costs)?;
+    }
     Ok(())
 }
 
@@ -409,6 +586,12 @@ pub(crate) fn gen_witness(
     scale: Option<u32>,
     batch_size: Option<usize>,
     settings_path: PathBuf,
+    activations_path: Option<PathBuf>,
+    elgamal_keypair_path: Option<PathBuf>,
+    elgamal_pk_path: Option<PathBuf>,
+    cipher_key_path: Option<PathBuf>,
+    input_signatures_path: Option<PathBuf>,
+    dataset_tree_path: Option<PathBuf>,
 ) -> Result<GraphWitness, Box<dyn Error>> {
     // these aren't real values so the sanity checks are mostly meaningless
 
@@ -427,9 +610,47 @@ pub(crate) fn gen_witness(
     let data = GraphInput::from_path(data)?;
     circuit.load_inputs(&data.input_data);
 
+    let elgamal_keypair = match elgamal_keypair_path {
+        Some(path) => Some(ElGamalKeypair::load(&path)?),
+        None => None,
+    };
+    if let Some(path) = elgamal_pk_path {
+        let pk = ElGamalKeypair::load_pk(&path)?;
+        circuit.settings.elgamal_recipient = match circuit.settings.elgamal_recipient {
+            ElGamalRecipient::PublicKey(_) => ElGamalRecipient::PublicKey(pk),
+            ElGamalRecipient::FixedPublicKey(fixed) if fixed == pk => {
+                ElGamalRecipient::FixedPublicKey(fixed)
+            }
+            ElGamalRecipient::FixedPublicKey(_) => {
+                return Err("the public key differs from the one fixed in the circuit".into())
+            }
+            ElGamalRecipient::Prover => {
+                return Err("the settings encrypt to the prover rather than to a public key".into())
+            }
+        };
+    }
+    // the prover only needs the recipient's public key, which is in the settings
+    if elgamal_keypair.is_some() && circuit.settings.elgamal_recipient.pk().is_some() {
+        return Err(
+            "the settings encrypt to a recipient's public key, pass it rather than a keypair"
+                .into(),
+        );
+    }
+    if let Some(path) = cipher_key_path {
+        circuit.cipher_key = Some(PoseidonCipherKey::load(&path)?);
+    } else if !circuit.settings.symencrypted_lens.is_empty() {
+        warn!("no cipher key supplied, the symencrypted tensors are encrypted under a fresh key only held in the witness");
+    }
+    if let Some(path) = input_signatures_path {
+        circuit.input_signatures = SchnorrSignature::load_all(&path)?;
+    }
+    if let Some(path) = dataset_tree_path {
+        circuit.dataset_tree = Some(DatasetTree::load(&path)?);
+    }
+
     let start_time = Instant::now();
 
-    let res = circuit.forward()?;
+    let res = circuit.forward_with_elgamal_keypair(elgamal_keypair.as_ref())?;
 
     trace!(
         "witness generation (B={:?}) took {:?}",
@@ -468,16 +689,61 @@ pub(crate) fn gen_witness(
     if let Some(output_path) = output {
         serde_json::to_writer(&File::create(output_path)?, &witness)?;
     }
+
+    if let Some(activations_path) = activations_path {
+        let names = Model::node_names(&mut File::open(&model_path)?, &circuit_settings.run_args)?;
+        Activations::new(&circuit.model, &circuit.inputs, &names)?.save(&activations_path)?;
+    }
     Ok(witness)
 }
 
+/// Compares the activations stored by [gen_witness] against the float activations of the model run by tract
+pub(crate) fn compare_activations(
+    model_path: PathBuf,
+    data: PathBuf,
+    activations_path: PathBuf,
+    settings_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let circuit_settings = GraphSettings::load(&settings_path)?;
+    let activations = Activations::load(&activations_path)?;
+    let data = GraphInput::from_path(data)?;
+
+    let float_activations = Model::float_activations(
+        &mut File::open(&model_path)?,
+        &circuit_settings.run_args,
+        &data.input_data,
+    )?;
+
+    let errors = activations.compare(&float_activations);
+    info!("\n {}", Activations::table_errors(&errors));
+
+    if let Some(output_path) = output {
+        serde_json::to_writer(&File::create(output_path)?, &errors)?;
+    }
+    Ok(())
+}
+
 /// Generate a circuit settings file
 pub(crate) fn gen_circuit_settings(
     model_path: PathBuf,
     params_output: PathBuf,
     run_args: RunArgs,
+    output_tolerances: Vec<Tolerance>,
+    input_visibilities: Vec<Visibility>,
+    output_visibilities: Vec<Visibility>,
+    elgamal_recipient: ElGamalRecipient,
 ) -> Result<(), Box<dyn Error>> {
-    let circuit = GraphCircuit::from_run_args(&run_args, &model_path, CheckMode::SAFE)?;
+    let settings = GraphSettings {
+        run_args,
+        output_tolerances: output_tolerances.into_iter().enumerate().collect(),
+        input_visibilities,
+        output_visibilities,
+        elgamal_recipient,
+        ..Default::default()
+    };
+    let model = Model::from_settings(&settings, &model_path)?;
+    let circuit = GraphCircuit::new(model, run_args, CheckMode::SAFE)?;
     let params = circuit.settings;
     params.save(&params_output).map_err(Box::<dyn Error>::from)
 }
@@ -505,6 +771,7 @@ pub(crate) fn calibrate(
     data: PathBuf,
     settings_path: PathBuf,
     target: CalibrationTarget,
+    max_error: Option<f32>,
 ) -> Result<(), Box<dyn Error>> {
     let data = GraphInput::from_path(data)?;
     // load the pre-generated settings
@@ -517,7 +784,7 @@ pub(crate) fn calibrate(
     pb.set_message("Calibrating...");
     // we load the model to get the input and output shapes
     let _r = Gag::stdout().unwrap();
-    let model = Model::from_run_args(&run_args, &model_path).unwrap();
+    let model = Model::from_settings(&settings, &model_path).unwrap();
     std::mem::drop(_r);
 
     let chunks = data
@@ -548,12 +815,18 @@ pub(crate) fn calibrate(
                     local_run_args.output_visibility = Visibility::Public;
 
                     // we need to set the output visibility to public to avoid dummy pass
-                    let mut circuit = GraphCircuit::from_run_args(
-                        &local_run_args,
-                        &model_path,
-                        CheckMode::UNSAFE,
+                    let model = Model::new_from_settings(
+                        &mut File::open(&model_path).map_err(|_| "failed to open model")?,
+                        &GraphSettings {
+                            run_args: local_run_args,
+                            input_visibilities: vec![],
+                            output_visibilities: vec![],
+                            ..settings.clone()
+                        },
                     )
-                    .map_err(|_| "failed to create circuit from run args")?;
+                    .map_err(|_| "failed to load model")?;
+                    let mut circuit = GraphCircuit::new(model, local_run_args, CheckMode::UNSAFE)
+                        .map_err(|_| "failed to create circuit from run args")?;
                     circuit.load_inputs(&chunk.input_data);
 
                     loop {
@@ -579,6 +852,7 @@ pub(crate) fn calibrate(
                     let found_settings = GraphSettings {
                         run_args: found_run_args,
                         required_lookups: circuit.settings.required_lookups,
+                        scale_overrides: circuit.settings.scale_overrides,
                         ..settings.clone()
                     };
 
@@ -611,7 +885,7 @@ pub(crate) fn calibrate(
     debug!("Found {} sets of parameters", found_params.len());
 
     // now find the best params according to the target
-    match target {
+    let mut best_params = match target {
         CalibrationTarget::Resources => {
             let mut param_iterator = found_params.iter().sorted_by_key(|p| p.run_args.logrows);
 
@@ -619,32 +893,329 @@ pub(crate) fn calibrate(
 
             // pick the ones that have the minimum logrows but also the largest scale:
             // this is the best tradeoff between resource usage and accuracy
-            let best_params = found_params
+            found_params
                 .iter()
                 .filter(|p| p.run_args.logrows == min_logrows)
                 .max_by_key(|p| p.run_args.scale)
-                .unwrap();
-
-            best_params.save(&settings_path)?;
+                .unwrap()
+                .clone()
         }
         CalibrationTarget::Accuracy => {
-            let param_iterator = found_params.iter().sorted_by_key(|p| p.run_args.scale);
+            // pick the ones with the smallest error against the float model, breaking ties with the smallest logrows:
+            // this is the best tradeoff between resource usage and accuracy
+            let mut errors = vec![];
+            for params in &found_params {
+                let max_abs_error = accuracy_report(&model_path, &chunks, params)?.max_abs_error();
+                debug!(
+                    "scale {} has a max absolute error of {}",
+                    params.run_args.scale, max_abs_error
+                );
+                errors.push((max_abs_error, params));
+            }
+            errors
+                .into_iter()
+                .min_by(|(a, p), (b, q)| {
+                    a.total_cmp(b)
+                        .then(p.run_args.logrows.cmp(&q.run_args.logrows))
+                })
+                .unwrap()
+                .1
+                .clone()
+        }
+    };
 
-            let max_scale = param_iterator.last().unwrap().run_args.scale;
+    if let Some(max_error) = max_error {
+        // the most precise settings found serve as the reference for the per-node search
+        let reference = found_params
+            .iter()
+            .max_by_key(|p| p.run_args.scale)
+            .unwrap();
+        best_params =
+            calibrate_scale_overrides(&model_path, &chunks, &best_params, reference, max_error)?;
+    }
 
-            // pick the ones that have the max scale but also the smallest logrows:
-            // this is the best tradeoff between resource usage and accuracy
-            let best_params = found_params
+    // the rows the nodes are laid out in depend on the scales and logrows settled on
+    let model = Model::from_settings(&best_params, &model_path)?;
+    best_params.node_offsets = model
+        .dummy_layout(&best_params.run_args, &model.graph.input_shapes())?
+        .node_offsets;
+
+    best_params.save(&settings_path)?;
+
+    debug!("Saved parameters.");
+
+    Ok(())
+}
+
+/// Runs the dataset through the float model (with tract) and through the circuit's forward pass,
+/// and measures the error of the (dequantized) circuit outputs.
+#[cfg(not(target_arch = "wasm32"))]
+fn accuracy_report(
+    model_path: &PathBuf,
+    chunks: &[GraphInput],
+    settings: &GraphSettings,
+) -> Result<AccuracyReport, Box<dyn Error>> {
+    let model = Model::from_settings(settings, model_path)?;
+    let output_shapes = model.graph.output_shapes();
+    let output_scales = model.graph.get_output_scales();
+
+    let res: Result<Vec<(Vec<Vec<f32>>, Vec<Vec<f32>>)>, String> = chunks
+        .par_iter()
+        .map(|chunk| {
+            let mut circuit =
+                GraphCircuit::new_from_settings(model.clone(), settings.clone(), CheckMode::UNSAFE)
+                    .map_err(|e| e.to_string())?;
+            circuit.load_inputs(&chunk.input_data);
+            let quantized = circuit
+                .model
+                .forward(&circuit.inputs)
+                .map_err(|e| e.to_string())?
+                .outputs
                 .iter()
-                .filter(|p| p.run_args.scale == max_scale)
-                .min_by_key(|p| p.run_args.logrows)
-                .unwrap();
+                .zip(&output_scales)
+                .map(|(o, scale)| {
+                    let mult = scale_to_multiplier(*scale) as f32;
+                    o.iter().map(|x| *x as f32 / mult).collect_vec()
+                })
+                .collect_vec();
+
+            let float = Model::float_forward(
+                &mut File::open(model_path).map_err(|e| e.to_string())?,
+                &settings.run_args,
+                &chunk.input_data,
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok((quantized, float))
+        })
+        .collect();
+
+    let (quantized, float): (Vec<_>, Vec<_>) = res?.into_iter().unzip();
+    Ok(AccuracyReport::new(&quantized, &float, &output_shapes))
+}
+
+/// Reports the error of the quantized model's outputs against the float model across a dataset
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn accuracy(
+    model_path: PathBuf,
+    data: PathBuf,
+    settings_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<AccuracyReport, Box<dyn Error>> {
+    let data = GraphInput::from_path(data)?;
+    let settings = GraphSettings::load(&settings_path)?;
+
+    let pb = init_spinner();
+    pb.set_message("Measuring accuracy...");
+    let _r = Gag::stdout().unwrap();
+    let model = Model::from_settings(&settings, &model_path)?;
+    let chunks =
+        data.split_into_batches(settings.run_args.batch_size, model.graph.input_shapes())?;
+    let report = accuracy_report(&model_path, &chunks, &settings);
+    std::mem::drop(_r);
+    pb.finish_with_message("Accuracy measured.");
+    let report = report?;
+
+    info!("\n {}", report.as_table());
+    if let Some(output_path) = output {
+        report.save(&output_path)?;
+    }
+    Ok(report)
+}
+
+/// Runs the calibration data through a circuit built from `settings` and the parsed Onnx graph, returning the converged
+/// settings (bits, logrows, lookups) and the dequantized outputs for each batch.
+#[cfg(not(target_arch = "wasm32"))]
+fn calibrate_batches(
+    typed: &Graph<TypedFact, Box<dyn TypedOp>>,
+    chunks: &[GraphInput],
+    settings: &GraphSettings,
+) -> Result<(GraphSettings, Vec<Vec<Vec<f32>>>), Box<dyn Error>> {
+    let mut run_args = settings.run_args;
+    run_args.allocated_constraints = Some(settings.num_constraints);
+    // we don't want to calculate the params here
+    run_args.input_visibility = Visibility::Public;
+    run_args.param_visibility = Visibility::Public;
+    run_args.output_visibility = Visibility::Public;
+
+    let model = Model::from_typed_graph(
+        typed,
+        &GraphSettings {
+            run_args,
+            input_visibilities: vec![],
+            output_visibilities: vec![],
+            ..settings.clone()
+        },
+    )?;
+
+    let _r = Gag::stdout().unwrap();
+    let res: Result<Vec<(GraphSettings, Vec<Vec<f32>>)>, String> = chunks
+        .par_iter()
+        .map(|chunk| {
+            let mut circuit = GraphCircuit::new(model.clone(), run_args, CheckMode::UNSAFE)
+                .map_err(|e| e.to_string())?;
+            circuit.load_inputs(&chunk.input_data);
+
+            loop {
+                // ensures we have converged
+                let params_before = circuit.settings.clone();
+                circuit.calibrate().map_err(|e| e.to_string())?;
+                if params_before == circuit.settings {
+                    break;
+                }
+            }
+
+            let outputs = circuit
+                .model
+                .forward(&circuit.inputs)
+                .map_err(|e| e.to_string())?
+                .outputs;
+            let dequantized = outputs
+                .iter()
+                .zip(circuit.model.graph.get_output_scales())
+                .map(|(o, scale)| {
+                    let mult = scale_to_multiplier(scale) as f32;
+                    o.iter().map(|x| *x as f32 / mult).collect_vec()
+                })
+                .collect_vec();
 
-            best_params.save(&settings_path)?;
+            Ok((circuit.settings, dequantized))
+        })
+        .collect();
+    std::mem::drop(_r);
+
+    let (found, outputs): (Vec<GraphSettings>, Vec<Vec<Vec<f32>>>) = res?.into_iter().unzip();
+    // pick the one with the largest logrows
+    let found = found
+        .into_iter()
+        .max_by_key(|p| p.run_args.logrows)
+        .unwrap();
+
+    Ok((
+        GraphSettings {
+            run_args: RunArgs {
+                bits: found.run_args.bits,
+                logrows: found.run_args.logrows,
+                ..settings.run_args
+            },
+            required_lookups: found.required_lookups,
+            ..settings.clone()
+        },
+        outputs,
+    ))
+}
+
+/// Greedily lowers the scale of individual nodes, keeping each reduction as long as the
+/// maximum absolute output error (relative to `reference`) stays within `max_error` and logrows don't grow.
+#[cfg(not(target_arch = "wasm32"))]
+fn calibrate_scale_overrides(
+    model_path: &PathBuf,
+    chunks: &[GraphInput],
+    settings: &GraphSettings,
+    reference: &GraphSettings,
+    max_error: f32,
+) -> Result<GraphSettings, Box<dyn Error>> {
+    // the settings only differ in their scales, so every trial can share a single parse of the model
+    let typed = Model::load_typed_onnx(&mut File::open(model_path)?, &settings.run_args)?;
+
+    let (_, reference_outputs) = calibrate_batches(&typed, chunks, reference)?;
+
+    let max_abs_error = |outputs: &Vec<Vec<Vec<f32>>>| {
+        outputs
+            .iter()
+            .flatten()
+            .flatten()
+            .zip(reference_outputs.iter().flatten().flatten())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0_f32, f32::max)
+    };
+
+    let (mut best, outputs) = calibrate_batches(&typed, chunks, settings)?;
+    if max_abs_error(&outputs) > max_error {
+        return Err(format!(
+            "calibrated settings already exceed the maximum output error of {}",
+            max_error
+        )
+        .into());
+    }
+
+    let model = Model::from_typed_graph(&typed, settings)?;
+    let candidates = model
+        .graph
+        .nodes
+        .iter()
+        .filter(|(_, n)| !n.is_input() && !n.inputs().is_empty())
+        .map(|(idx, _)| *idx)
+        .collect_vec();
+
+    let pb = init_spinner();
+    for idx in candidates {
+        let start_scale = best
+            .scale_overrides
+            .get(&idx)
+            .cloned()
+            .unwrap_or(best.run_args.scale);
+        for scale in (1..start_scale).rev() {
+            pb.set_message(format!("Calibrating node {} with scale {}", idx, scale));
+            let mut trial = best.clone();
+            trial.scale_overrides.insert(idx, scale);
+            match calibrate_batches(&typed, chunks, &trial) {
+                Ok((trial, outputs))
+                    if max_abs_error(&outputs) <= max_error
+                        && trial.run_args.logrows <= best.run_args.logrows =>
+                {
+                    debug!("node {} accepted scale {}", idx, scale);
+                    best = trial;
+                }
+                _ => break,
+            }
         }
     }
+    pb.finish_with_message("Per-node calibration done.");
 
-    debug!("Saved parameters.");
+    Ok(best)
+}
+
+/// Estimates key sizes, proof size and proving time from the circuit settings
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn estimate(
+    settings_path: PathBuf,
+    bench_logrows: u32,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let logrows = settings.run_args.logrows;
+
+    let mut cs = ConstraintSystem::<Fr>::default();
+    let _ = GraphCircuit::configure_with_params(&mut cs, settings);
+    let shape = CircuitShape::from_cs(&cs, logrows);
+
+    let pb = init_spinner();
+    pb.set_message("Benchmarking...");
+    let benchmark = MicroBenchmark::run(bench_logrows.min(logrows));
+    pb.finish_with_message("Benchmarking Done.");
+
+    let estimate = Estimate::new(&shape, &benchmark);
+
+    info!("circuit shape: {:?}", estimate.shape);
+    info!(
+        "pk size: {:.2} MB, vk size: {:.2} KB, proof size: {} bytes",
+        estimate.pk_bytes as f64 / 1e6,
+        estimate.vk_bytes as f64 / 1e3,
+        estimate.proof_bytes
+    );
+    info!(
+        "memory needed for pk generation: {:.2} MB",
+        estimate.keygen_memory_bytes as f64 / 1e6
+    );
+    info!(
+        "proving time: {:.2}s (excluding witness generation)",
+        estimate.proving_secs
+    );
+
+    if let Some(output) = output {
+        serde_json::to_writer(&File::create(output)?, &estimate)?;
+    }
 
     Ok(())
 }
@@ -667,10 +1238,15 @@ pub(crate) fn mock(
 
     let prover = MockProver::run(circuit.settings.run_args.logrows, &circuit, public_inputs)
         .map_err(Box::<dyn Error>::from)?;
-    prover.assert_satisfied();
-    prover
-        .verify()
-        .map_err(|e| Box::<dyn Error>::from(ExecutionError::VerifyError(e)))?;
+    if let Err(failures) = prover.verify() {
+        for failure in &failures {
+            error!("{}", failure);
+        }
+        for failure in circuit.locate_failures(&failures) {
+            error!("{}", failure);
+        }
+        return Err(Box::new(ExecutionError::VerifyError(failures)));
+    }
     Ok(())
 }
 
@@ -682,6 +1258,256 @@ pub(crate) fn print_proof_hex(proof_path: PathBuf) -> Result<(), Box<dyn Error>>
     info!("{}", hex::encode(proof.proof));
     Ok(())
 }
+/// Reads the commitments to the inputs and outputs with [crate::graph::Visibility::KZGCommit] from a proof (inputs first).
+/// A tensor spanning several columns maps onto several commitments. Two proofs (with the same logrows)
+/// committing to the same tensor contain the same commitments.
+pub(crate) fn kzg_commitments(
+    proof_path: PathBuf,
+    settings_path: PathBuf,
+) -> Result<Vec<Vec<G1Affine>>, Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path, None, None)?;
+    let columns = GraphCircuit::kzg_commit_columns(&settings)?;
+    let num_commitments = columns.iter().flatten().max().map_or(0, |i| i + 1);
+
+    let commitments: Vec<G1Affine> = match proof.transcript_type {
+        TranscriptType::Blake => {
+            read_advice_commitments::<_, Challenge255<_>, Blake2bRead<_, _, _>>(
+                &proof.proof,
+                num_commitments,
+            )?
+        }
+        TranscriptType::EVM => read_advice_commitments::<_, _, EvmTranscript<G1Affine, _, _, _>>(
+            &proof.proof,
+            num_commitments,
+        )?,
+        TranscriptType::Poseidon => read_advice_commitments::<
+            _,
+            _,
+            PoseidonTranscript<NativeLoader, _>,
+        >(&proof.proof, num_commitments)?,
+    };
+
+    Ok(columns
+        .iter()
+        .map(|cols| cols.iter().map(|c| commitments[*c]).collect())
+        .collect())
+}
+
+/// Generates an ElGamal keypair and saves it to `output`, and its public key alone to `pk_output` if supplied
+pub(crate) fn gen_elgamal_keypair(
+    output: PathBuf,
+    pk_output: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let keypair = ElGamalKeypair::gen_random(&mut rand::thread_rng());
+    info!("generated ElGamal public key: {:?}", keypair.pk);
+    if let Some(pk_output) = pk_output {
+        keypair.save_pk(&pk_output)?;
+    }
+    keypair.save(&output)
+}
+
+/// Generates a key for the Poseidon cipher and saves it to `output`
+pub(crate) fn gen_cipher_key(output: PathBuf) -> Result<(), Box<dyn Error>> {
+    let key = PoseidonCipherKey::gen_random(&mut rand::thread_rng());
+    info!(
+        "generated cipher key with hash: {:?}",
+        ModulePoseidonCipher::hash_key(key.0)
+    );
+    key.save(&output)
+}
+
+/// Generates a Schnorr keypair and saves it to `output`
+pub(crate) fn gen_schnorr_keypair(output: PathBuf) -> Result<(), Box<dyn Error>> {
+    let keypair = SchnorrKeypair::gen_random(&mut rand::thread_rng());
+    info!("generated Schnorr public key: {:?}", keypair.pk);
+    keypair.save(&output)
+}
+
+/// Signs the quantized inputs with [Visibility::Signed] of a data file with the keypair of `keypair_path`.
+/// Returns the signature of each signed input, in order.
+pub(crate) fn sign_inputs(
+    model_path: PathBuf,
+    data: PathBuf,
+    settings_path: PathBuf,
+    keypair_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<Vec<SchnorrSignature>, Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let keypair = SchnorrKeypair::load(&keypair_path)?;
+
+    let mut circuit = GraphCircuit::from_settings(&settings, &model_path, CheckMode::UNSAFE)?;
+    let data = GraphInput::from_path(data)?;
+    circuit.load_inputs(&data.input_data);
+
+    let visibility = VarVisibility::from_settings(&settings)?;
+    let signatures = circuit
+        .inputs
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| visibility.input_at(*i).is_signed())
+        .map(|(_, input)| {
+            let msg = input.iter().map(|x| i128_to_felt::<Fr>(*x)).collect();
+            ModuleSchnorr::sign(&keypair, &mut rand::thread_rng(), msg)
+        })
+        .collect_vec();
+
+    if signatures.is_empty() {
+        return Err("the circuit does not have any signed inputs".into());
+    }
+
+    if let Some(output) = output {
+        SchnorrSignature::save_all(&signatures, &output)?;
+    }
+
+    Ok(signatures)
+}
+
+/// Builds the tree committing to the rows of the dataset of `data` (a .json list of flattened rows), quantized at the
+/// scale of the settings of `settings_path`. Returns the tree, whose root is a public input of proofs over inputs with
+/// [Visibility::Member].
+pub(crate) fn build_dataset_tree(
+    data: PathBuf,
+    settings_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<DatasetTree, Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let rows: Vec<Vec<f32>> = serde_json::from_reader(File::open(data)?)?;
+    if rows.is_empty() {
+        return Err("the dataset does not have any rows".into());
+    }
+
+    let rows = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|x| {
+                    Ok(i128_to_felt::<Fr>(quantize_float(
+                        x,
+                        0.0,
+                        settings.run_args.scale,
+                    )?))
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let tree = ModulePoseidonMembership::tree(rows);
+
+    info!("dataset root: {:?}", tree.root());
+    info!(
+        "dataset depth: {} (to be set as the membership depth of the settings)",
+        tree.depth()
+    );
+
+    if let Some(output) = output {
+        tree.save(&output)?;
+    }
+
+    Ok(tree)
+}
+
+/// Computes the hashes identifying a model (see [ModelFingerprint]), the settings being hashed in their canonical
+/// serialization and the verification key as stored.
+pub(crate) fn fingerprint(
+    model: PathBuf,
+    settings_path: PathBuf,
+    vk_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<ModelFingerprint, Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let circuit = GraphCircuit::from_settings(&settings, &model, CheckMode::UNSAFE)?;
+
+    let fingerprint = ModelFingerprint {
+        params_hash: circuit.params_hash()?,
+        settings_hash: hash_bytes(&serde_json::to_vec(&settings)?)?,
+        vk_hash: hash_bytes(&std::fs::read(vk_path)?)?,
+    };
+
+    match fingerprint.params_hash {
+        Some(params_hash) => info!("params hash: {:?}", params_hash),
+        None => info!("none of the params are hashed"),
+    }
+    info!("settings hash: {:?}", fingerprint.settings_hash);
+    info!("vk hash: {:?}", fingerprint.vk_hash);
+
+    if let Some(output) = output {
+        serde_json::to_writer(&File::create(output)?, &fingerprint)?;
+    }
+
+    Ok(fingerprint)
+}
+
+/// Decrypts the ciphertexts contained in a proof's instances using the secret key of `keypair_path`.
+/// Returns the decrypted (quantized) values of each ElGamal module layout, i.e of the encrypted inputs, params and
+/// outputs (each if any, in that order, see [GraphSettings::encrypted_lens]).
+pub(crate) fn elgamal_decrypt(
+    proof_path: PathBuf,
+    settings_path: PathBuf,
+    keypair_path: PathBuf,
+) -> Result<Vec<Vec<i128>>, Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path, None, None)?;
+    let keypair = ElGamalKeypair::load(&keypair_path)?;
+
+    if settings.encrypted_lens.is_empty() {
+        return Err("the circuit does not encrypt any inputs, params or outputs".into());
+    }
+    // the ElGamal instances (c1 and sk hashes or recipient pks, then ciphertexts) follow the model's instance columns
+    let columns = settings
+        .module_sizes
+        .instance_columns(ELGAMAL)
+        .ok_or("the circuit does not encrypt any inputs, params or outputs")?;
+    let column = |i: usize| {
+        proof
+            .instances
+            .get(settings.model_instance_shapes.len() + columns.start + i)
+            .ok_or("the proof does not contain any ElGamal instances")
+    };
+    let decrypted = ElGamalGadget::decrypt_instances(
+        column(1)?,
+        column(2)?,
+        &settings.encrypted_lens,
+        settings.elgamal_recipient,
+        &keypair,
+    )?
+    .into_iter()
+    .map(|msg| msg.into_iter().map(felt_to_i128).collect())
+    .collect();
+
+    Ok(decrypted)
+}
+
+/// Decrypts the ciphertexts of the Poseidon cipher contained in a proof's instances using the key of `key_path`.
+/// Returns the decrypted (quantized) values of each symencrypted tensor, i.e of the symencrypted inputs, params and
+/// outputs (see [GraphSettings::symencrypted_lens]).
+pub(crate) fn decrypt(
+    proof_path: PathBuf,
+    settings_path: PathBuf,
+    key_path: PathBuf,
+) -> Result<Vec<Vec<i128>>, Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path, None, None)?;
+    let key = PoseidonCipherKey::load(&key_path)?;
+
+    // the cipher's instance column follows the model's instance columns
+    let cipher_columns = settings
+        .module_sizes
+        .instance_columns(CIPHER)
+        .ok_or("the circuit does not encrypt any tensors with the Poseidon cipher")?;
+    let instances = proof
+        .instances
+        .get(settings.model_instance_shapes.len() + cipher_columns.start)
+        .ok_or("the proof does not contain any cipher instances")?;
+
+    let decrypted =
+        ModulePoseidonCipher::decrypt_tensors(key.0, instances, &settings.symencrypted_lens)?
+            .into_iter()
+            .map(|msg| msg.into_iter().map(felt_to_i128).collect())
+            .collect();
+
+    Ok(decrypted)
+}
+
 /// helper function to generate the deployment code from yul code
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) fn gen_deployment_code(yul_code: YulCode) -> Result<DeploymentCode, Box<dyn Error>> {
@@ -1402,6 +2228,50 @@ pub(crate) fn verify(
     Ok(())
 }
 
+/// Verifies a chain of proofs and checks that the hashed outputs of each proof are the hashed inputs of the next
+/// (see [crate::graph::chain::check_hash_chain]).
+pub(crate) fn verify_chain(
+    proof_paths: Vec<PathBuf>,
+    settings_paths: Vec<PathBuf>,
+    vk_paths: Vec<PathBuf>,
+    srs_path: PathBuf,
+) -> Result<(), Box<dyn Error>> {
+    if proof_paths.len() != settings_paths.len() || proof_paths.len() != vk_paths.len() {
+        return Err(Box::new(ExecutionError::ChainLength(
+            proof_paths.len(),
+            settings_paths.len(),
+            vk_paths.len(),
+        )));
+    }
+
+    let mut links = vec![];
+    for (i, ((proof_path, settings_path), vk_path)) in proof_paths
+        .into_iter()
+        .zip(settings_paths)
+        .zip(vk_paths)
+        .enumerate()
+    {
+        let circuit_settings = GraphSettings::load(&settings_path)?;
+        let params = load_srs_cmd(srs_path.clone(), circuit_settings.run_args.logrows)?;
+        let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path, None, None)?;
+        let instances = proof.instances.clone();
+
+        let strategy = KZGSingleStrategy::new(params.verifier_params());
+        let vk = load_vk::<KZGCommitmentScheme<Bn256>, Fr, GraphCircuit>(
+            vk_path,
+            circuit_settings.clone(),
+        )?;
+        if verify_proof_circuit_kzg(params.verifier_params(), proof, &vk, strategy).is_err() {
+            return Err(Box::new(ExecutionError::ChainVerifyError(i)));
+        }
+        links.push((circuit_settings, instances));
+    }
+
+    check_hash_chain(&links)?;
+    info!("verified chain of {} proofs", links.len());
+    Ok(())
+}
+
 pub(crate) fn verify_aggr(
     proof_path: PathBuf,
     vk_path: PathBuf,
@@ -0,0 +1,390 @@
+use super::model::{NodeType, ParsedNodes};
+use super::node::Node;
+use super::GraphError;
+use crate::circuit::hybrid::HybridOp;
+use crate::circuit::lookup::LookupOp;
+use crate::circuit::poly::PolyOp;
+use crate::circuit::utils::F32;
+use crate::circuit::{Input, Op};
+use halo2curves::bn256::Fr as Fp;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use tabled::{Table, Tabled};
+
+/// The scale the one-hot labels of the accuracy head are quantized at, such that they're 0 or 1 in-circuit.
+pub const LABELS_SCALE: u32 = 0;
+
+/// Error statistics of a single (dequantized) model output against the float model's output.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Tabled, PartialEq)]
+pub struct OutputAccuracy {
+    /// The index of the output.
+    pub output: usize,
+    /// The largest absolute error over all elements and samples.
+    pub max_abs_error: f32,
+    /// The mean relative error over all elements (with a non-zero float value) and samples.
+    pub mean_rel_error: f32,
+    /// The fraction of rows (along the output's last dimension) whose argmax agrees with the float model.
+    pub classification_agreement: f32,
+}
+
+/// Per-output accuracy of the quantized model against the float model across a dataset.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct AccuracyReport {
+    /// The statistics of each output.
+    pub outputs: Vec<OutputAccuracy>,
+}
+
+impl AccuracyReport {
+    /// Computes per-output error statistics.
+    /// # Arguments
+    /// * `quantized` - The dequantized outputs of the circuit, per batch and output.
+    /// * `float` - The outputs of the float model, per batch and output.
+    /// * `output_shapes` - The shapes of the outputs, used to find the rows classification agreement is measured over.
+    pub fn new(
+        quantized: &[Vec<Vec<f32>>],
+        float: &[Vec<Vec<f32>>],
+        output_shapes: &[Vec<usize>],
+    ) -> Self {
+        let outputs = output_shapes
+            .iter()
+            .enumerate()
+            .map(|(i, shape)| {
+                let row_len = shape.last().cloned().unwrap_or(1).max(1);
+                let mut max_abs_error = 0.0_f32;
+                let mut rel_error_sum = 0.0_f32;
+                let mut num_rel = 0;
+                let mut agreeing_rows = 0;
+                let mut num_rows = 0;
+                for (q, f) in quantized.iter().zip(float) {
+                    let (q, f) = match (q.get(i), f.get(i)) {
+                        (Some(q), Some(f)) => (q, f),
+                        _ => continue,
+                    };
+                    for (a, b) in q.iter().zip(f) {
+                        let abs_error = (a - b).abs();
+                        max_abs_error = max_abs_error.max(abs_error);
+                        if *b != 0.0 {
+                            rel_error_sum += abs_error / b.abs();
+                            num_rel += 1;
+                        }
+                    }
+                    for (q_row, f_row) in q.chunks(row_len).zip(f.chunks(row_len)) {
+                        num_rows += 1;
+                        if argmax(q_row) == argmax(f_row) {
+                            agreeing_rows += 1;
+                        }
+                    }
+                }
+                OutputAccuracy {
+                    output: i,
+                    max_abs_error,
+                    mean_rel_error: rel_error_sum / num_rel.max(1) as f32,
+                    classification_agreement: agreeing_rows as f32 / num_rows.max(1) as f32,
+                }
+            })
+            .collect();
+        AccuracyReport { outputs }
+    }
+
+    /// The largest absolute error over all outputs.
+    pub fn max_abs_error(&self) -> f32 {
+        self.outputs
+            .iter()
+            .map(|o| o.max_abs_error)
+            .fold(0.0, f32::max)
+    }
+
+    /// Formats the report into a table.
+    pub fn as_table(&self) -> String {
+        let mut table = Table::new(self.outputs.iter());
+        table.with(tabled::settings::Style::modern());
+        table.to_string()
+    }
+
+    /// Saves the report to a json file.
+    pub fn save(&self, path: &std::path::PathBuf) -> Result<(), std::io::Error> {
+        let encoded = serde_json::to_string(&self)?;
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(encoded.as_bytes())
+    }
+}
+
+impl ParsedNodes {
+    /// Appends a head counting the rows the model classifies correctly, such that a proof attests to the model's
+    /// accuracy on a test set (see [crate::commands::RunArgs::prove_accuracy]).
+    /// The one-hot labels of the rows become an extra (last) input, quantized at [LABELS_SCALE], and the count becomes
+    /// the only output. The last axis of the model's output holds the classes, and the leading axes index the rows.
+    /// The labels are constrained to be one-hot, and a row is correct when the output at its label is strictly the
+    /// largest of the row (ties count as incorrect). The classes that reach the row's max are flagged as
+    /// `output - row_max > -1/2`, and the row is correct when the label hits a flagged class and no other class is
+    /// flagged, i.e `2 * hits - flagged > 1/2`, without locating the argmax in-circuit.
+    pub fn append_accuracy_head(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let output = match self.outputs[..] {
+            [output] => output,
+            _ => {
+                return Err(Box::new(GraphError::AccuracyHead(format!(
+                    "the model must have a single output, it has {}",
+                    self.outputs.len()
+                ))))
+            }
+        };
+        let (dims, scale) = match self.nodes.get(&output) {
+            Some(NodeType::Node(n)) if n.out_dims.len() >= 2 => (n.out_dims.clone(), n.out_scale),
+            _ => {
+                return Err(Box::new(GraphError::AccuracyHead(
+                    "the output must be a node with a batch and a class axis".to_string(),
+                )))
+            }
+        };
+        let class_axis = dims.len() - 1;
+        let mut row_dims = dims.clone();
+        row_dims[class_axis] = 1;
+
+        let labels = self.push_node(
+            Box::new(Input {
+                scale: LABELS_SCALE,
+            }),
+            vec![],
+            dims.clone(),
+            LABELS_SCALE,
+        );
+        let one_hot = self.push_node(
+            Box::new(PolyOp::<Fp>::OneHotCheck { axis: class_axis }),
+            vec![labels],
+            dims.clone(),
+            LABELS_SCALE,
+        );
+        let row_max = self.push_node(
+            Box::new(HybridOp::Max {
+                axes: vec![class_axis],
+            }),
+            vec![output],
+            row_dims.clone(),
+            scale,
+        );
+        let gap = self.push_node(
+            Box::new(PolyOp::<Fp>::Sub),
+            vec![output, row_max],
+            dims.clone(),
+            scale,
+        );
+        // the gap is a non-positive integer, which is 0 iff the class's output is the largest of its row
+        let is_max = self.push_node(
+            Box::new(LookupOp::GreaterThan { a: F32(-0.5) }),
+            vec![gap],
+            dims.clone(),
+            0,
+        );
+        let masked = self.push_node(
+            Box::new(PolyOp::<Fp>::Mult { a: None }),
+            vec![is_max, one_hot],
+            dims.clone(),
+            0,
+        );
+        let hits = self.push_node(
+            Box::new(PolyOp::<Fp>::Sum {
+                axes: vec![class_axis],
+            }),
+            vec![masked],
+            row_dims.clone(),
+            0,
+        );
+        let flagged = self.push_node(
+            Box::new(PolyOp::<Fp>::Sum {
+                axes: vec![class_axis],
+            }),
+            vec![is_max],
+            row_dims.clone(),
+            0,
+        );
+        let double_hits = self.push_node(
+            Box::new(PolyOp::<Fp>::Add { a: None }),
+            vec![hits, hits],
+            row_dims.clone(),
+            0,
+        );
+        // hits is 0 or 1 and at least one class is flagged, such that the margin is 1 iff the label is the only max
+        let margin = self.push_node(
+            Box::new(PolyOp::<Fp>::Sub),
+            vec![double_hits, flagged],
+            row_dims.clone(),
+            0,
+        );
+        let correct = self.push_node(
+            Box::new(LookupOp::GreaterThan { a: F32(0.5) }),
+            vec![margin],
+            row_dims,
+            0,
+        );
+        let count = self.push_node(
+            Box::new(PolyOp::<Fp>::Sum {
+                axes: (0..dims.len()).collect(),
+            }),
+            vec![correct],
+            vec![1; dims.len()],
+            0,
+        );
+
+        self.inputs.push(labels);
+        self.outputs = vec![count];
+        Ok(())
+    }
+
+    /// Appends a node after all others, returning its index.
+    fn push_node(
+        &mut self,
+        opkind: Box<dyn Op<Fp>>,
+        inputs: Vec<usize>,
+        out_dims: Vec<usize>,
+        out_scale: u32,
+    ) -> usize {
+        let idx = self.nodes.keys().max().map_or(0, |i| i + 1);
+        self.nodes.insert(
+            idx,
+            NodeType::Node(Node {
+                opkind,
+                out_scale,
+                inputs,
+                out_dims,
+                idx,
+            }),
+        );
+        idx
+    }
+}
+
+/// Index of the largest element of a row.
+fn argmax(row: &[f32]) -> Option<usize> {
+    row.iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::CheckMode;
+    use crate::commands::RunArgs;
+    use crate::graph::input::GraphWitness;
+    use crate::graph::model::Model;
+    use crate::graph::vars::{VarVisibility, Visibility};
+    use crate::graph::GraphCircuit;
+    use crate::tensor::Tensor;
+    use halo2_proofs::dev::MockProver;
+    use std::collections::BTreeMap;
+
+    const LOGITS: [i128; 9] = [1, 5, 2, 4, 3, 0, 2, 2, 2];
+
+    /// A model whose only output is its 3x3 input, with an accuracy head.
+    fn accuracy_graph(scale: u32) -> ParsedNodes {
+        let mut graph = ParsedNodes {
+            nodes: BTreeMap::from([(
+                0,
+                NodeType::Node(Node {
+                    opkind: Box::new(Input { scale }),
+                    out_scale: scale,
+                    inputs: vec![],
+                    out_dims: vec![3, 3],
+                    idx: 0,
+                }),
+            )]),
+            inputs: vec![0],
+            outputs: vec![0],
+        };
+        graph.append_accuracy_head().unwrap();
+        graph
+    }
+
+    #[test]
+    fn computes_output_statistics() {
+        let quantized = vec![vec![vec![0.5, 2.0, 1.0, 0.0]]];
+        let float = vec![vec![vec![1.0, 2.0, 0.0, 1.0]]];
+        let report = AccuracyReport::new(&quantized, &float, &[vec![2, 2]]);
+        let output = &report.outputs[0];
+        assert_eq!(output.max_abs_error, 1.0);
+        // only the non-zero float values count towards the relative error
+        assert_eq!(output.mean_rel_error, (0.5 + 0.0 + 1.0) / 3.0);
+        // the first row agrees (argmax 1), the second doesn't
+        assert_eq!(output.classification_agreement, 0.5);
+    }
+
+    #[test]
+    fn accuracy_head_counts_correct_rows() {
+        let graph = accuracy_graph(0);
+        assert_eq!(graph.input_shapes(), vec![vec![3, 3], vec![3, 3]]);
+        assert_eq!(graph.get_input_scales(), vec![0, LABELS_SCALE]);
+        assert_eq!(graph.output_shapes(), vec![vec![1, 1]]);
+
+        let model = Model {
+            graph,
+            ..Default::default()
+        };
+        let logits = Tensor::new(Some(&LOGITS), &[3, 3]).unwrap();
+        // the first row is correct, the second isn't and the third ties (which counts as incorrect)
+        let labels = Tensor::new(Some(&[0, 1, 0, 0, 0, 1, 0, 0, 1]), &[3, 3]).unwrap();
+        let res = model.forward(&[logits.clone(), labels]).unwrap();
+        assert_eq!(res.outputs[0], Tensor::new(Some(&[1]), &[1, 1]).unwrap());
+
+        // labels that aren't one-hot are rejected
+        let labels = Tensor::new(Some(&[0, 1, 0, 0, 0, 0, 0, 0, 1]), &[3, 3]).unwrap();
+        assert!(model.forward(&[logits, labels]).is_err());
+    }
+
+    /// Mock proves the accuracy head over [LOGITS] (quantized at scale 2) and `labels`, claiming `count` correct rows.
+    fn mock_prove_accuracy(
+        labels: [f32; 9],
+        count: f32,
+    ) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+        let run_args = RunArgs {
+            scale: 2,
+            bits: 8,
+            logrows: 12,
+            batch_size: 1,
+            output_visibility: Visibility::Public,
+            prove_accuracy: true,
+            ..Default::default()
+        };
+        let model = Model {
+            graph: accuracy_graph(run_args.scale),
+            visibility: VarVisibility::new(run_args, vec![], vec![], BTreeMap::new()).unwrap(),
+            ..Default::default()
+        };
+        let mut circuit = GraphCircuit::new(model, run_args, CheckMode::UNSAFE).unwrap();
+
+        let logits = LOGITS.iter().map(|x| *x as f32).collect();
+        let witness = GraphWitness::new(vec![logits, labels.to_vec()], vec![vec![count]]);
+        let instances = circuit.prepare_public_inputs(&witness, None).unwrap();
+        // the labels are quantized at their own scale, the logits at the global one
+        assert_eq!(circuit.inputs[0][1], 20);
+        assert!(circuit.inputs[1]
+            .iter()
+            .zip(labels)
+            .all(|(q, l)| *q as f32 == l));
+
+        MockProver::run(run_args.logrows, &circuit, instances)
+            .unwrap()
+            .verify()
+    }
+
+    #[test]
+    fn accuracy_head_proves_the_count_of_correct_rows() {
+        let labels = [0., 1., 0., 0., 0., 1., 0., 0., 1.];
+        assert_eq!(mock_prove_accuracy(labels, 1.0), Ok(()));
+        // the third row ties, which doesn't count as correct
+        assert!(mock_prove_accuracy(labels, 2.0).is_err());
+    }
+
+    #[test]
+    fn accuracy_head_rejects_labels_that_arent_one_hot() {
+        // the second row's labels cover its max as well as another class
+        let two_hot = [0., 1., 0., 1., 0., 1., 0., 0., 1.];
+        assert!(mock_prove_accuracy(two_hot, 2.0).is_err());
+        // the second row has no label
+        let no_label = [0., 1., 0., 0., 0., 0., 0., 0., 1.];
+        assert!(mock_prove_accuracy(no_label, 1.0).is_err());
+        // the second row's labels sum to 1 without being boolean
+        let weighted = [0., 1., 0., 2., 0., -1., 0., 0., 1.];
+        assert!(mock_prove_accuracy(weighted, 2.0).is_err());
+    }
+}
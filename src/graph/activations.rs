@@ -0,0 +1,167 @@
+use super::model::Model;
+use super::scale_to_multiplier;
+use crate::tensor::Tensor;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use tabled::{Table, Tabled};
+
+/// The output of a single node in a forward pass.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NodeActivation {
+    /// The node's unique identifier.
+    pub idx: usize,
+    /// The name of the node in the Onnx graph.
+    pub name: String,
+    /// The node's operation.
+    pub op: String,
+    /// The scale the node's output is quantized at.
+    pub scale: u32,
+    /// The dimensions of the node's output.
+    pub dims: Vec<usize>,
+    /// The node's (flattened) integer output.
+    pub quantized: Vec<i128>,
+    /// The node's (flattened) output, dequantized using the node's scale.
+    pub dequantized: Vec<f32>,
+}
+
+/// The intermediate activations of every node in a forward pass, as stored alongside a witness.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Activations {
+    /// The activations, in node order.
+    pub nodes: Vec<NodeActivation>,
+}
+
+/// The error between a node's dequantized output and the corresponding float output from tract.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Tabled)]
+pub struct ActivationError {
+    /// The node's unique identifier.
+    pub idx: usize,
+    /// The name of the node in the Onnx graph.
+    pub name: String,
+    /// The node's operation.
+    pub op: String,
+    /// The largest absolute error over the node's output.
+    pub max_abs_error: f32,
+    /// The mean absolute error over the node's output.
+    pub mean_abs_error: f32,
+    /// The largest absolute value of the float output, to put the errors into perspective.
+    pub max_abs_value: f32,
+}
+
+impl Activations {
+    /// Collects the activations of every node of `model` in a forward pass over `inputs`.
+    /// # Arguments
+    /// * `model` - The model to run.
+    /// * `inputs` - The quantized inputs to the model.
+    /// * `names` - The names of the nodes in the Onnx graph, keyed by node index.
+    pub fn new(
+        model: &Model,
+        inputs: &[Tensor<i128>],
+        names: &BTreeMap<usize, String>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (results, _) = model.forward_nodes(inputs)?;
+        let mut nodes = vec![];
+        for (idx, output) in results {
+            let node = match model.graph.nodes.get(&idx) {
+                Some(node) => node,
+                None => continue,
+            };
+            let scale = node.out_scales().first().cloned().unwrap_or_default();
+            let multiplier = scale_to_multiplier(scale);
+            nodes.push(NodeActivation {
+                idx,
+                name: names.get(&idx).cloned().unwrap_or_default(),
+                op: node.as_str(),
+                scale,
+                dims: output.dims().to_vec(),
+                quantized: output.to_vec(),
+                dequantized: output
+                    .iter()
+                    .map(|x| (*x as f64 / multiplier) as f32)
+                    .collect(),
+            });
+        }
+        Ok(Activations { nodes })
+    }
+
+    /// Compares the dequantized activations with float activations keyed by node index (see [Model::float_activations]).
+    /// Nodes missing from `float_activations`, or whose number of elements differs, are skipped.
+    pub fn compare(
+        &self,
+        float_activations: &BTreeMap<usize, (String, Vec<f32>)>,
+    ) -> Vec<ActivationError> {
+        let mut errors = vec![];
+        for node in &self.nodes {
+            let float = match float_activations.get(&node.idx) {
+                Some((_, float)) if float.len() == node.dequantized.len() => float,
+                _ => continue,
+            };
+            let abs_errors = node
+                .dequantized
+                .iter()
+                .zip(float)
+                .map(|(q, f)| (q - f).abs())
+                .collect::<Vec<_>>();
+            let len = abs_errors.len().max(1) as f32;
+            errors.push(ActivationError {
+                idx: node.idx,
+                name: node.name.clone(),
+                op: node.op.clone(),
+                max_abs_error: abs_errors.iter().cloned().fold(0.0, f32::max),
+                mean_abs_error: abs_errors.iter().sum::<f32>() / len,
+                max_abs_value: float.iter().map(|f| f.abs()).fold(0.0, f32::max),
+            });
+        }
+        errors
+    }
+
+    /// Formats a comparison (see [Activations::compare]) into a table.
+    pub fn table_errors(errors: &[ActivationError]) -> String {
+        let mut table = Table::new(errors.iter());
+        table.with(tabled::settings::Style::modern());
+        table.to_string()
+    }
+
+    /// Saves the activations to a json file.
+    pub fn save(&self, path: &std::path::PathBuf) -> Result<(), std::io::Error> {
+        let encoded = serde_json::to_string(&self)?;
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(encoded.as_bytes())
+    }
+
+    /// Loads activations from a json file.
+    pub fn load(path: &std::path::PathBuf) -> Result<Self, std::io::Error> {
+        let mut file = std::fs::File::open(path)?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+        let res = serde_json::from_str(&data)?;
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_against_float_activations() {
+        let activations = Activations {
+            nodes: vec![NodeActivation {
+                idx: 1,
+                dequantized: vec![1.0, 2.5],
+                ..Default::default()
+            }],
+        };
+        let float = BTreeMap::from([
+            (1, ("a".to_string(), vec![1.0, 2.0])),
+            (2, ("b".to_string(), vec![0.0])),
+        ]);
+        let errors = activations.compare(&float);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].max_abs_error, 0.5);
+        assert_eq!(errors[0].mean_abs_error, 0.25);
+        assert_eq!(errors[0].max_abs_value, 2.0);
+    }
+}
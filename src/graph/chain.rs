@@ -0,0 +1,183 @@
+use halo2curves::bn256::Fr as Fp;
+
+use super::{GraphError, GraphSettings};
+
+/// Checks that a sequence of proofs forms a chain, i.e that the Poseidon hashes of the hashed outputs of each proof are
+/// the hashes of the hashed inputs of the next (see [super::Visibility::Hashed]).
+/// Only the instances are checked, the proofs themselves are not verified.
+/// # Arguments
+/// * `links` - The settings of the circuit each proof is for (which locate the hashes among its instances) and the
+/// instances of the proof, in the order of the chain.
+pub fn check_hash_chain(links: &[(GraphSettings, Vec<Vec<Fp>>)]) -> Result<(), GraphError> {
+    for (i, pair) in links.windows(2).enumerate() {
+        let (settings, instances) = &pair[0];
+        let outputs = settings.output_hashes(instances)?;
+        let (settings, instances) = &pair[1];
+        let inputs = settings.input_hashes(instances)?;
+        // proofs without hashes to link by don't form a chain
+        if outputs.is_empty() || outputs != inputs {
+            return Err(GraphError::BrokenChain(i, i + 1));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{HashMode, RunArgs};
+    use crate::graph::modules::{GraphModules, POSEIDON};
+    use crate::graph::registry::ModuleContext;
+    use crate::graph::{VarVisibility, Visibility};
+
+    /// Settings of a circuit with one public output (of 2 elements) followed by `hashed_outputs` hashed outputs, and
+    /// `hashed_inputs` hashed inputs followed by a symencrypted input if `symencrypted`
+    fn settings(
+        hashed_inputs: usize,
+        hashed_outputs: usize,
+        symencrypted: bool,
+        hash_mode: HashMode,
+    ) -> GraphSettings {
+        let mut inputs = vec![Visibility::Hashed; hashed_inputs];
+        if symencrypted {
+            inputs.push(Visibility::SymEncrypted);
+        }
+        let mut outputs = vec![Visibility::Public];
+        outputs.extend(vec![Visibility::Hashed; hashed_outputs]);
+        let mut settings = GraphSettings {
+            run_args: RunArgs {
+                hash_mode,
+                ..Default::default()
+            },
+            model_instance_shapes: vec![vec![2]],
+            ..Default::default()
+        };
+        settings.module_sizes = GraphModules::num_constraints_and_instances(
+            &settings.modules,
+            vec![vec![2]; inputs.len()],
+            vec![],
+            vec![vec![2]; outputs.len()],
+            VarVisibility {
+                inputs,
+                outputs,
+                ..Default::default()
+            },
+            &ModuleContext::from(&settings),
+        )
+        .unwrap();
+        settings
+    }
+
+    /// The instances of a proof: the public output, the `ciphertexts` instances of the cipher module if any, and
+    /// the Poseidon column (whose instance columns follow the cipher module's, as modules are ordered by name)
+    fn instances(ciphertexts: usize, hashes: &[u64]) -> Vec<Vec<Fp>> {
+        let mut instances = vec![vec![Fp::from(7), Fp::from(8)]];
+        if ciphertexts > 0 {
+            instances.push((0..ciphertexts as u64).map(|i| Fp::from(100 + i)).collect());
+        }
+        instances.push(hashes.iter().map(|h| Fp::from(*h)).collect());
+        instances
+    }
+
+    #[test]
+    fn links_hashed_outputs_to_hashed_inputs() {
+        let chain = vec![
+            (settings(1, 1, false, HashMode::Flat), instances(0, &[1, 2])),
+            (
+                settings(1, 2, false, HashMode::Flat),
+                instances(0, &[2, 3, 4]),
+            ),
+            (settings(2, 0, false, HashMode::Flat), instances(0, &[3, 4])),
+        ];
+        assert!(check_hash_chain(&chain).is_ok());
+
+        let mut broken = chain;
+        broken[2].1 = instances(0, &[4, 3]);
+        assert!(matches!(
+            check_hash_chain(&broken),
+            Err(GraphError::BrokenChain(1, 2))
+        ));
+    }
+
+    #[test]
+    fn locates_the_hashes_after_the_instances_of_other_modules() {
+        let settings_with_cipher = settings(1, 1, true, HashMode::Flat);
+        // the key hash, nonce and 2 ciphertexts of the symencrypted input come first
+        assert_eq!(
+            settings_with_cipher.module_sizes.num_instances(),
+            vec![4, 2]
+        );
+        assert_eq!(
+            settings_with_cipher.module_sizes.instance_columns(POSEIDON),
+            Some(1..2)
+        );
+
+        let chain = vec![
+            (settings(0, 1, false, HashMode::Flat), instances(0, &[1])),
+            (settings_with_cipher.clone(), instances(4, &[1, 2])),
+            (settings(1, 0, false, HashMode::Flat), instances(0, &[2])),
+        ];
+        assert!(check_hash_chain(&chain).is_ok());
+
+        // the hashes aren't read from the cipher module's instances
+        let mut broken = chain;
+        broken[1].1 = instances(4, &[100, 2]);
+        assert!(matches!(
+            check_hash_chain(&broken),
+            Err(GraphError::BrokenChain(0, 1))
+        ));
+    }
+
+    #[test]
+    fn links_merkle_roots() {
+        // each hashed tensor is committed to by a single Merkle root
+        let merkle = settings(2, 1, false, HashMode::Merkle);
+        assert_eq!(merkle.module_sizes.num_instances(), vec![3]);
+        assert_eq!(
+            merkle
+                .input_hashes(&instances(0, &[1, 2, 3]))
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let chain = vec![
+            (
+                settings(0, 2, false, HashMode::Merkle),
+                instances(0, &[1, 2]),
+            ),
+            (merkle, instances(0, &[1, 2, 3])),
+            (settings(1, 0, true, HashMode::Merkle), instances(4, &[3])),
+        ];
+        assert!(check_hash_chain(&chain).is_ok());
+    }
+
+    #[test]
+    fn rejects_proofs_without_hashes() {
+        // proofs without hashed outputs and inputs can't be linked
+        let chain = vec![
+            (
+                settings(0, 0, false, HashMode::Flat),
+                vec![vec![Fp::from(7), Fp::from(8)]],
+            ),
+            (
+                settings(0, 0, false, HashMode::Flat),
+                vec![vec![Fp::from(7), Fp::from(8)]],
+            ),
+        ];
+        assert!(matches!(
+            check_hash_chain(&chain),
+            Err(GraphError::BrokenChain(0, 1))
+        ));
+
+        // the instances don't hold the hashes the settings expect
+        let chain = vec![
+            (settings(1, 1, false, HashMode::Flat), vec![]),
+            (settings(1, 1, false, HashMode::Flat), vec![]),
+        ];
+        assert!(matches!(
+            check_hash_chain(&chain),
+            Err(GraphError::MissingHashes)
+        ));
+    }
+}
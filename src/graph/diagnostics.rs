@@ -0,0 +1,403 @@
+use super::model::{Model, NodeOffsets};
+use super::GraphError;
+use crate::tensor::{Tensor, ValTensor};
+use halo2_proofs::dev::{metadata, FailureLocation, VerifyFailure};
+use halo2_proofs::plonk::{Any, Column};
+use halo2curves::bn256::Fr as Fp;
+use itertools::Itertools;
+use log::error;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// The maximum number of differing elements printed for a divergent node.
+const MAX_DIFF_ELEMENTS: usize = 10;
+
+/// A failing constraint, lookup or copy constraint reported by the mock prover, attributed to a node of the model.
+#[derive(Clone, Debug)]
+pub struct NodeFailure {
+    /// the failure as reported by the mock prover
+    pub failure: String,
+    /// the index of the node whose rows contain the failure
+    pub idx: usize,
+    /// the op of the node
+    pub op: String,
+    /// the coordinate of the failing element in the node's output, if the row maps onto it
+    pub coordinate: Option<Vec<usize>>,
+}
+
+impl fmt::Display for NodeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node {} ({})", self.idx, self.op)?;
+        if let Some(coordinate) = &self.coordinate {
+            write!(f, " at output coordinate {:?}", coordinate)?;
+        }
+        write!(f, ": {}", self.failure)
+    }
+}
+
+/// The name of the region the model's nodes are laid out in.
+const MODEL_REGION: &str = "model";
+
+/// Where the model's cells live in the circuit, used to map the rows and columns reported by the mock prover
+/// back onto the (linear) offsets of the model's region.
+#[derive(Clone, Debug, Default)]
+pub struct ModelColumns {
+    /// The columns of each of the model's [crate::tensor::VarTensor]s. Values wrap around these columns in order.
+    pub columns: Vec<Vec<Column<Any>>>,
+    /// The column of the lookup input each lookup argument reads from, keyed by the index of the argument.
+    pub lookup_columns: BTreeMap<usize, usize>,
+    /// The number of rows in each column.
+    pub col_size: usize,
+}
+
+impl ModelColumns {
+    /// The number of columns each of the model's tensors wraps around.
+    fn num_cols(&self) -> usize {
+        self.columns.iter().map(|c| c.len()).max().unwrap_or(0)
+    }
+
+    /// The position of `column` amongst the columns of the model's tensors.
+    fn position(&self, column: &Column<Any>) -> Option<usize> {
+        self.columns
+            .iter()
+            .find_map(|columns| columns.iter().position(|c| c == column))
+    }
+
+    /// The position of a column, as recorded by the mock prover's metadata, amongst the columns of the model's tensors.
+    fn position_of_metadata(&self, column: &metadata::Column) -> Option<usize> {
+        self.columns.iter().find_map(|columns| {
+            columns
+                .iter()
+                .position(|c| metadata::Column::from(*c) == *column)
+        })
+    }
+}
+
+/// The name of a region, as formatted by the mock prover (`Region {index} ('{name}')`).
+fn region_name(region: &metadata::Region) -> String {
+    let region = region.to_string();
+    match (region.find("('"), region.rfind("')")) {
+        (Some(start), Some(end)) if start + 2 <= end => region[start + 2..end].to_string(),
+        _ => region,
+    }
+}
+
+/// Returns the row (relative to the model's region) a failure occurred at, if it occurred within the model's region,
+/// alongside the position of the failing column amongst the model's columns if the failure identifies one.
+/// Failing constraints don't identify the column the failing gate was enabled on, so they return `None` for the column.
+fn model_region_cell(
+    failure: &VerifyFailure,
+    columns: &ModelColumns,
+) -> Option<(usize, Option<usize>)> {
+    let (region, row, column) = match failure {
+        VerifyFailure::CellNotAssigned {
+            region,
+            offset,
+            column,
+            ..
+        } => (
+            region,
+            usize::try_from(*offset).ok()?,
+            Some(columns.position(column)?),
+        ),
+        VerifyFailure::Permutation {
+            column, location, ..
+        } => match location {
+            FailureLocation::InRegion { region, offset } => {
+                (region, *offset, Some(columns.position_of_metadata(column)?))
+            }
+            FailureLocation::OutsideRegion { .. } => return None,
+        },
+        VerifyFailure::Lookup {
+            lookup_index,
+            location,
+            ..
+        } => match location {
+            FailureLocation::InRegion { region, offset } => (
+                region,
+                *offset,
+                Some(*columns.lookup_columns.get(lookup_index)?),
+            ),
+            FailureLocation::OutsideRegion { .. } => return None,
+        },
+        VerifyFailure::ConstraintNotSatisfied { location, .. } => match location {
+            FailureLocation::InRegion { region, offset } => (region, *offset, None),
+            FailureLocation::OutsideRegion { .. } => return None,
+        },
+        _ => return None,
+    };
+    if region_name(region) == MODEL_REGION {
+        Some((row, column))
+    } else {
+        None
+    }
+}
+
+/// Unravels a linear index into a coordinate of a tensor with dimensions `dims`.
+fn unravel(mut linear: usize, dims: &[usize]) -> Vec<usize> {
+    let mut coordinate = vec![0; dims.len()];
+    for (c, d) in coordinate.iter_mut().zip(dims).rev() {
+        *c = linear % d;
+        linear /= d;
+    }
+    coordinate
+}
+
+impl Model {
+    /// Maps mock prover failures within the model's region back onto the nodes that were laid out at the failing cells.
+    /// Failures that identify their column map onto a single offset of the region. Failing constraints only identify their row,
+    /// so they are attributed to the nodes laid out at that row in any of the model's columns.
+    /// # Arguments
+    /// * `node_offsets` - The rows the nodes are laid out in, as used by [Model::layout].
+    /// * `columns` - The columns of the model's region.
+    /// * `failures` - The failures reported by the mock prover.
+    pub fn locate_failures(
+        &self,
+        node_offsets: &NodeOffsets,
+        columns: &ModelColumns,
+        failures: &[VerifyFailure],
+    ) -> Vec<NodeFailure> {
+        let mut located = vec![];
+        for failure in failures {
+            let (row, column) = match model_region_cell(failure, columns) {
+                Some(cell) => cell,
+                None => continue,
+            };
+            let candidates = match column {
+                Some(x) => vec![x],
+                None => (0..columns.num_cols()).collect(),
+            };
+            let offsets = candidates
+                .into_iter()
+                .map(|x| x * columns.col_size + row)
+                .collect_vec();
+            for linear in offsets {
+                for (idx, range) in node_offsets
+                    .nodes_at(linear)
+                    .into_iter()
+                    .unique_by(|(i, _)| *i)
+                {
+                    let node = match self.graph.nodes.get(&idx) {
+                        Some(node) => node,
+                        None => continue,
+                    };
+                    let dims = node.out_dims().first().cloned().unwrap_or_default();
+                    let relative = linear - range.start;
+                    let coordinate = if relative < dims.iter().product::<usize>() {
+                        Some(unravel(relative, &dims))
+                    } else {
+                        None
+                    };
+                    located.push(NodeFailure {
+                        failure: failure.to_string(),
+                        idx,
+                        op: node.as_str(),
+                        coordinate,
+                    });
+                }
+            }
+        }
+        located
+    }
+
+    /// Compares the witness assigned by every node with the node's output in a forward pass over the same inputs,
+    /// stopping at the first node that diverges and logging the elements that differ.
+    /// # Arguments
+    /// * `expected` - The output of each node in a forward pass, keyed by node index (see [Model::forward_nodes]).
+    /// * `results` - The values assigned by each node, keyed by node index.
+    pub(crate) fn check_divergence(
+        &self,
+        expected: &BTreeMap<usize, Tensor<i128>>,
+        results: &BTreeMap<usize, ValTensor<Fp>>,
+    ) -> Result<(), Box<dyn Error>> {
+        for (idx, node) in self.graph.nodes.iter() {
+            let (witness, expected) = match (results.get(idx), expected.get(idx)) {
+                (Some(w), Some(e)) => match w.get_int_evals() {
+                    Ok(w) => (w, e),
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+
+            let diff = expected
+                .iter()
+                .zip(witness.iter())
+                .enumerate()
+                .filter(|(_, (e, w))| e != w)
+                .collect_vec();
+            if diff.is_empty() && witness.len() == expected.len() {
+                continue;
+            }
+
+            error!(
+                "node {} ({}) diverges from the forward pass in {} of {} elements (witness has {})",
+                idx,
+                node.as_str(),
+                diff.len(),
+                expected.len(),
+                witness.len()
+            );
+            for (i, (e, w)) in diff.iter().take(MAX_DIFF_ELEMENTS) {
+                error!(
+                    "  at {:?}: forward {}, witness {}",
+                    unravel(*i, expected.dims()),
+                    e,
+                    w
+                );
+            }
+            return Err(Box::new(GraphError::NodeDivergence(*idx, node.as_str())));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::poly::PolyOp;
+    use crate::circuit::Input;
+    use crate::graph::model::{NodeType, ParsedNodes};
+    use crate::graph::Node;
+    use halo2_proofs::plonk::ConstraintSystem;
+
+    fn model() -> Model {
+        let node = |idx: usize, inputs: Vec<usize>, out_dims: Vec<usize>| {
+            let opkind: Box<dyn crate::circuit::Op<Fp>> = if inputs.is_empty() {
+                Box::new(Input { scale: 0 })
+            } else {
+                Box::new(PolyOp::Identity)
+            };
+            NodeType::Node(Node {
+                opkind,
+                out_scale: 0,
+                inputs,
+                out_dims,
+                idx,
+            })
+        };
+        Model {
+            graph: ParsedNodes {
+                nodes: BTreeMap::from([
+                    (0, node(0, vec![], vec![5])),
+                    (1, node(1, vec![0], vec![2, 5])),
+                ]),
+                inputs: vec![0],
+                outputs: vec![1],
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn locates_failures_in_the_failing_column() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let advices = (0..2)
+            .map(|_| Column::<Any>::from(cs.advice_column()))
+            .collect_vec();
+        let columns = ModelColumns {
+            columns: vec![advices.clone()],
+            lookup_columns: BTreeMap::new(),
+            col_size: 10,
+        };
+        let node_offsets = NodeOffsets {
+            col_size: 10,
+            ranges: BTreeMap::from([(0, 0..5), (1, 5..15)]),
+            end: 15,
+            output_checks: vec![],
+        };
+        let unassigned =
+            |region: &str, column: Column<Any>, offset: isize| VerifyFailure::CellNotAssigned {
+                gate: (0, "gate").into(),
+                region: (0, region).into(),
+                gate_offset: 0,
+                column,
+                offset,
+            };
+
+        let model = model();
+        // row 2 of the second column is offset 12 of the region, the 8th element of node 1
+        let located = model.locate_failures(
+            &node_offsets,
+            &columns,
+            &[unassigned("model", advices[1], 2)],
+        );
+        assert_eq!(located.len(), 1);
+        assert_eq!(located[0].idx, 1);
+        assert_eq!(located[0].coordinate, Some(vec![1, 2]));
+
+        // the same row of the first column belongs to node 0
+        let located = model.locate_failures(
+            &node_offsets,
+            &columns,
+            &[unassigned("model", advices[0], 2)],
+        );
+        assert_eq!(located.len(), 1);
+        assert_eq!(located[0].idx, 0);
+        assert_eq!(located[0].coordinate, Some(vec![2]));
+
+        // failures in other regions aren't attributed to the model's nodes
+        let located = model.locate_failures(
+            &node_offsets,
+            &columns,
+            &[unassigned("model_outputs", advices[0], 2)],
+        );
+        assert!(located.is_empty());
+    }
+
+    #[test]
+    fn flags_the_first_divergent_node() {
+        let model = model();
+        let values = Tensor::new(Some(&[1, 2, 3, 4, 5]), &[5]).unwrap();
+        let expected = BTreeMap::from([(0, values.clone()), (1, values.clone())]);
+        let witness = |t: &Tensor<i128>| {
+            ValTensor::from(<Tensor<i128> as Into<
+                Tensor<halo2_proofs::circuit::Value<Fp>>,
+            >>::into(t.clone()))
+        };
+
+        let mut results = BTreeMap::from([(0, witness(&values)), (1, witness(&values))]);
+        assert!(model.check_divergence(&expected, &results).is_ok());
+
+        let diverged = Tensor::new(Some(&[1, 2, 0, 4, 5]), &[5]).unwrap();
+        results.insert(1, witness(&diverged));
+        let err = model.check_divergence(&expected, &results).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GraphError>(),
+            Some(GraphError::NodeDivergence(1, _))
+        ));
+    }
+
+    #[test]
+    fn forward_pass_runs_once_when_inputs_are_loaded() {
+        let circuit = |check_mode| {
+            let mut circuit = crate::graph::GraphCircuit::new_from_settings(
+                model(),
+                Default::default(),
+                check_mode,
+            )
+            .unwrap();
+            circuit.load_inputs(&[vec![1.0, 2.0, 3.0, 4.0, 5.0]]);
+            circuit
+        };
+
+        let node_outputs = circuit(crate::circuit::CheckMode::SAFE)
+            .node_outputs
+            .unwrap();
+        assert_eq!(
+            node_outputs[&1],
+            Tensor::new(Some(&[1, 2, 3, 4, 5]), &[5]).unwrap()
+        );
+        assert!(circuit(crate::circuit::CheckMode::UNSAFE)
+            .node_outputs
+            .is_none());
+    }
+
+    #[test]
+    fn unravels_linear_indices() {
+        assert_eq!(unravel(0, &[2, 3]), vec![0, 0]);
+        assert_eq!(unravel(4, &[2, 3]), vec![1, 1]);
+        assert_eq!(unravel(5, &[1, 2, 3]), vec![0, 1, 2]);
+    }
+}
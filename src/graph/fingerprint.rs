@@ -0,0 +1,57 @@
+use std::error::Error;
+
+use halo2curves::bn256::Fr as Fp;
+use halo2curves::ff::PrimeField;
+use serde::{Deserialize, Serialize};
+
+use super::modules::ModulePoseidonLengthPrefixed;
+use crate::circuit::modules::Module;
+
+/// The number of bytes packed in each field element, such that any packed chunk is smaller than the modulus.
+const BYTES_PER_ELEMENT: usize = 31;
+
+/// Hashes identifying a model, which a registry can publish and verifiers check proofs against.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ModelFingerprint {
+    /// The Poseidon hash of the params with [super::Visibility::Hashed], as it appears in the instances of the model's
+    /// proofs. `None` if none of the params are hashed.
+    pub params_hash: Option<Fp>,
+    /// The hash of the (canonically serialized) circuit settings, see [hash_bytes].
+    pub settings_hash: Fp,
+    /// The hash of the verification key, see [hash_bytes].
+    pub vk_hash: Fp,
+}
+
+/// Hashes bytes with the length-prefixed Poseidon tree, packing them (little endian) in chunks of 31 bytes per field
+/// element. The packed message is itself prefixed with the number of bytes, such that byte strings that only differ by
+/// trailing zeros (which pack into the same field elements) hash to different digests.
+pub fn hash_bytes(bytes: &[u8]) -> Result<Fp, Box<dyn Error>> {
+    let message = std::iter::once(Fp::from(bytes.len() as u64))
+        .chain(bytes.chunks(BYTES_PER_ELEMENT).map(|chunk| {
+            let mut repr = <Fp as PrimeField>::Repr::default();
+            repr.as_mut()[..chunk.len()].copy_from_slice(chunk);
+            // 31 bytes are always below the modulus
+            Fp::from_repr(repr).unwrap()
+        }))
+        .collect();
+    Ok(ModulePoseidonLengthPrefixed::run(message)?[0][0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_bytes_by_content_and_length() {
+        let bytes = (0..100).collect::<Vec<u8>>();
+        assert_eq!(hash_bytes(&bytes).unwrap(), hash_bytes(&bytes).unwrap());
+
+        let mut flipped = bytes.clone();
+        flipped[99] ^= 1;
+        assert_ne!(hash_bytes(&bytes).unwrap(), hash_bytes(&flipped).unwrap());
+
+        // trailing zeros pack into the same field elements, but change the length prefix
+        assert_ne!(hash_bytes(&[1]).unwrap(), hash_bytes(&[1, 0]).unwrap());
+        assert_ne!(hash_bytes(&[]).unwrap(), hash_bytes(&[0]).unwrap());
+    }
+}
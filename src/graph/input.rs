@@ -168,12 +168,6 @@ fn field_to_vecu64<F: PrimeField + SerdeObject + Serialize>(fp: &F) -> Vec<u64>
     b
 }
 
-#[cfg(feature = "python-bindings")]
-fn insert_poseidon_hash_pydict(pydict: &PyDict, poseidon_hash: &Vec<Fp>) {
-    let poseidon_hash: Vec<Vec<u64>> = poseidon_hash.iter().map(field_to_vecu64).collect();
-    pydict.set_item("poseidon_hash", poseidon_hash).unwrap();
-}
-
 #[cfg(feature = "python-bindings")]
 fn g1affine_to_pydict(g1affine_dict: &PyDict, g1affine: &G1Affine) {
     let g1affine_x = field_to_vecu64(&g1affine.x);
@@ -183,7 +177,10 @@ fn g1affine_to_pydict(g1affine_dict: &PyDict, g1affine: &G1Affine) {
 }
 
 #[cfg(feature = "python-bindings")]
-use super::modules::ElGamalResult;
+use super::modules::{
+    CipherResult, ElGamalResult, MembershipResult, SchnorrResult, CIPHER, ELGAMAL, MEMBERSHIP,
+    POSEIDON, SCHNORR,
+};
 #[cfg(feature = "python-bindings")]
 fn insert_elgamal_results_pydict(py: Python, pydict: &PyDict, elgamal_results: &ElGamalResult) {
     let results_dict = PyDict::new(py);
@@ -227,6 +224,180 @@ fn insert_elgamal_results_pydict(py: Python, pydict: &PyDict, elgamal_results: &
     //elgamal
 }
 
+#[cfg(feature = "python-bindings")]
+fn insert_cipher_results_pydict(py: Python, pydict: &PyDict, cipher_results: &CipherResult) {
+    let results_dict = PyDict::new(py);
+    let cipher_text: Vec<Vec<u64>> = cipher_results
+        .ciphertexts
+        .iter()
+        .map(field_to_vecu64)
+        .collect();
+    results_dict.set_item("ciphertexts", cipher_text).unwrap();
+
+    let variables_dict = PyDict::new(py);
+    // shared key
+    let key = field_to_vecu64(&cipher_results.variables.key);
+    variables_dict.set_item("key", key).unwrap();
+    // nonce of the first tensor
+    let nonce = field_to_vecu64(&cipher_results.variables.nonce);
+    variables_dict.set_item("nonce", nonce).unwrap();
+
+    results_dict.set_item("variables", variables_dict).unwrap();
+
+    pydict.set_item("cipher", results_dict).unwrap();
+}
+
+#[cfg(feature = "python-bindings")]
+fn insert_schnorr_results_pydict(py: Python, pydict: &PyDict, schnorr_results: &SchnorrResult) {
+    let results_dict = PyDict::new(py);
+    let instances: Vec<Vec<Vec<u64>>> = schnorr_results
+        .instances
+        .iter()
+        .map(|v| v.iter().map(field_to_vecu64).collect::<Vec<Vec<u64>>>())
+        .collect::<Vec<Vec<Vec<u64>>>>();
+    results_dict.set_item("instances", instances).unwrap();
+
+    let variables_dict = PyDict::new(py);
+    let variables = &schnorr_results.variables;
+
+    let mut signatures = vec![];
+    for signature in variables.signatures.iter() {
+        let signature_dict = PyDict::new(py);
+        // public key of the signer
+        let pk_dict = PyDict::new(py);
+        g1affine_to_pydict(pk_dict, &signature.pk);
+        signature_dict.set_item("pk", pk_dict).unwrap();
+        // commitment to the signing nonce
+        let r_dict = PyDict::new(py);
+        g1affine_to_pydict(r_dict, &signature.r);
+        signature_dict.set_item("r", r_dict).unwrap();
+        // response
+        let s = field_to_vecu64(&signature.s);
+        signature_dict.set_item("s", s).unwrap();
+        signatures.push(signature_dict);
+    }
+    variables_dict.set_item("signatures", signatures).unwrap();
+
+    let aux_generator_dict = PyDict::new(py);
+    // schnorr aux generator used in ecc chip
+    g1affine_to_pydict(aux_generator_dict, &variables.aux_generator);
+    variables_dict
+        .set_item("aux_generator", aux_generator_dict)
+        .unwrap();
+
+    // schnorr window size used in ecc chip
+    variables_dict
+        .set_item("window_size", variables.window_size)
+        .unwrap();
+
+    results_dict.set_item("variables", variables_dict).unwrap();
+
+    pydict.set_item("schnorr", results_dict).unwrap();
+}
+
+#[cfg(feature = "python-bindings")]
+fn insert_membership_results_pydict(
+    py: Python,
+    pydict: &PyDict,
+    membership_results: &MembershipResult,
+) {
+    let results_dict = PyDict::new(py);
+    let roots: Vec<Vec<u64>> = membership_results
+        .roots
+        .iter()
+        .map(field_to_vecu64)
+        .collect();
+    results_dict.set_item("roots", roots).unwrap();
+
+    let variables_dict = PyDict::new(py);
+    let variables = &membership_results.variables;
+
+    let mut paths = vec![];
+    for path in variables.paths.iter() {
+        let path_dict = PyDict::new(py);
+        // position of the row in the dataset
+        path_dict.set_item("index", path.index).unwrap();
+        // siblings from the leaf up to the root
+        let siblings: Vec<Vec<u64>> = path.siblings.iter().map(field_to_vecu64).collect();
+        path_dict.set_item("siblings", siblings).unwrap();
+        paths.push(path_dict);
+    }
+    variables_dict.set_item("paths", paths).unwrap();
+    variables_dict.set_item("depth", variables.depth).unwrap();
+
+    results_dict.set_item("variables", variables_dict).unwrap();
+
+    pydict.set_item("membership", results_dict).unwrap();
+}
+
+#[cfg(feature = "python-bindings")]
+fn insert_poseidon_hash_pydict(pydict: &PyDict, poseidon_hash: &[Fp]) {
+    let poseidon_hash: Vec<Vec<u64>> = poseidon_hash.iter().map(field_to_vecu64).collect();
+    pydict.set_item("poseidon_hash", poseidon_hash).unwrap();
+}
+
+#[cfg(feature = "python-bindings")]
+/// converts the result of a user-defined module, as serialized in the witness, into a python object
+fn json_to_pyobject(py: Python, value: &serde_json::Value) -> PyObject {
+    match value {
+        serde_json::Value::Null => py.None(),
+        serde_json::Value::Bool(b) => b.to_object(py),
+        serde_json::Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => u.to_object(py),
+            (None, Some(i)) => i.to_object(py),
+            _ => n.as_f64().to_object(py),
+        },
+        serde_json::Value::String(s) => s.to_object(py),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(|v| json_to_pyobject(py, v))
+            .collect::<Vec<PyObject>>()
+            .to_object(py),
+        serde_json::Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (k, v) in map {
+                dict.set_item(k, json_to_pyobject(py, v)).unwrap();
+            }
+            dict.to_object(py)
+        }
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+fn insert_module_results_pydict(py: Python, pydict: &PyDict, processed: &ModuleForwardResult) {
+    for (name, result) in processed.iter() {
+        match name.as_str() {
+            POSEIDON => {
+                if let Ok(Some(hashes)) = processed.parse::<Vec<Fp>>(POSEIDON) {
+                    insert_poseidon_hash_pydict(pydict, &hashes);
+                }
+            }
+            ELGAMAL => {
+                if let Ok(Some(res)) = processed.parse::<ElGamalResult>(ELGAMAL) {
+                    insert_elgamal_results_pydict(py, pydict, &res);
+                }
+            }
+            CIPHER => {
+                if let Ok(Some(res)) = processed.parse::<CipherResult>(CIPHER) {
+                    insert_cipher_results_pydict(py, pydict, &res);
+                }
+            }
+            SCHNORR => {
+                if let Ok(Some(res)) = processed.parse::<SchnorrResult>(SCHNORR) {
+                    insert_schnorr_results_pydict(py, pydict, &res);
+                }
+            }
+            MEMBERSHIP => {
+                if let Ok(Some(res)) = processed.parse::<MembershipResult>(MEMBERSHIP) {
+                    insert_membership_results_pydict(py, pydict, &res);
+                }
+            }
+            // user-defined modules' results are converted as they are serialized
+            _ => pydict.set_item(name, json_to_pyobject(py, result)).unwrap(),
+        }
+    }
+}
+
 #[cfg(feature = "python-bindings")]
 impl ToPyObject for GraphWitness {
     fn to_object(&self, py: Python) -> PyObject {
@@ -243,36 +414,17 @@ impl ToPyObject for GraphWitness {
         dict.set_item("output_data", &output_data_mut).unwrap();
 
         if let Some(processed_inputs) = &self.processed_inputs {
-            //poseidon_hash
-            if let Some(processed_inputs_poseidon_hash) = &processed_inputs.poseidon_hash {
-                insert_poseidon_hash_pydict(&dict_inputs, processed_inputs_poseidon_hash);
-            }
-            if let Some(processed_inputs_elgamal) = &processed_inputs.elgamal {
-                insert_elgamal_results_pydict(py, dict_inputs, processed_inputs_elgamal);
-            }
-
+            insert_module_results_pydict(py, dict_inputs, processed_inputs);
             dict.set_item("processed_inputs", dict_inputs).unwrap();
         }
 
         if let Some(processed_params) = &self.processed_params {
-            if let Some(processed_params_poseidon_hash) = &processed_params.poseidon_hash {
-                insert_poseidon_hash_pydict(dict_params, processed_params_poseidon_hash);
-            }
-            if let Some(processed_params_elgamal) = &processed_params.elgamal {
-                insert_elgamal_results_pydict(py, dict_params, processed_params_elgamal);
-            }
-
+            insert_module_results_pydict(py, dict_params, processed_params);
             dict.set_item("processed_params", dict_params).unwrap();
         }
 
         if let Some(processed_outputs) = &self.processed_outputs {
-            if let Some(processed_outputs_poseidon_hash) = &processed_outputs.poseidon_hash {
-                insert_poseidon_hash_pydict(dict_outputs, processed_outputs_poseidon_hash);
-            }
-            if let Some(processed_outputs_elgamal) = &processed_outputs.elgamal {
-                insert_elgamal_results_pydict(py, dict_outputs, processed_outputs_elgamal);
-            }
-
+            insert_module_results_pydict(py, dict_outputs, processed_outputs);
             dict.set_item("processed_outputs", dict_outputs).unwrap();
         }
 
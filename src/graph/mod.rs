@@ -1,3 +1,13 @@
+/// Accuracy of a quantized model, against its float counterpart or the labels of a test set.
+pub mod accuracy;
+/// Intermediate activations of a forward pass, for auditing quantization error.
+pub mod activations;
+/// Linking proofs whose hashed outputs feed the hashed inputs of the next.
+pub mod chain;
+/// Debugging aids tracing circuit failures back to the nodes of a computational graph.
+pub mod diagnostics;
+/// Hashes identifying a model, its settings and verification key.
+pub mod fingerprint;
 /// Representations of a computational graph's inputs.
 pub mod input;
 /// Crate for defining a computational graph and building a ZK-circuit from it.
@@ -6,40 +16,52 @@ pub mod model;
 pub mod modules;
 /// Inner elements of a computational graph that represent a single operation / constraints.
 pub mod node;
+/// Optimization passes over a parsed computational graph.
+pub mod passes;
+/// The registry of the modules processing inputs, params and outputs, keyed by name.
+pub mod registry;
 /// Helper functions
 pub mod utilities;
 /// Representations of a computational graph's variables.
 pub mod vars;
 
+pub use diagnostics::{ModelColumns, NodeFailure};
 pub use input::GraphInput;
 pub use input::GraphWitness;
 
 use crate::circuit::lookup::LookupOp;
+use crate::circuit::modules::elgamal::{ElGamalKeypair, ElGamalRecipient};
+use crate::circuit::modules::poseidon::cipher::PoseidonCipherKey;
+use crate::circuit::modules::poseidon::membership::DatasetTree;
+use crate::circuit::modules::schnorr::SchnorrSignature;
 use crate::circuit::modules::ModulePlanner;
-use crate::circuit::CheckMode;
+use crate::circuit::region::RegionCtx;
+use crate::circuit::{CheckMode, Tolerance};
 use crate::commands::RunArgs;
 use crate::fieldutils::i128_to_felt;
 use crate::graph::modules::ModuleInstanceOffset;
-use crate::tensor::{Tensor, ValTensor};
+use crate::tensor::{Tensor, ValTensor, VarTensor};
 use halo2_proofs::{
     circuit::{Layouter, Value},
     plonk::{Circuit, ConstraintSystem, Error as PlonkError},
 };
 use halo2curves::bn256::{self, Fr as Fp};
 use halo2curves::ff::PrimeField;
-use log::{error, info, trace};
+use log::{error, info, trace, warn};
 pub use model::*;
 pub use node::*;
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::{Read, Write};
 use thiserror::Error;
 pub use utilities::*;
 pub use vars::*;
 
 use self::modules::{
-    GraphModules, ModuleConfigs, ModuleForwardResult, ModuleSettings, ModuleSizes,
+    GraphModules, ModuleConfigs, ModuleForwardResult, ModuleSettings, ModuleSizes, POSEIDON,
 };
+use self::registry::{ModuleContext, ModuleRegistry, ProverInputs};
 
 /// circuit related errors.
 #[derive(Debug, Error)]
@@ -68,9 +90,35 @@ pub enum GraphError {
     /// A node has missing parameters
     #[error("a node is has misformed params: {0}")]
     MisformedParams(String),
+    /// The instances of a proof don't hold the hashes its settings expect
+    #[error("the instances do not hold the expected poseidon hashes")]
+    MissingHashes,
+    /// The hashed outputs of a proof aren't the hashed inputs of the next proof of a chain
+    #[error("the hashed outputs of proof {0} do not match the hashed inputs of proof {1}")]
+    BrokenChain(usize, usize),
+    /// The model can't be given an accuracy head
+    #[error("cannot prove accuracy: {0}")]
+    AccuracyHead(String),
     /// Error in the configuration of the visibility of variables
     #[error("there should be at least one set of public variables")]
     Visibility,
+    /// The visibility only applies to inputs
+    #[error("only inputs can have {0} visibility")]
+    InputOnlyVisibility(Visibility),
+    /// The visibility doesn't apply to params
+    #[error("params cannot have {0} visibility")]
+    NoParamVisibility(Visibility),
+    /// A module cannot be registered under a name
+    #[error(
+        "a module cannot be registered as {0:?}, as the name is not an identifier of at most 32 characters or is a built-in visibility or module"
+    )]
+    ReservedModuleName(String),
+    /// A module is already registered under a name
+    #[error("a module is already registered as {0:?}")]
+    ModuleAlreadyRegistered(String),
+    /// No module is registered under a name a visibility selects
+    #[error("no module is registered as {0:?}")]
+    UnregisteredModule(String),
     /// Ezkl only supports divisions by constants
     #[error("ezkl currently only supports division by constants")]
     NonConstantDiv,
@@ -86,6 +134,17 @@ pub enum GraphError {
     /// Packing exponent is too large
     #[error("largest packing exponent exceeds max. try reducing the scale")]
     PackingExponent,
+    /// The witness assigned by a node differs from its output in the forward pass
+    #[error("the witness of node {0} ({1}) diverges from the forward pass")]
+    NodeDivergence(usize, String),
+    /// A node isn't laid out in the rows recorded in the settings
+    #[error(
+        "node {0} is not laid out in the rows recorded in the settings, which may be out of date"
+    )]
+    NodeOffsets(usize),
+    /// The commitments to the columns committed to with [Visibility::KZGCommit] can't be read from a proof
+    #[error("cannot read the kzg commitments from the proof: {0}")]
+    KZGCommitColumns(String),
 }
 
 const ASSUMED_BLINDING_FACTORS: usize = 6;
@@ -125,9 +184,59 @@ pub struct GraphSettings {
     pub required_lookups: Vec<LookupOp>,
     /// check mode
     pub check_mode: CheckMode,
+    /// per-node fixed point scales (keyed by node index) that override the global scale
+    #[serde(default)]
+    pub scale_overrides: BTreeMap<usize, u32>,
+    /// per-output tolerances (keyed by output index) that override the global tolerance
+    #[serde(default)]
+    pub output_tolerances: BTreeMap<usize, Tolerance>,
+    /// per-input visibilities (in order of appearance) that override the global input visibility
+    #[serde(default)]
+    pub input_visibilities: Vec<Visibility>,
+    /// per-output visibilities (in order of appearance) that override the global output visibility
+    #[serde(default)]
+    pub output_visibilities: Vec<Visibility>,
+    /// per-node param visibilities (keyed by node index or Onnx initializer name) that override the global param visibility
+    #[serde(default)]
+    pub param_visibilities: BTreeMap<String, Visibility>,
+    /// the shapes of the inputs and outputs (in order of appearance) committed to with [Visibility::KZGCommit]
+    #[serde(default)]
+    pub kzg_commit_shapes: Vec<Vec<usize>>,
+    /// the number of rows of each column committed to with [Visibility::KZGCommit] (see [GraphSettings::kzg_col_size]).
+    /// Two proofs only hold the same commitments to a tensor if they split it into columns the same way.
+    #[serde(default)]
+    pub kzg_commit_col_size: Option<usize>,
+    /// the number of elements encrypted by each ElGamal module layout, i.e of the encrypted inputs, params and outputs (see [Model::encrypted_lens])
+    #[serde(default)]
+    pub encrypted_lens: Vec<usize>,
+    /// the number of elements of each tensor encrypted with the Poseidon cipher, i.e of the symencrypted inputs,
+    /// params and outputs (see [Model::symencrypted_lens])
+    #[serde(default)]
+    pub symencrypted_lens: Vec<usize>,
+    /// who the encrypted inputs, params and outputs are encrypted to
+    #[serde(default)]
+    pub elgamal_recipient: ElGamalRecipient,
+    /// the rows the model's nodes are laid out in, such that independent nodes can be laid out in parallel.
+    /// The nodes are laid out sequentially if these are missing or were computed for a different logrows.
+    #[serde(default)]
+    pub node_offsets: NodeOffsets,
+    /// the modules the circuit can be laid out with. These aren't serialized, such that loaded settings hold the
+    /// built-in modules only until the user-defined ones are registered again (which only the library can do, see
+    /// [ModuleRegistry]).
+    #[serde(skip)]
+    pub modules: ModuleRegistry,
 }
 
 impl GraphSettings {
+    /// The number of rows of each column committed to with [Visibility::KZGCommit]: `kzg_commit_col_size` if set,
+    /// and otherwise all the rows but those assumed to be taken by blinding factors. This depends on the settings
+    /// alone (rather than on the blinding factors of the circuit), such that circuits with the same logrows split
+    /// a committed tensor into the same columns.
+    pub fn kzg_col_size(&self) -> usize {
+        self.kzg_commit_col_size
+            .unwrap_or((1 << self.run_args.logrows) - ASSUMED_BLINDING_FACTORS)
+    }
+
     /// calculate the total number of instances
     pub fn total_instances(&self) -> Vec<usize> {
         let mut instances: Vec<usize> = self
@@ -140,6 +249,37 @@ impl GraphSettings {
         instances
     }
 
+    /// The Poseidon hashes of the hashed inputs (see [Visibility::Hashed]) among the instances of a proof
+    pub fn input_hashes(&self, instances: &[Vec<Fp>]) -> Result<Vec<Fp>, GraphError> {
+        self.poseidon_hashes(instances, self.module_sizes.input_hash_positions())
+    }
+
+    /// The Poseidon hashes of the hashed outputs (see [Visibility::Hashed]) among the instances of a proof
+    pub fn output_hashes(&self, instances: &[Vec<Fp>]) -> Result<Vec<Fp>, GraphError> {
+        self.poseidon_hashes(instances, self.module_sizes.output_hash_positions())
+    }
+
+    fn poseidon_hashes(
+        &self,
+        instances: &[Vec<Fp>],
+        positions: std::ops::Range<usize>,
+    ) -> Result<Vec<Fp>, GraphError> {
+        if positions.is_empty() {
+            return Ok(vec![]);
+        }
+        // the modules' instance columns follow the model's
+        let column = self
+            .module_sizes
+            .instance_columns(POSEIDON)
+            .ok_or(GraphError::MissingHashes)?
+            .start;
+        instances
+            .get(self.model_instance_shapes.len() + column)
+            .and_then(|column| column.get(positions))
+            .map(|hashes| hashes.to_vec())
+            .ok_or(GraphError::MissingHashes)
+    }
+
     /// save params to file
     pub fn save(&self, path: &std::path::PathBuf) -> Result<(), std::io::Error> {
         let encoded = serde_json::to_string(&self)?;
@@ -174,6 +314,14 @@ pub struct GraphCircuit {
     pub settings: GraphSettings,
     /// The settings of the model's modules.
     pub module_settings: ModuleSettings,
+    /// The signature of each input with [Visibility::Signed], in order.
+    pub input_signatures: Vec<SchnorrSignature>,
+    /// The dataset inputs with [Visibility::Member] are rows of.
+    pub dataset_tree: Option<DatasetTree>,
+    /// The key shared with the recipient of the inputs, params and outputs with [Visibility::SymEncrypted].
+    pub cipher_key: Option<PoseidonCipherKey>,
+    /// In safe mode, the output of every node in a forward pass over `inputs`, which the witness is checked against.
+    pub node_outputs: Option<BTreeMap<usize, Tensor<i128>>>,
 }
 
 impl GraphCircuit {
@@ -182,6 +330,17 @@ impl GraphCircuit {
         model: Model,
         run_args: RunArgs,
         check_mode: CheckMode,
+    ) -> Result<GraphCircuit, Box<dyn std::error::Error>> {
+        Self::new_with_modules(model, run_args, check_mode, ModuleRegistry::default())
+    }
+
+    /// Creates a circuit whose inputs, params and outputs can be processed by the modules of `modules`, e.g by
+    /// user-defined modules selected with `custom:<name>` visibilities.
+    pub fn new_with_modules(
+        model: Model,
+        run_args: RunArgs,
+        check_mode: CheckMode,
+        modules: ModuleRegistry,
     ) -> Result<GraphCircuit, Box<dyn std::error::Error>> {
         // placeholder dummy inputs - must call prepare_public_inputs to load data afterwards
         let mut inputs: Vec<Tensor<i128>> = vec![];
@@ -195,19 +354,37 @@ impl GraphCircuit {
 
         let mut settings = model.gen_params(run_args, check_mode)?;
 
-        let mut num_params = 0;
-        if !model.const_shapes().is_empty() {
-            for shape in model.const_shapes() {
-                num_params += shape.iter().product::<usize>();
-            }
-        }
+        let visibility = VarVisibility::new(
+            run_args,
+            model.visibility.inputs.clone(),
+            model.visibility.outputs.clone(),
+            model.visibility.param_overrides.clone(),
+        )?;
+        modules.check(&visibility)?;
+        settings.modules = modules;
+
+        // the params of each visibility are flattened into a single tensor before being processed by the modules
+        let params_shapes = visibility
+            .module_visibilities()
+            .into_iter()
+            .map(|visibility| {
+                let num_params = model
+                    .const_shapes(visibility)
+                    .iter()
+                    .map(|shape| shape.iter().product::<usize>())
+                    .sum::<usize>();
+                (visibility, vec![num_params])
+            })
+            .collect();
 
         let sizes = GraphModules::num_constraints_and_instances(
+            &settings.modules,
             model.graph.input_shapes(),
-            vec![vec![num_params]],
+            params_shapes,
             model.graph.output_shapes(),
-            VarVisibility::from_args(run_args).unwrap(),
-        );
+            visibility,
+            &ModuleContext::from(&settings),
+        )?;
 
         // number of instances used by modules
         settings.module_sizes = sizes.clone();
@@ -215,11 +392,31 @@ impl GraphCircuit {
         // as they occupy independent rows
         settings.num_constraints = std::cmp::max(settings.num_constraints, sizes.max_constraints());
 
+        // the dummy layout assumes the blinding factors of a bare circuit, so we recompute the node offsets if those
+        // of the full circuit leave a different number of rows in each column
+        if !settings.node_offsets.ranges.is_empty() {
+            let mut cs = ConstraintSystem::default();
+            let config = Self::configure_with_params(&mut cs, settings.clone());
+            if settings.node_offsets.col_size != config.model_config.base.output.col_size() {
+                settings.node_offsets = model
+                    .dummy_layout_with_config(
+                        &config.model_config,
+                        &run_args,
+                        &model.graph.input_shapes(),
+                    )?
+                    .node_offsets;
+            }
+        }
+
         Ok(GraphCircuit {
             model,
             inputs,
             settings,
             module_settings,
+            input_signatures: vec![],
+            dataset_tree: None,
+            cipher_key: None,
+            node_outputs: None,
         })
     }
 
@@ -240,22 +437,37 @@ impl GraphCircuit {
         let module_settings = ModuleSettings::default();
 
         settings.check_mode = check_mode;
+        settings
+            .modules
+            .check(&VarVisibility::from_settings(&settings)?)?;
+        // settings sized with user-defined modules can't be configured until these are registered again
+        settings.modules.check_sizes(&settings.module_sizes)?;
 
         Ok(GraphCircuit {
             model,
             inputs,
             settings,
             module_settings,
+            input_signatures: vec![],
+            dataset_tree: None,
+            cipher_key: None,
+            node_outputs: None,
         })
     }
     ///
     pub fn load_inputs(&mut self, data: &[Vec<f32>]) {
-        // quantize the supplied data using the provided scale.
+        // quantize the supplied data using the provided scale, bar the one-hot labels of the accuracy head.
+        let labels_idx = self.model.graph.inputs.len().checked_sub(1);
         let mut inputs: Vec<Tensor<i128>> = vec![];
-        for (input, shape) in data.iter().zip(self.model.graph.input_shapes()) {
+        for (i, (input, shape)) in data.iter().zip(self.model.graph.input_shapes()).enumerate() {
+            let scale = if self.settings.run_args.prove_accuracy && Some(i) == labels_idx {
+                accuracy::LABELS_SCALE
+            } else {
+                self.settings.run_args.scale
+            };
             let t: Vec<i128> = input
                 .par_iter()
-                .map(|x| quantize_float(x, 0.0, self.settings.run_args.scale).unwrap())
+                .map(|x| quantize_float(x, 0.0, scale).unwrap())
                 .collect();
 
             let mut t: Tensor<i128> = t.into_iter().into();
@@ -264,6 +476,7 @@ impl GraphCircuit {
             inputs.push(t);
         }
         self.inputs = inputs;
+        self.update_node_outputs();
     }
     ///
     pub fn load_on_chain_inputs(&mut self, data: Vec<Vec<i128>>) {
@@ -275,14 +488,32 @@ impl GraphCircuit {
             inputs.push(t);
         }
         self.inputs = inputs;
+        self.update_node_outputs();
+    }
+
+    /// In safe mode, runs the forward pass the witness of every node is checked against, once per set of inputs
+    /// rather than on every synthesis.
+    fn update_node_outputs(&mut self) {
+        self.node_outputs = None;
+        if self.settings.check_mode != CheckMode::SAFE {
+            return;
+        }
+        match self.model.forward_nodes(&self.inputs) {
+            Ok((node_outputs, _)) => self.node_outputs = Some(node_outputs),
+            Err(e) => warn!(
+                "the witness won't be checked against the forward pass: {}",
+                e
+            ),
+        }
     }
 
     /// Calibrate the circuit to the supplied data.
     pub fn calibrate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let res = self.forward()?;
+        // only the model's lookups matter here, so we skip the modules (which may require signatures)
+        let res = self.model.forward(&self.inputs)?;
         let max_range = 2i128.pow(self.settings.run_args.bits as u32 - 1);
-        if res.max_lookup_input > max_range {
-            let recommended_bits = (res.max_lookup_input as f64).log2().ceil() as usize + 1;
+        if res.max_lookup_inputs > max_range {
+            let recommended_bits = (res.max_lookup_inputs as f64).log2().ceil() as usize + 1;
 
             if recommended_bits <= (MAX_PUBLIC_SRS - 1) as usize {
                 self.settings.run_args.bits = recommended_bits;
@@ -293,7 +524,7 @@ impl GraphCircuit {
                 return Err(err_string.into());
             }
         } else {
-            let min_bits = (res.max_lookup_input as f64).log2().ceil() as usize + 1;
+            let min_bits = (res.max_lookup_inputs as f64).log2().ceil() as usize + 1;
 
             let min_rows_from_constraints = (self.settings.num_constraints as f64
                 + ASSUMED_BLINDING_FACTORS as f64)
@@ -318,44 +549,119 @@ impl GraphCircuit {
             self.settings.run_args.logrows = logrows as u32;
         }
 
-        self.settings = GraphCircuit::new(
+        self.settings = GraphCircuit::new_with_modules(
             self.model.clone(),
             self.settings.run_args,
             self.settings.check_mode,
+            self.settings.modules.clone(),
         )?
         .settings;
 
         Ok(())
     }
 
+    /// The Poseidon hash of the params with [Visibility::Hashed], as it appears in the instances of the circuit's proofs.
+    /// Returns `None` if none of the params are hashed.
+    pub fn params_hash(&self) -> Result<Option<Fp>, Box<dyn std::error::Error>> {
+        let consts = self.model.get_all_consts(Visibility::Hashed);
+        if consts.is_empty() {
+            return Ok(None);
+        }
+        // the hashed params are flattened into a single tensor, as in the forward pass
+        let flattened_params: Tensor<i128> = flatten_valtensors(consts)?
+            .get_int_evals()?
+            .into_iter()
+            .into();
+        let res = GraphModules::forward(
+            &self.settings.modules,
+            &[flattened_params],
+            Visibility::Hashed,
+            &ModuleContext::from(&self.settings),
+            &ProverInputs::default(),
+        )?;
+        Ok(res
+            .poseidon_hash()?
+            .and_then(|hashes| hashes.first().copied()))
+    }
+
     /// Runs the forward pass of the model / graph of computations and any associated hashing.
     pub fn forward(&self) -> Result<ForwardResult, Box<dyn std::error::Error>> {
-        let visibility = VarVisibility::from_args(self.settings.run_args)?;
-        let mut processed_inputs = None;
-        let mut processed_params = None;
-        let mut processed_outputs = None;
+        self.forward_with_elgamal_keypair(None)
+    }
 
-        if visibility.input.requires_processing() {
-            processed_inputs = Some(GraphModules::forward(&self.inputs, visibility.input)?);
+    /// Runs the forward pass, encrypting any encrypted inputs, params and outputs to `elgamal_keypair`
+    /// (instead of to a freshly generated keypair). The symencrypted inputs, params and outputs are all encrypted
+    /// under [GraphCircuit::cipher_key], or under a single freshly generated key if it is unset.
+    pub fn forward_with_elgamal_keypair(
+        &self,
+        elgamal_keypair: Option<&ElGamalKeypair>,
+    ) -> Result<ForwardResult, Box<dyn std::error::Error>> {
+        let visibility = VarVisibility::from_settings(&self.settings)?;
+
+        if let Some(tree) = &self.dataset_tree {
+            if tree.depth() != self.settings.run_args.membership_depth {
+                return Err(format!(
+                    "the dataset tree has depth {} but the settings expect depth {}",
+                    tree.depth(),
+                    self.settings.run_args.membership_depth
+                )
+                .into());
+            }
         }
 
-        if visibility.params.requires_processing() {
-            let params = self.model.get_all_consts();
-            let flattened_params = flatten_valtensors(params)?
-                .get_int_evals()?
-                .into_iter()
-                .into();
-            processed_params = Some(GraphModules::forward(
-                &[flattened_params],
-                visibility.params,
-            )?);
+        let cipher_key = self
+            .cipher_key
+            .unwrap_or_else(|| PoseidonCipherKey::gen_random(&mut rand::thread_rng()));
+
+        let ctx = ModuleContext::from(&self.settings);
+        let prover = ProverInputs {
+            elgamal_keypair,
+            cipher_key: Some(&cipher_key),
+            ..Default::default()
+        };
+
+        let processed_inputs = GraphModules::forward_per_tensor(
+            &self.settings.modules,
+            &self.inputs,
+            &visibility.inputs_for(self.inputs.len()),
+            &ctx,
+            &ProverInputs {
+                signatures: &self.input_signatures,
+                dataset: self.dataset_tree.as_ref(),
+                ..prover
+            },
+        )?;
+
+        let mut params = vec![];
+        let mut param_visibilities = vec![];
+        for param_visibility in visibility.module_visibilities() {
+            let consts = self.model.get_all_consts(param_visibility);
+            if !consts.is_empty() {
+                let flattened_params: Tensor<i128> = flatten_valtensors(consts)?
+                    .get_int_evals()?
+                    .into_iter()
+                    .into();
+                params.push(flattened_params);
+                param_visibilities.push(param_visibility);
+            }
         }
+        let processed_params = GraphModules::forward_per_tensor(
+            &self.settings.modules,
+            &params,
+            &param_visibilities,
+            &ctx,
+            &prover,
+        )?;
 
         let outputs = self.model.forward(&self.inputs)?;
 
-        if visibility.output.requires_processing() {
-            processed_outputs = Some(GraphModules::forward(&outputs.outputs, visibility.output)?);
-        }
+        let processed_outputs = GraphModules::forward_per_tensor(
+            &self.settings.modules,
+            &outputs.outputs,
+            &visibility.outputs_for(outputs.outputs.len()),
+            &ctx,
+            &prover,
+        )?;
 
         Ok(ForwardResult {
             inputs: self.inputs.clone(),
@@ -383,7 +689,7 @@ impl GraphCircuit {
         model_path: &std::path::PathBuf,
         check_mode: CheckMode,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let model = Model::from_run_args(&params.run_args, model_path)?;
+        let model = Model::from_settings(params, model_path)?;
         Self::new_from_settings(model, params.clone(), check_mode)
     }
 
@@ -407,12 +713,15 @@ impl GraphCircuit {
         // quantize the supplied data using the provided scale.
         // the ordering here is important, we want the inputs to come before the outputs
         // as they are configured in that order as Column<Instances>
+        let visibility = VarVisibility::from_settings(&self.settings)?;
         let mut public_inputs = vec![];
-        if self.settings.run_args.input_visibility.is_public() {
-            public_inputs = self.inputs.clone();
+        for (idx, input) in self.inputs.iter().enumerate() {
+            if visibility.input_at(idx).is_public() {
+                public_inputs.push(input.clone());
+            }
         }
-        if self.settings.run_args.output_visibility.is_public() {
-            for (idx, v) in data.output_data.iter().enumerate() {
+        for (idx, v) in data.output_data.iter().enumerate() {
+            if visibility.output_at(idx).is_public() {
                 let t: Vec<i128> = v
                     .par_iter()
                     .map(|x| quantize_float(x, 0.0, out_scales[idx]).unwrap())
@@ -442,7 +751,7 @@ impl GraphCircuit {
             .collect::<Vec<Vec<Fp>>>();
 
         let module_instances =
-            GraphModules::public_inputs(data, VarVisibility::from_args(self.settings.run_args)?);
+            GraphModules::public_inputs(&self.settings.modules, data, visibility)?;
 
         if !module_instances.is_empty() {
             pi_inner.extend(module_instances);
@@ -450,6 +759,83 @@ impl GraphCircuit {
 
         Ok(pi_inner)
     }
+
+    /// Maps mock prover failures back onto the model nodes (and output coordinates) that were laid out at the failing cells,
+    /// using the node offsets recorded in the settings (which [Model::layout] checks the real layout against).
+    pub fn locate_failures(
+        &self,
+        failures: &[halo2_proofs::dev::VerifyFailure],
+    ) -> Vec<NodeFailure> {
+        let node_offsets = &self.settings.node_offsets;
+        if node_offsets.ranges.is_empty() {
+            warn!("the settings hold no node offsets, regenerate them to map failures onto nodes");
+            return vec![];
+        }
+
+        let mut cs = ConstraintSystem::default();
+        let config = Self::configure_with_params(&mut cs, self.settings.clone());
+        let vars = &config.model_config.vars;
+        let columns = ModelColumns {
+            columns: vars
+                .advices
+                .iter()
+                .chain(vars.fixed.iter())
+                .map(|var| match var {
+                    VarTensor::Advice { inner, .. } => inner.iter().map(|c| (*c).into()).collect(),
+                    VarTensor::Fixed { inner, .. } => inner.iter().map(|c| (*c).into()).collect(),
+                    _ => vec![],
+                })
+                .collect(),
+            lookup_columns: config.model_config.base.lookup_columns.clone(),
+            col_size: config.model_config.base.output.col_size(),
+        };
+        if columns.col_size != node_offsets.col_size {
+            warn!("the node offsets in the settings are out of date, regenerate them to map failures onto nodes");
+            return vec![];
+        }
+
+        self.model.locate_failures(node_offsets, &columns, failures)
+    }
+
+    /// The indices of the advice columns holding each input and output with [Visibility::KZGCommit] (inputs first).
+    /// With KZG, instances aren't written to the transcript, so a proof starts with the commitments to the advice
+    /// columns of the first phase, in order of column index. This checks that the committed columns (and the columns
+    /// before them) are all in the first phase, and that the committed columns fit in the rows left by the blinding factors.
+    pub fn kzg_commit_columns(
+        settings: &GraphSettings,
+    ) -> Result<Vec<Vec<usize>>, Box<dyn std::error::Error>> {
+        let mut cs = ConstraintSystem::default();
+        let config = Self::configure_with_params(&mut cs, settings.clone());
+        let columns: Vec<Vec<usize>> = config
+            .model_config
+            .vars
+            .kzg_commits
+            .iter()
+            .map(|var| match var {
+                VarTensor::Advice { inner, .. } => inner.iter().map(|c| c.index()).collect(),
+                _ => vec![],
+            })
+            .collect();
+
+        let num_commitments = columns.iter().flatten().max().map_or(0, |i| i + 1);
+        if cs.advice_column_phase()[..num_commitments]
+            .iter()
+            .any(|phase| *phase != 0)
+        {
+            return Err(Box::new(GraphError::KZGCommitColumns(
+                "the committed columns are not all in the first phase".to_string(),
+            )));
+        }
+        let usable_rows = (1 << settings.run_args.logrows) - cs.blinding_factors() - 1;
+        if settings.kzg_col_size() > usable_rows {
+            return Err(Box::new(GraphError::KZGCommitColumns(format!(
+                "the committed columns have {} rows but only {} are usable",
+                settings.kzg_col_size(),
+                usable_rows
+            ))));
+        }
+        Ok(columns)
+    }
 }
 
 impl Circuit<Fp> for GraphCircuit {
@@ -467,7 +853,7 @@ impl Circuit<Fp> for GraphCircuit {
     }
 
     fn configure_with_params(cs: &mut ConstraintSystem<Fp>, params: Self::Params) -> Self::Config {
-        let visibility = VarVisibility::from_args(params.run_args).unwrap();
+        let visibility = VarVisibility::from_settings(&params).unwrap();
 
         let mut vars = ModelVars::new(
             cs,
@@ -477,6 +863,7 @@ impl Circuit<Fp> for GraphCircuit {
             visibility.clone(),
             params.run_args.scale,
         );
+        vars.configure_kzg_commits(cs, params.kzg_col_size(), &params.kzg_commit_shapes);
 
         let base = Model::configure(
             cs,
@@ -489,7 +876,13 @@ impl Circuit<Fp> for GraphCircuit {
 
         let model_config = ModelConfig { base, vars };
 
-        let module_configs = ModuleConfigs::from_visibility(cs, visibility, params.module_sizes);
+        let module_configs = ModuleConfigs::configure(
+            cs,
+            &params.modules,
+            &params.module_sizes,
+            ModuleContext::from(&params),
+        )
+        .unwrap();
 
         trace!(
             "log2_ceil of degree: {:?}",
@@ -521,79 +914,104 @@ impl Circuit<Fp> for GraphCircuit {
         let mut instance_offset = ModuleInstanceOffset::new();
 
         trace!("running input module layout");
-        // we reserve module 0 for poseidon
-        // we reserve module 1 for elgamal
-        GraphModules::layout(
+        // we reserve a module for each of the configured modules, in the order of their instance columns
+        let visibility = VarVisibility::from_settings(&self.settings).map_err(|e| {
+            log::error!("{}", e);
+            PlonkError::Synthesis
+        })?;
+        for module_space in 0..config.module_configs.len() {
+            layouter.assign_region(|| format!("_enter_module_{}", module_space), |_| Ok(()))?;
+        }
+        GraphModules::layout_per_tensor(
             &mut layouter,
             &config.module_configs,
             &mut inputs,
-            self.settings.run_args.input_visibility,
+            &visibility.inputs_for(inputs.len()),
             &mut instance_offset,
             &self.module_settings.input,
         )?;
 
-        trace!("flattening params");
-        // now we need to flatten the params
-        let mut flattened_params = vec![];
-        if !self.model.get_all_consts().is_empty() {
-            flattened_params =
-                vec![
-                    flatten_valtensors(self.model.get_all_consts()).map_err(|_| {
-                        log::error!("failed to flatten params");
-                        PlonkError::Synthesis
-                    })?,
-                ];
-        }
+        let mut model = self.model.clone();
+        for param_visibility in visibility.module_visibilities() {
+            let consts = self.model.get_all_consts(param_visibility);
+            if consts.is_empty() {
+                continue;
+            }
 
-        trace!("running params module layout");
-        // now do stuff to the model params
-        GraphModules::layout(
-            &mut layouter,
-            &config.module_configs,
-            &mut flattened_params,
-            self.settings.run_args.param_visibility,
-            &mut instance_offset,
-            &self.module_settings.params,
-        )?;
+            trace!("flattening {} params", param_visibility);
+            // now we need to flatten the params
+            let mut flattened_params = vec![flatten_valtensors(consts).map_err(|_| {
+                log::error!("failed to flatten params");
+                PlonkError::Synthesis
+            })?];
 
-        trace!("replacing params");
-        // now we need to assign the flattened params to the model
-        let mut model = self.model.clone();
-        if !self.model.get_all_consts().is_empty() {
+            trace!("running {} params module layout", param_visibility);
+            // now do stuff to the model params
+            GraphModules::layout(
+                &mut layouter,
+                &config.module_configs,
+                &mut flattened_params,
+                param_visibility,
+                &mut instance_offset,
+                &self.module_settings.params,
+            )?;
+
+            trace!("replacing {} params", param_visibility);
             // now the flattened_params have been assigned to and we-assign them to the model consts such that they are constrained to be equal
             model.replace_consts(
-                split_valtensor(flattened_params[0].clone(), self.model.const_shapes()).map_err(
-                    |_| {
-                        log::error!("failed to replace params");
-                        PlonkError::Synthesis
-                    },
-                )?,
+                split_valtensor(
+                    flattened_params[0].clone(),
+                    self.model.const_shapes(param_visibility),
+                )
+                .map_err(|_| {
+                    log::error!("failed to replace params");
+                    PlonkError::Synthesis
+                })?,
+                param_visibility,
             );
         }
 
-        // create a new module for the model (space 2)
+        // create a new module for the model (after the modules')
         layouter.assign_region(|| "_new_module", |_| Ok(()))?;
+
+        let mut kzg_commits = config.model_config.vars.kzg_commits.iter();
+        for (i, input) in inputs.iter_mut().enumerate() {
+            if visibility.input_at(i).is_kzgcommit() {
+                trace!("committing to input {}", i);
+                *input = layout_kzg_commit(&mut layouter, kzg_commits.next(), input)?;
+            }
+        }
+
         trace!("Laying out model");
         let mut outputs = model
             .layout(
                 config.model_config.clone(),
                 &mut layouter,
                 &self.settings.run_args,
+                &self.settings.node_offsets,
                 &inputs,
                 &config.model_config.vars,
+                self.node_outputs.as_ref(),
             )
             .map_err(|e| {
                 log::error!("{}", e);
                 PlonkError::Synthesis
             })?;
 
+        for (i, output) in outputs.iter_mut().enumerate() {
+            if visibility.output_at(i).is_kzgcommit() {
+                trace!("committing to output {}", i);
+                *output = layout_kzg_commit(&mut layouter, kzg_commits.next(), output)?;
+            }
+        }
+
         trace!("running output module layout");
-        // this will re-enter module 0
-        GraphModules::layout(
+        // this will re-enter the modules' module spaces
+        GraphModules::layout_per_tensor(
             &mut layouter,
             &config.module_configs,
             &mut outputs,
-            self.settings.run_args.output_visibility,
+            &visibility.outputs_for(outputs.len()),
             &mut instance_offset,
             &self.module_settings.output,
         )?;
@@ -601,3 +1019,20 @@ impl Circuit<Fp> for GraphCircuit {
         Ok(())
     }
 }
+
+/// Assigns a tensor to its dedicated (unblinded) advice column, such that the column's commitment in
+/// the proof is a commitment to the tensor.
+fn layout_kzg_commit(
+    layouter: &mut impl Layouter<Fp>,
+    var: Option<&VarTensor>,
+    value: &ValTensor<Fp>,
+) -> Result<ValTensor<Fp>, PlonkError> {
+    let var = var.ok_or_else(|| {
+        log::error!("missing column for kzg commitment");
+        PlonkError::Synthesis
+    })?;
+    layouter.assign_region(
+        || "kzg_commit",
+        |region| RegionCtx::new(region, 0).assign(var, value),
+    )
+}
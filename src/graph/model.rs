@@ -5,11 +5,12 @@ use super::vars::*;
 use super::GraphError;
 use super::GraphSettings;
 use crate::circuit::hybrid::HybridOp;
+use crate::circuit::modules::elgamal::ElGamalRecipient;
 use crate::circuit::region::RegionCtx;
 use crate::circuit::Input;
 use crate::circuit::Unknown;
 use crate::{
-    circuit::{lookup::LookupOp, BaseConfig as PolyConfig, CheckMode, Op},
+    circuit::{lookup::LookupOp, BaseConfig as PolyConfig, CheckMode, Op, Tolerance},
     commands::RunArgs,
     tensor::{Tensor, ValTensor},
 };
@@ -17,7 +18,8 @@ use halo2curves::bn256::Fr as Fp;
 
 use colored::Colorize;
 use tract_onnx::prelude::{
-    DatumExt, Graph, InferenceFact, InferenceModelExt, SymbolValues, TypedFact, TypedOp,
+    tvec, DatumExt, Graph, InferenceFact, InferenceModelExt, OutletId, SimplePlan, SymbolValues,
+    TValue, TVec, Tensor as TractTensor, TypedFact, TypedOp,
 };
 use tract_onnx::tract_hir::ops::scan::Scan;
 
@@ -28,11 +30,14 @@ use halo2_proofs::{
 };
 use itertools::Itertools;
 use log::error;
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::error::Error;
-use tabled::Table;
+use std::ops::Range;
+use tabled::{Table, Tabled};
 use tract_onnx;
 use tract_onnx::prelude::Framework;
 
@@ -45,6 +50,130 @@ pub struct ForwardResult {
     pub max_lookup_inputs: i128,
 }
 
+/// The rows of the model's region each of the (top level) nodes is laid out in. These are computed once by the dummy
+/// layout pass when generating the settings, such that independent nodes can then be laid out in parallel.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct NodeOffsets {
+    /// The number of rows in each of the columns the offsets were computed for, as values wrap around columns.
+    pub col_size: usize,
+    /// The (linear) row range each node is laid out in.
+    pub ranges: BTreeMap<usize, Range<usize>>,
+    /// The row offset directly after the last node.
+    pub end: usize,
+    /// The (linear) row range each range check of a public output is laid out in, after `end`, keyed by output node.
+    pub output_checks: Vec<(usize, Range<usize>)>,
+}
+
+impl NodeOffsets {
+    /// Returns the nodes whose rows (or output range checks) contain the (linear) row `offset`.
+    pub fn nodes_at(&self, offset: usize) -> Vec<(usize, &Range<usize>)> {
+        self.ranges
+            .iter()
+            .map(|(idx, range)| (*idx, range))
+            .chain(self.output_checks.iter().map(|(idx, range)| (*idx, range)))
+            .filter(|(_, range)| range.contains(&offset))
+            .collect()
+    }
+}
+
+/// The row usage of a model, as computed by a dummy layout pass.
+#[derive(Clone, Debug, Default)]
+pub struct ModelLayout {
+    /// The total number of rows used by the model (including any output range checks).
+    pub num_rows: usize,
+    /// The rows each of the (top level) nodes is laid out in.
+    pub node_offsets: NodeOffsets,
+    /// The resources consumed by each of the (top level) nodes.
+    pub node_costs: Vec<NodeCost>,
+}
+
+impl ModelLayout {
+    /// Formats the resources consumed by each node into a table, followed by the totals.
+    pub fn table_costs(&self) -> String {
+        let mut table = Table::new(self.node_costs.iter());
+        table.with(tabled::settings::Style::modern());
+
+        let mut lookups = BTreeMap::<String, usize>::new();
+        for cost in &self.node_costs {
+            for (op, n) in &cost.lookups {
+                *lookups.entry(op.clone()).or_insert(0) += n;
+            }
+        }
+        format!(
+            "{}\n total rows: {}, constants: {}, instances: {}\n total lookups:\n{}",
+            table,
+            self.num_rows,
+            self.node_costs.iter().map(|c| c.constants).sum::<usize>(),
+            self.node_costs.iter().map(|c| c.instances).sum::<usize>(),
+            display_lookups(&lookups)
+        )
+    }
+}
+
+fn display_lookups(lookups: &BTreeMap<String, usize>) -> String {
+    lookups
+        .iter()
+        .map(|(op, n)| format!("{}: {}", op, n))
+        .join("\n")
+}
+
+/// The resources consumed by a single node, as recorded during a dummy layout pass.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Tabled)]
+pub struct NodeCost {
+    /// The node's unique identifier.
+    pub idx: usize,
+    /// The node's operation.
+    pub op: String,
+    /// The number of rows consumed by the node (including the range check against public outputs).
+    pub rows: usize,
+    /// The number of lookups performed by the node, per lookup table.
+    #[tabled(display_with = "display_lookups")]
+    pub lookups: BTreeMap<String, usize>,
+    /// The number of constant cells assigned by the node.
+    pub constants: usize,
+    /// The number of instance cells the node's values are copied from or constrained against.
+    pub instances: usize,
+}
+
+impl NodeCost {
+    /// The resources consumed in a region so far.
+    fn snapshot(region: &RegionCtx<Fp>) -> Self {
+        NodeCost {
+            rows: region.offset(),
+            lookups: region
+                .lookups()
+                .iter()
+                .map(|(op, n)| (format!("{:?}", op), *n))
+                .collect(),
+            constants: region.total_constants(),
+            ..Default::default()
+        }
+    }
+
+    /// The resources consumed between `before` and this snapshot.
+    fn since(mut self, before: &NodeCost) -> Self {
+        self.rows -= before.rows;
+        self.constants -= before.constants;
+        for (op, n) in &before.lookups {
+            if let Some(m) = self.lookups.get_mut(op) {
+                *m -= n;
+            }
+        }
+        self.lookups.retain(|_, n| *n > 0);
+        self
+    }
+
+    /// Adds the resources consumed in `other` to this cost.
+    fn add(&mut self, other: &NodeCost) {
+        self.rows += other.rows;
+        self.constants += other.constants;
+        self.instances += other.instances;
+        for (op, n) in &other.lookups {
+            *self.lookups.entry(op.clone()).or_insert(0) += n;
+        }
+    }
+}
+
 /// A circuit configuration for the entirety of a model loaded from an Onnx file.
 #[derive(Clone, Debug)]
 pub struct ModelConfig {
@@ -64,6 +193,14 @@ pub struct Model {
     pub graph: ParsedNodes,
     /// Defines which inputs to the model are public and private (params, inputs, outputs) using [VarVisibility].
     pub visibility: VarVisibility,
+    /// Per-node fixed point scales that override the global [RunArgs] scale.
+    pub scale_overrides: BTreeMap<usize, u32>,
+    /// Per-output tolerances (keyed by output index) that override the global [RunArgs] tolerance.
+    pub output_tolerances: BTreeMap<usize, Tolerance>,
+    /// The param visibilities of the nodes matched by [VarVisibility::param_overrides], keyed by node index.
+    pub param_visibilities: BTreeMap<usize, Visibility>,
+    /// Who the encrypted inputs, params and outputs are encrypted to.
+    pub elgamal_recipient: ElGamalRecipient,
 }
 
 /// Enables model as subnode of other models
@@ -148,9 +285,9 @@ impl NodeType {
 #[derive(Clone, Debug, Default)]
 /// A set of EZKL nodes that represent a computational graph.
 pub struct ParsedNodes {
-    nodes: BTreeMap<usize, NodeType>,
-    inputs: Vec<usize>,
-    outputs: Vec<usize>,
+    pub(crate) nodes: BTreeMap<usize, NodeType>,
+    pub(crate) inputs: Vec<usize>,
+    pub(crate) outputs: Vec<usize>,
 }
 
 impl ParsedNodes {
@@ -182,6 +319,14 @@ impl ParsedNodes {
             .collect_vec()
     }
 
+    /// Returns the fixed point scale of the computational graph's inputs
+    pub fn get_input_scales(&self) -> Vec<u32> {
+        let input_nodes = self.inputs.iter();
+        input_nodes
+            .flat_map(|o| self.nodes.get(o).unwrap().out_scales())
+            .collect_vec()
+    }
+
     /// Returns the fixed point scale of the computational graph's outputs
     pub fn get_output_scales(&self) -> Vec<u32> {
         let output_nodes = self.outputs.iter();
@@ -189,6 +334,28 @@ impl ParsedNodes {
             .flat_map(|o| self.nodes.get(o).unwrap().out_scales())
             .collect_vec()
     }
+
+    /// Groups the nodes into sets where each set only depends on nodes in previous sets.
+    /// Nodes within a set are independent of one another and can be laid out in parallel.
+    pub fn independent_sets(&self) -> Vec<Vec<usize>> {
+        let mut depths = BTreeMap::<usize, usize>::new();
+        let mut sets: Vec<Vec<usize>> = vec![];
+        for (idx, node) in self.nodes.iter() {
+            let depth = node
+                .inputs()
+                .iter()
+                .filter_map(|i| depths.get(i))
+                .max()
+                .map(|d| d + 1)
+                .unwrap_or(0);
+            depths.insert(*idx, depth);
+            if sets.len() <= depth {
+                sets.resize(depth + 1, vec![]);
+            }
+            sets[depth].push(*idx);
+        }
+        sets
+    }
 }
 
 impl Model {
@@ -205,11 +372,54 @@ impl Model {
     /// * `reader` - A reader for an Onnx file.
     /// * `run_args` - [RunArgs]
     pub fn new(reader: &mut dyn std::io::Read, run_args: RunArgs) -> Result<Self, Box<dyn Error>> {
-        let visibility = VarVisibility::from_args(run_args)?;
+        Self::new_with_scale_overrides(reader, run_args, BTreeMap::new())
+    }
 
-        let graph = Self::load_onnx_model(reader, &run_args, &visibility)?;
+    /// Creates a `Model` from a specified path to an Onnx file, quantizing the nodes in `scale_overrides` at their own scale.
+    /// # Arguments
+    /// * `reader` - A reader for an Onnx file.
+    /// * `run_args` - [RunArgs]
+    /// * `scale_overrides` - Per-node scales, keyed by node index.
+    pub fn new_with_scale_overrides(
+        reader: &mut dyn std::io::Read,
+        run_args: RunArgs,
+        scale_overrides: BTreeMap<usize, u32>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::new_with_visibility(
+            reader,
+            run_args,
+            VarVisibility::from_args(run_args)?,
+            scale_overrides,
+        )
+    }
+
+    fn new_with_visibility(
+        reader: &mut dyn std::io::Read,
+        run_args: RunArgs,
+        visibility: VarVisibility,
+        scale_overrides: BTreeMap<usize, u32>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let typed = Self::load_typed_onnx(reader, &run_args)?;
+        Self::from_typed_with_visibility(&typed, run_args, visibility, scale_overrides)
+    }
+
+    fn from_typed_with_visibility(
+        typed: &Graph<TypedFact, Box<dyn TypedOp>>,
+        run_args: RunArgs,
+        visibility: VarVisibility,
+        scale_overrides: BTreeMap<usize, u32>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (graph, param_visibilities) =
+            Self::load_onnx_model(typed, &run_args, &visibility, &scale_overrides)?;
 
-        let om = Model { graph, visibility };
+        let om = Model {
+            graph,
+            visibility,
+            scale_overrides,
+            output_tolerances: BTreeMap::new(),
+            param_visibilities,
+            elgamal_recipient: ElGamalRecipient::default(),
+        };
 
         debug!("\n {}", om.table_nodes());
 
@@ -226,12 +436,13 @@ impl Model {
         // this is the total number of variables we will need to allocate
         // for the circuit
 
-        let num_constraints = if let Some(num_constraints) = run_args.allocated_constraints {
-            num_constraints
-        } else {
-            self.dummy_layout(&run_args, &self.graph.input_shapes())
-                .unwrap()
+        // the dummy layout pass counts the constraints and records the node offsets, which we do without when the
+        // constraints are allocated (at the cost of laying out the nodes sequentially)
+        let layout = match run_args.allocated_constraints {
+            Some(_) => ModelLayout::default(),
+            None => self.dummy_layout(&run_args, &self.graph.input_shapes())?,
         };
+        let num_constraints = run_args.allocated_constraints.unwrap_or(layout.num_rows);
 
         // Then number of columns in the circuits
         info!(
@@ -246,13 +457,9 @@ impl Model {
 
         // if we're using percentage tolerance, we need to add the necessary range check ops for it.
 
-        if run_args.tolerance.val > 0.0 {
-            for scale in self.graph.get_output_scales() {
-                let mut tolerance = run_args.tolerance;
-                tolerance.scales = (
-                    scale_to_multiplier(scale) as usize,
-                    scale_to_multiplier(run_args.scale) as usize,
-                );
+        for i in 0..self.graph.outputs.len() {
+            let tolerance = self.output_tolerance(i, &run_args);
+            if tolerance.val > 0.0 {
                 let opkind: Box<dyn Op<Fp>> = Box::new(HybridOp::RangeCheck(tolerance));
                 lookup_ops.extend(opkind.required_lookups());
             }
@@ -271,16 +478,99 @@ impl Model {
             num_constraints,
             required_lookups: lookup_ops,
             check_mode,
+            scale_overrides: self.scale_overrides.clone(),
+            output_tolerances: self.output_tolerances.clone(),
+            input_visibilities: self.visibility.inputs.clone(),
+            output_visibilities: self.visibility.outputs.clone(),
+            param_visibilities: self.visibility.param_overrides.clone(),
+            kzg_commit_shapes: self.kzg_commit_shapes(),
+            kzg_commit_col_size: None,
+            encrypted_lens: self.encrypted_lens(),
+            symencrypted_lens: self.symencrypted_lens(),
+            elgamal_recipient: self.elgamal_recipient,
+            node_offsets: layout.node_offsets,
+            modules: Default::default(),
         })
     }
 
+    /// The index of the instance column holding the `i`th input, if the input is public.
+    pub fn input_instance_idx(&self, i: usize) -> Option<usize> {
+        if !self.visibility.input_at(i).is_public() {
+            return None;
+        }
+        Some(
+            (0..i)
+                .filter(|j| self.visibility.input_at(*j).is_public())
+                .count(),
+        )
+    }
+
+    /// The index of the instance column holding the `i`th output, if the output is public.
+    /// Public outputs are placed after all public inputs.
+    pub fn output_instance_idx(&self, i: usize) -> Option<usize> {
+        if !self.visibility.output_at(i).is_public() {
+            return None;
+        }
+        let num_public_inputs = (0..self.graph.inputs.len())
+            .filter(|j| self.visibility.input_at(*j).is_public())
+            .count();
+        Some(
+            num_public_inputs
+                + (0..i)
+                    .filter(|j| self.visibility.output_at(*j).is_public())
+                    .count(),
+        )
+    }
+
+    /// The tolerance on the `i`th output (falling back on the global [RunArgs] tolerance),
+    /// with its scales set to the output's scale and the global scale.
+    pub fn output_tolerance(&self, i: usize, run_args: &RunArgs) -> Tolerance {
+        let mut tolerance = self
+            .output_tolerances
+            .get(&i)
+            .cloned()
+            .unwrap_or(run_args.tolerance);
+        tolerance.scales = (
+            scale_to_multiplier(self.graph.get_output_scales()[i]) as usize,
+            scale_to_multiplier(run_args.scale) as usize,
+        );
+        tolerance
+    }
+
     /// Runs a forward pass on sample data !
     /// # Arguments
     /// * `reader` - A reader for an Onnx file.
     /// * `model_inputs` - A vector of [Tensor]s to use as inputs to the model.
     /// * `run_args` - [RunArgs]
     pub fn forward(&self, model_inputs: &[Tensor<i128>]) -> Result<ForwardResult, Box<dyn Error>> {
-        let mut results: BTreeMap<&usize, Tensor<i128>> = BTreeMap::new();
+        let (results, max_lookup_inputs) = self.forward_nodes(model_inputs)?;
+
+        let output_nodes = self.graph.outputs.iter();
+        debug!(
+            "model outputs are nodes: {:?}",
+            output_nodes.clone().collect_vec()
+        );
+        let outputs = output_nodes
+            .map(|o| results.get(o).unwrap().clone().map(|x| x))
+            .collect_vec();
+
+        let res = ForwardResult {
+            outputs,
+            max_lookup_inputs,
+        };
+
+        Ok(res)
+    }
+
+    /// Runs a forward pass on sample data, returning the integer output of every node (keyed by node index)
+    /// and the largest input to any lookup.
+    /// # Arguments
+    /// * `model_inputs` - A vector of [Tensor]s to use as inputs to the model.
+    pub fn forward_nodes(
+        &self,
+        model_inputs: &[Tensor<i128>],
+    ) -> Result<(BTreeMap<usize, Tensor<i128>>, i128), Box<dyn Error>> {
+        let mut results: BTreeMap<usize, Tensor<i128>> = BTreeMap::new();
         let mut max_lookup_inputs = 0;
         let mut input_idx = 0;
         for (idx, n) in self.graph.nodes.iter() {
@@ -294,7 +584,7 @@ impl Model {
                 debug!("executing {}: {}", idx, n.as_str());
                 trace!("dims: {:?}", n.out_dims());
                 for i in n.inputs().iter() {
-                    match results.get(&i) {
+                    match results.get(i) {
                         Some(value) => inputs.push(value.clone()),
                         None => return Err(Box::new(GraphError::MissingNode(*i))),
                     }
@@ -321,7 +611,7 @@ impl Model {
                         max_lookup_inputs = max_lookup_inputs.max(max);
                     }
 
-                    results.insert(idx, res.output);
+                    results.insert(*idx, res.output);
                 }
                 NodeType::SubGraph { model, .. } => {
                     let res = model.forward(&inputs)?;
@@ -330,40 +620,75 @@ impl Model {
 
                     let mut res = res.outputs.last().unwrap().clone();
                     res.flatten();
-                    results.insert(idx, res);
+                    results.insert(*idx, res);
                 }
             }
         }
 
-        let output_nodes = self.graph.outputs.iter();
-        debug!(
-            "model outputs are nodes: {:?}",
-            output_nodes.clone().collect_vec()
-        );
-        let outputs = output_nodes
-            .map(|o| results.get(&o).unwrap().clone().map(|x| x))
-            .collect_vec();
-
-        let res = ForwardResult {
-            outputs,
-            max_lookup_inputs,
-        };
-
-        Ok(res)
+        Ok((results, max_lookup_inputs))
     }
 
-    /// Loads an Onnx model from a specified path.
+    /// Quantizes an Onnx model loaded with [Model::load_typed_onnx].
     /// # Arguments
-    /// * `reader` - A reader for an Onnx file.
+    /// * `model` - The decluttered tract graph.
     /// * `scale` - The scale to use for quantization.
     /// * `public_params` - Whether to make the params public.
+    /// * `scale_overrides` - Per-node scales, keyed by node index.
+    ///
+    /// Returns the parsed nodes along with the param visibilities of the nodes matched by [VarVisibility::param_overrides].
     fn load_onnx_model(
-        reader: &mut dyn std::io::Read,
+        model: &Graph<TypedFact, Box<dyn TypedOp>>,
         run_args: &RunArgs,
         visibility: &VarVisibility,
-    ) -> Result<ParsedNodes, Box<dyn Error>> {
+        scale_overrides: &BTreeMap<usize, u32>,
+    ) -> Result<(ParsedNodes, BTreeMap<usize, Visibility>), Box<dyn Error>> {
         let start_time = instant::Instant::now();
 
+        let param_visibilities = (0..model.nodes.len())
+            .filter_map(|i| {
+                visibility
+                    .param_override(i, &Self::initializer_names(model, i))
+                    .map(|v| (i, v))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let nodes = Self::nodes_from_graph(
+            model,
+            run_args,
+            visibility,
+            model.inputs.iter().map(|_| run_args.scale).collect(),
+            scale_overrides,
+        )?;
+
+        debug!("\n {}", model);
+
+        let mut parsed_nodes = ParsedNodes {
+            nodes,
+            inputs: model.inputs.iter().map(|o| o.node).collect(),
+            outputs: model.outputs.iter().map(|o| o.node).collect(),
+        };
+
+        parsed_nodes.optimize(run_args.param_visibility, &param_visibilities)?;
+
+        if run_args.prove_accuracy {
+            parsed_nodes.append_accuracy_head()?;
+        }
+
+        let duration = start_time.elapsed();
+        trace!("model loading took: {:?}", duration);
+
+        Ok((parsed_nodes, param_visibilities))
+    }
+
+    /// Loads an Onnx model into a decluttered tract graph, with concrete input shapes and dimensions.
+    /// The ids of the graph's nodes are the indices of the corresponding [Model] nodes.
+    /// # Arguments
+    /// * `reader` - A reader for an Onnx file.
+    /// * `run_args` - [RunArgs] holding the batch size.
+    pub fn load_typed_onnx(
+        reader: &mut dyn std::io::Read,
+        run_args: &RunArgs,
+    ) -> Result<Graph<TypedFact, Box<dyn TypedOp>>, Box<dyn Error>> {
         let mut model = tract_onnx::onnx().model_for_read(reader).map_err(|e| {
             error!("Error loading model: {}", e);
             GraphError::ModelLoad
@@ -410,25 +735,111 @@ impl Model {
 
         info!("set batch size to {}", run_args.batch_size);
 
-        let nodes = Self::nodes_from_graph(
-            &model,
-            run_args,
-            visibility,
-            model.inputs.iter().map(|_| run_args.scale).collect(),
-        )?;
+        Ok(model)
+    }
 
-        debug!("\n {}", model);
+    /// Runs the (float) Onnx model with tract, returning the output of every node keyed by node index,
+    /// alongside the name of the node in the Onnx graph.
+    /// Outputs that cannot be cast to `f32` are skipped.
+    /// # Arguments
+    /// * `reader` - A reader for an Onnx file.
+    /// * `run_args` - [RunArgs] holding the batch size.
+    /// * `inputs` - The (flattened) float inputs to the model.
+    pub fn float_activations(
+        reader: &mut dyn std::io::Read,
+        run_args: &RunArgs,
+        inputs: &[Vec<f32>],
+    ) -> Result<BTreeMap<usize, (String, Vec<f32>)>, Box<dyn Error>> {
+        let mut model = Self::load_typed_onnx(reader, run_args)?;
+        let tensors = Self::tract_inputs(&model, inputs)?;
+
+        // expose every node's output so the plan keeps all intermediate activations
+        let outlets = model
+            .nodes()
+            .iter()
+            .filter(|n| !n.outputs.is_empty())
+            .map(|n| OutletId::new(n.id, 0))
+            .collect_vec();
+        model.set_output_outlets(&outlets)?;
+        let names = model
+            .nodes()
+            .iter()
+            .map(|n| (n.id, n.name.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        let plan = SimplePlan::new(model)?;
+        let outputs = plan.run(tensors)?;
+
+        let mut activations = BTreeMap::new();
+        for (outlet, output) in outlets.iter().zip(outputs.iter()) {
+            if let Ok(values) = output.cast_to::<f32>() {
+                activations.insert(
+                    outlet.node,
+                    (
+                        names[&outlet.node].clone(),
+                        values.as_slice::<f32>()?.to_vec(),
+                    ),
+                );
+            }
+        }
+        Ok(activations)
+    }
 
-        let parsed_nodes = ParsedNodes {
-            nodes,
-            inputs: model.inputs.iter().map(|o| o.node).collect(),
-            outputs: model.outputs.iter().map(|o| o.node).collect(),
-        };
+    /// Runs the (float) Onnx model with tract, returning its (flattened) outputs.
+    /// # Arguments
+    /// * `reader` - A reader for an Onnx file.
+    /// * `run_args` - [RunArgs] holding the batch size.
+    /// * `inputs` - The (flattened) float inputs to the model.
+    pub fn float_forward(
+        reader: &mut dyn std::io::Read,
+        run_args: &RunArgs,
+        inputs: &[Vec<f32>],
+    ) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let model = Self::load_typed_onnx(reader, run_args)?;
+        let tensors = Self::tract_inputs(&model, inputs)?;
 
-        let duration = start_time.elapsed();
-        trace!("model loading took: {:?}", duration);
+        let plan = SimplePlan::new(model)?;
+        let outputs = plan.run(tensors)?;
+
+        let mut res = vec![];
+        for output in outputs.iter() {
+            res.push(output.cast_to::<f32>()?.as_slice::<f32>()?.to_vec());
+        }
+        Ok(res)
+    }
 
-        Ok(parsed_nodes)
+    /// Shapes (flattened) float inputs into tract tensors matching the inputs of `model`.
+    fn tract_inputs(
+        model: &Graph<TypedFact, Box<dyn TypedOp>>,
+        inputs: &[Vec<f32>],
+    ) -> Result<TVec<TValue>, Box<dyn Error>> {
+        let mut tensors: TVec<TValue> = tvec![];
+        for (outlet, data) in model.inputs.iter().zip(inputs) {
+            let shape = model
+                .outlet_fact(*outlet)?
+                .shape
+                .as_concrete()
+                .ok_or(GraphError::InvalidDims(outlet.node, "input".to_string()))?
+                .to_vec();
+            tensors.push(TractTensor::from_shape(&shape, data.as_slice())?.into());
+        }
+        Ok(tensors)
+    }
+
+    /// Returns the name of every node in the Onnx graph, keyed by node index.
+    /// # Arguments
+    /// * `reader` - A reader for an Onnx file.
+    /// * `run_args` - [RunArgs] holding the batch size.
+    pub fn node_names(
+        reader: &mut dyn std::io::Read,
+        run_args: &RunArgs,
+    ) -> Result<BTreeMap<usize, String>, Box<dyn Error>> {
+        let model = Self::load_typed_onnx(reader, run_args)?;
+        Ok(model
+            .nodes()
+            .iter()
+            .map(|n| (n.id, n.name.clone()))
+            .collect())
     }
 
     /// Formats nodes (including subgraphs) into tables !
@@ -472,11 +883,14 @@ impl Model {
     /// * `run_args` - [RunArgs]
     /// * `mode` - The [Mode] we're using the model in.
     /// * `visibility` - Which inputs to the model are public and private (params, inputs, outputs) using [VarVisibility].
+    /// * `input_scales` - The scales of the graph's inputs.
+    /// * `scale_overrides` - Per-node scales, keyed by node index. Nodes not in the map use the global scale.
     pub fn nodes_from_graph(
         graph: &Graph<TypedFact, Box<dyn TypedOp>>,
         run_args: &RunArgs,
         visibility: &VarVisibility,
         input_scales: Vec<u32>,
+        scale_overrides: &BTreeMap<usize, u32>,
     ) -> Result<BTreeMap<usize, NodeType>, Box<dyn Error>> {
         let mut nodes = BTreeMap::<usize, NodeType>::new();
         let mut input_idx = 0;
@@ -490,8 +904,18 @@ impl Model {
                         .iter()
                         .map(|i| nodes.get(&i.node).unwrap().out_scales()[0])
                         .collect_vec();
-                    let subgraph_nodes =
-                        Self::nodes_from_graph(&model, run_args, visibility, input_scales)?;
+                    // subgraph nodes have their own index space so overrides don't apply to them
+                    let visibility = VarVisibility {
+                        param_overrides: BTreeMap::new(),
+                        ..visibility.clone()
+                    };
+                    let subgraph_nodes = Self::nodes_from_graph(
+                        &model,
+                        run_args,
+                        &visibility,
+                        input_scales,
+                        &BTreeMap::new(),
+                    )?;
 
                     let subgraph = ParsedNodes {
                         nodes: subgraph_nodes,
@@ -501,7 +925,11 @@ impl Model {
 
                     let om = Model {
                         graph: subgraph,
-                        visibility: visibility.clone(),
+                        visibility,
+                        scale_overrides: BTreeMap::new(),
+                        output_tolerances: BTreeMap::new(),
+                        param_visibilities: BTreeMap::new(),
+                        elgamal_recipient: ElGamalRecipient::default(),
                     };
                     nodes.insert(
                         i,
@@ -514,13 +942,21 @@ impl Model {
                     );
                 }
                 None => {
-                    let mut n = Node::new(
-                        n.clone(),
-                        &mut nodes,
-                        run_args.scale,
-                        run_args.param_visibility,
-                        i,
-                    )?;
+                    let mut scale = scale_overrides.get(&i).cloned().unwrap_or(run_args.scale);
+                    if !scale_overrides.is_empty() {
+                        // lookups can only scale values down, so once some nodes have been lowered a node's
+                        // scale can't exceed that of its inputs
+                        let max_input_scale = n
+                            .inputs
+                            .iter()
+                            .filter_map(|i| nodes.get(&i.node))
+                            .map(|n| n.out_scales()[0])
+                            .max();
+                        scale = max_input_scale.map_or(scale, |s| scale.min(s));
+                    }
+                    let param_visibility =
+                        visibility.params_at(i, &Self::initializer_names(graph, i));
+                    let mut n = Node::new(n.clone(), &mut nodes, scale, param_visibility, i)?;
                     if n.opkind.is_input() {
                         n.opkind = Box::new(Input {
                             scale: input_scales[input_idx],
@@ -536,6 +972,18 @@ impl Model {
         Ok(nodes)
     }
 
+    /// The Onnx names of the initializers the `i`th node of `graph` reads: its own name if it is a constant,
+    /// and otherwise the names of the constants feeding it. Name-based param visibilities match on these,
+    /// as an initializer keeps its name on the node producing it rather than on the nodes consuming it.
+    pub fn initializer_names(graph: &Graph<TypedFact, Box<dyn TypedOp>>, i: usize) -> Vec<&str> {
+        let node = &graph.nodes[i];
+        std::iter::once(node)
+            .chain(node.inputs.iter().map(|o| &graph.nodes[o.node]))
+            .filter(|n| n.op().name() == "Const")
+            .map(|n| n.name.as_str())
+            .collect()
+    }
+
     /// Creates a `Model` from parsed run_args
     /// # Arguments
     /// * `params` - A [GraphSettings] struct holding parsed CLI arguments.
@@ -546,6 +994,51 @@ impl Model {
         Model::new(&mut std::fs::File::open(model)?, *run_args)
     }
 
+    /// Creates a `Model` from a [GraphSettings] struct, applying any per-node scale overrides and per-output tolerances.
+    /// # Arguments
+    /// * `settings` - A [GraphSettings] struct.
+    /// * `model` - The path to the Onnx file.
+    pub fn from_settings(
+        settings: &GraphSettings,
+        model: &std::path::PathBuf,
+    ) -> Result<Self, Box<dyn Error>> {
+        Model::new_from_settings(&mut std::fs::File::open(model)?, settings)
+    }
+
+    /// Creates a `Model` from a reader for an Onnx file and a [GraphSettings] struct, applying any
+    /// per-node scale overrides, per-output tolerances, per-tensor visibilities and the ElGamal recipient.
+    /// # Arguments
+    /// * `reader` - A reader for an Onnx file.
+    /// * `settings` - A [GraphSettings] struct.
+    pub fn new_from_settings(
+        reader: &mut dyn std::io::Read,
+        settings: &GraphSettings,
+    ) -> Result<Self, Box<dyn Error>> {
+        let typed = Self::load_typed_onnx(reader, &settings.run_args)?;
+        Self::from_typed_graph(&typed, settings)
+    }
+
+    /// Creates a `Model` from a graph loaded with [Model::load_typed_onnx] and a [GraphSettings] struct,
+    /// so that models quantized with different settings can share a single parse of the Onnx file.
+    /// The graph must have been loaded with the batch size of `settings`.
+    /// # Arguments
+    /// * `typed` - The decluttered tract graph.
+    /// * `settings` - A [GraphSettings] struct.
+    pub fn from_typed_graph(
+        typed: &Graph<TypedFact, Box<dyn TypedOp>>,
+        settings: &GraphSettings,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut model = Model::from_typed_with_visibility(
+            typed,
+            settings.run_args,
+            VarVisibility::from_settings(settings)?,
+            settings.scale_overrides.clone(),
+        )?;
+        model.output_tolerances = settings.output_tolerances.clone();
+        model.elgamal_recipient = settings.elgamal_recipient;
+        Ok(model)
+    }
+
     /// Configures a model for the circuit
     /// # Arguments
     /// * `meta` - The constraint system.
@@ -581,28 +1074,47 @@ impl Model {
     /// # Arguments
     /// * `config` - [ModelConfig] holding all node configs.
     /// * `layouter` - Halo2 Layouter.
+    /// * `node_offsets` - The rows the nodes are laid out in (see [NodeOffsets]).
     /// * `inputs` - The values to feed into the circuit.
     /// * `vars` - The variables for the circuit.
+    /// * `expected` - The output of each node in a forward pass over the inputs, which the witness is checked against if given.
     pub fn layout(
         &self,
         mut config: ModelConfig,
         layouter: &mut impl Layouter<Fp>,
         run_args: &RunArgs,
+        node_offsets: &NodeOffsets,
         inputs: &[ValTensor<Fp>],
         vars: &ModelVars<Fp>,
+        expected: Option<&BTreeMap<usize, Tensor<i128>>>,
     ) -> Result<Vec<ValTensor<Fp>>, Box<dyn Error>> {
         info!("model layout...");
 
         let start_time = instant::Instant::now();
 
+        // the offsets only line up with the rows the nodes will be assigned to if values wrap around
+        // columns of the same size, otherwise we fall back on laying out the nodes sequentially
+        let col_size = config.base.output.col_size();
+        let parallel = if node_offsets.ranges.is_empty() {
+            debug!("no node offsets, laying out the nodes sequentially");
+            false
+        } else if node_offsets.col_size != col_size {
+            warn!(
+                "the node offsets were computed for columns of {} rows but the columns have {}, laying out the nodes sequentially",
+                node_offsets.col_size, col_size
+            );
+            false
+        } else {
+            true
+        };
+
         let mut results = BTreeMap::<usize, ValTensor<Fp>>::new();
 
         for (i, input_idx) in self.graph.inputs.iter().enumerate() {
-            if self.visibility.input.is_public() {
-                results.insert(*input_idx, vars.instances[i].clone());
-            } else {
-                results.insert(*input_idx, inputs[i].clone());
-            }
+            match self.input_instance_idx(i) {
+                Some(instance) => results.insert(*input_idx, vars.instances[instance].clone()),
+                None => results.insert(*input_idx, inputs[i].clone()),
+            };
         }
 
         config.base.layout_tables(layouter)?;
@@ -611,37 +1123,45 @@ impl Model {
             || "model",
             |region| {
                 let mut thread_safe_region = RegionCtx::new(region, 0);
-
-                let outputs = self
-                    .layout_nodes(&mut config, &mut thread_safe_region, &mut results)
-                    .map_err(|e| {
+                let mut results = results.clone();
+
+                let outputs = if parallel {
+                    self.layout_nodes_parallel(
+                        &config,
+                        &mut thread_safe_region,
+                        &mut results,
+                        node_offsets,
+                    )
+                } else {
+                    self.layout_nodes(&mut config, &mut thread_safe_region, &mut results)
+                }
+                .map_err(|e| {
+                    error!("{}", e);
+                    halo2_proofs::plonk::Error::Synthesis
+                })?;
+
+                // we check the witness of every node against the forward pass (if there is one),
+                // so that a failing proof can be traced back to the first node that diverges
+                if let Some(expected) = expected {
+                    self.check_divergence(expected, &results).map_err(|e| {
                         error!("{}", e);
                         halo2_proofs::plonk::Error::Synthesis
                     })?;
-
-                if run_args.output_visibility == Visibility::Public {
-                    let output_scales = self.graph.get_output_scales();
-                    let global_scale = scale_to_multiplier(run_args.scale) as usize;
-                    let _ = outputs
-                        .iter()
-                        .enumerate()
-                        .map(|(i, output)| {
-                            let mut tolerance = run_args.tolerance.clone();
-                            tolerance.scales =
-                                (scale_to_multiplier(output_scales[i]) as usize, global_scale);
-
-                            let mut instance_offset = 0;
-                            if self.visibility.input.is_public() {
-                                instance_offset += inputs.len();
-                            };
-                            config.base.layout(
-                                &mut thread_safe_region,
-                                &[output.clone(), vars.instances[instance_offset + i].clone()],
-                                Box::new(HybridOp::RangeCheck(tolerance)),
-                            )
-                        })
-                        .collect_vec();
                 }
+
+                let _ = outputs
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, output)| {
+                        let instance = self.output_instance_idx(i)?;
+                        let tolerance = self.output_tolerance(i, run_args);
+                        Some(config.base.layout(
+                            &mut thread_safe_region,
+                            &[output.clone(), vars.instances[instance].clone()],
+                            Box::new(HybridOp::RangeCheck(tolerance)),
+                        ))
+                    })
+                    .collect_vec();
                 info!("computing...");
                 Ok(outputs)
             },
@@ -653,11 +1173,121 @@ impl Model {
         Ok(outputs)
     }
 
+    /// Lays out the nodes set by set (see [ParsedNodes::independent_sets]). Nodes within a set are
+    /// laid out in parallel, each in its own slice of the region starting at the offset recorded
+    /// by the dummy layout pass.
+    fn layout_nodes_parallel(
+        &self,
+        config: &ModelConfig,
+        region: &mut RegionCtx<Fp>,
+        results: &mut BTreeMap<usize, ValTensor<Fp>>,
+        node_offsets: &NodeOffsets,
+    ) -> Result<Vec<ValTensor<Fp>>, Box<dyn Error>> {
+        for set in self.graph.independent_sets() {
+            // subgraphs share the results map with their parent so are laid out sequentially
+            let mut subgraphs = vec![];
+            let mut nodes = vec![];
+            for idx in set {
+                let range = node_offsets
+                    .ranges
+                    .get(&idx)
+                    .cloned()
+                    .ok_or(GraphError::NodeOffsets(idx))?;
+                match self.graph.nodes.get(&idx) {
+                    Some(NodeType::SubGraph { model, .. }) => subgraphs.push((idx, model, range)),
+                    Some(NodeType::Node(n)) => nodes.push((idx, n, range)),
+                    None => return Err(Box::new(GraphError::MissingNode(idx))),
+                }
+            }
+
+            for (idx, model, range) in subgraphs {
+                let mut local_region = RegionCtx::from_wrapped_region(region.region(), range.start);
+                let mut local_config = config.clone();
+                let res = model.layout_nodes(&mut local_config, &mut local_region, results)?;
+                if local_region.offset() != range.end {
+                    return Err(Box::new(GraphError::NodeOffsets(idx)));
+                }
+                let mut res = res.last().unwrap().clone();
+                res.flatten();
+                results.insert(idx, res);
+            }
+
+            let wrapped_region = region.region();
+            let laid_out = nodes
+                .par_iter()
+                .map(|(idx, node, range)| {
+                    let values: Vec<ValTensor<Fp>> = node
+                        .inputs
+                        .iter()
+                        .map(|i| results.get(i).unwrap().clone())
+                        .collect_vec();
+
+                    debug!(
+                        "laying out {}: {}, offset:{}",
+                        idx,
+                        node.opkind.as_string(),
+                        range.start
+                    );
+                    trace!("dims: {:?}", node.out_dims);
+
+                    let mut local_region =
+                        RegionCtx::from_wrapped_region(wrapped_region.clone(), range.start);
+                    let mut local_config = config.base.clone();
+                    let res = local_config
+                        .layout(&mut local_region, &values, node.opkind.clone_dyn())
+                        .map_err(|e| e.to_string())?;
+                    // a node spilling over its slice would overwrite the rows of the next one
+                    if local_region.offset() != range.end {
+                        return Err(GraphError::NodeOffsets(*idx).to_string());
+                    }
+                    Ok((*idx, res))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+
+            for (idx, res) in laid_out {
+                if let Some(vt) = res {
+                    results.insert(idx, vt);
+                    //only use with mock prover
+                    trace!(
+                        "------------ output node {:?}: {:?}",
+                        idx,
+                        results.get(&idx).unwrap().show()
+                    );
+                }
+            }
+        }
+
+        region.increment(node_offsets.end - region.offset());
+
+        let output_nodes = self.graph.outputs.iter();
+        debug!(
+            "model outputs are nodes: {:?}",
+            output_nodes.clone().collect_vec()
+        );
+        let outputs = output_nodes
+            .map(|o| results.get(o).unwrap().clone())
+            .collect_vec();
+
+        Ok(outputs)
+    }
+
     fn layout_nodes(
         &self,
         config: &mut ModelConfig,
         region: &mut RegionCtx<Fp>,
         results: &mut BTreeMap<usize, ValTensor<Fp>>,
+    ) -> Result<Vec<ValTensor<Fp>>, Box<dyn Error>> {
+        self.layout_nodes_recorded(config, region, results, &mut ModelLayout::default())
+    }
+
+    /// Lays out the nodes sequentially, recording the rows each node is laid out in
+    /// and the resources each node consumes.
+    fn layout_nodes_recorded(
+        &self,
+        config: &mut ModelConfig,
+        region: &mut RegionCtx<Fp>,
+        results: &mut BTreeMap<usize, ValTensor<Fp>>,
+        model_layout: &mut ModelLayout,
     ) -> Result<Vec<ValTensor<Fp>>, Box<dyn Error>> {
         for (idx, node) in self.graph.nodes.iter() {
             let values: Vec<ValTensor<Fp>> = node
@@ -673,6 +1303,7 @@ impl Model {
                 region.offset()
             );
             trace!("dims: {:?}", node.out_dims());
+            let cost_before = NodeCost::snapshot(region);
             match node {
                 NodeType::Node(n) => {
                     let res = config
@@ -701,6 +1332,14 @@ impl Model {
                     results.insert(*idx, res);
                 }
             }
+            model_layout
+                .node_offsets
+                .ranges
+                .insert(*idx, cost_before.rows..region.offset());
+            let mut cost = NodeCost::snapshot(region).since(&cost_before);
+            cost.idx = *idx;
+            cost.op = node.as_str();
+            model_layout.node_costs.push(cost);
         }
         let output_nodes = self.graph.outputs.iter();
         debug!(
@@ -721,7 +1360,25 @@ impl Model {
         &self,
         run_args: &RunArgs,
         input_shapes: &[Vec<usize>],
-    ) -> Result<usize, Box<dyn Error>> {
+    ) -> Result<ModelLayout, Box<dyn Error>> {
+        let model_config = ModelConfig {
+            base: PolyConfig::dummy(run_args.logrows as usize),
+            vars: ModelVars::new_dummy(),
+        };
+        self.dummy_layout_with_config(&model_config, run_args, input_shapes)
+    }
+
+    /// Assigns dummy values using the columns of an existing [ModelConfig], recording the row
+    /// offset at which each node starts.
+    /// # Arguments
+    /// * `config` - The [ModelConfig] whose columns determine where values wrap.
+    /// * `input_shapes` - The shapes of the inputs to the model.
+    pub fn dummy_layout_with_config(
+        &self,
+        config: &ModelConfig,
+        run_args: &RunArgs,
+        input_shapes: &[Vec<usize>],
+    ) -> Result<ModelLayout, Box<dyn Error>> {
         info!("calculating num of constraints using dummy model layout...");
 
         let start_time = instant::Instant::now();
@@ -740,81 +1397,122 @@ impl Model {
             results.insert(*input_idx, inputs[i].clone());
         }
 
-        let mut dummy_config = PolyConfig::dummy(run_args.logrows as usize);
-        let mut model_config = ModelConfig {
-            base: dummy_config.clone(),
-            vars: ModelVars::new_dummy(),
-        };
+        let mut model_config = config.clone();
 
         let mut region = RegionCtx::new_dummy(0);
 
-        let outputs = self.layout_nodes(&mut model_config, &mut region, &mut results)?;
-
-        if run_args.output_visibility == Visibility::Public {
-            let _ = outputs
-                .into_iter()
-                .map(|output| {
-                    dummy_config
-                        .layout(
-                            &mut region,
-                            &[output.clone(), output],
-                            Box::new(HybridOp::RangeCheck(run_args.tolerance)),
-                        )
-                        .unwrap()
-                })
-                .collect_vec();
+        let mut model_layout = ModelLayout::default();
+        model_layout.node_offsets.col_size = model_config.base.output.col_size();
+        let outputs = self.layout_nodes_recorded(
+            &mut model_config,
+            &mut region,
+            &mut results,
+            &mut model_layout,
+        )?;
+
+        model_layout.node_offsets.end = region.offset();
+
+        // the nodes consuming public inputs copy them from their instance cells, which we attribute to the input nodes
+        for (i, (input_idx, shape)) in self.graph.inputs.iter().zip(input_shapes).enumerate() {
+            if self.visibility.input_at(i).is_public() {
+                if let Some(cost) = model_layout
+                    .node_costs
+                    .iter_mut()
+                    .find(|c| c.idx == *input_idx)
+                {
+                    cost.instances += shape.iter().product::<usize>();
+                }
+            }
+        }
+
+        for (i, (output_idx, output)) in self.graph.outputs.iter().zip(outputs).enumerate() {
+            if self.visibility.output_at(i).is_public() {
+                let cost_before = NodeCost::snapshot(&region);
+                let len = output.len();
+                model_config.base.layout(
+                    &mut region,
+                    &[output.clone(), output],
+                    Box::new(HybridOp::RangeCheck(self.output_tolerance(i, run_args))),
+                )?;
+                // attribute the range check against the public outputs to the output nodes
+                model_layout
+                    .node_offsets
+                    .output_checks
+                    .push((*output_idx, cost_before.rows..region.offset()));
+                let range_check_cost = NodeCost::snapshot(&region).since(&cost_before);
+                if let Some(cost) = model_layout
+                    .node_costs
+                    .iter_mut()
+                    .find(|c| c.idx == *output_idx)
+                {
+                    cost.add(&range_check_cost);
+                    cost.instances += len;
+                }
+            }
         }
 
         let duration = start_time.elapsed();
         trace!("dummy model layout took: {:?}", duration);
 
-        Ok(region.offset())
+        model_layout.num_rows = region.offset();
+
+        Ok(model_layout)
+    }
+
+    /// The visibility of the params of a node (falling back on the global param visibility).
+    pub fn param_visibility(&self, idx: usize) -> Visibility {
+        self.param_visibilities
+            .get(&idx)
+            .cloned()
+            .unwrap_or(self.visibility.params)
     }
 
-    /// Retrieves all constants from the model.
-    pub fn get_all_consts(&self) -> Vec<ValTensor<Fp>> {
+    /// Retrieves all constants from the model whose params have the given visibility.
+    pub fn get_all_consts(&self, visibility: Visibility) -> Vec<ValTensor<Fp>> {
         let mut consts = vec![];
-        for node in self.graph.nodes.values() {
+        for (idx, node) in self.graph.nodes.iter() {
             match node {
                 NodeType::Node(n) => {
+                    if self.param_visibility(*idx) != visibility {
+                        continue;
+                    }
                     let boxed_op = n.opkind.clone_dyn();
                     if let Some(constant) = extract_const_quantized_values(boxed_op) {
                         consts.push(constant);
                     };
                 }
                 NodeType::SubGraph { model, .. } => {
-                    consts.extend(model.get_all_consts());
+                    consts.extend(model.get_all_consts(visibility));
                 }
             }
         }
         consts
     }
 
-    /// Shapes of the computational graph's constants
-    pub fn const_shapes(&self) -> Vec<Vec<usize>> {
-        let mut const_shapes = vec![];
-        for node in self.graph.nodes.values() {
-            match node {
-                NodeType::Node(n) => {
-                    let boxed_op = n.opkind.clone_dyn();
-                    if let Some(constant) = extract_const_quantized_values(boxed_op) {
-                        const_shapes.push(constant.dims().to_vec());
-                    };
-                }
-                NodeType::SubGraph { model, .. } => {
-                    const_shapes.extend(model.const_shapes());
-                }
-            }
-        }
-        const_shapes
+    /// Shapes of the computational graph's constants whose params have the given visibility
+    pub fn const_shapes(&self, visibility: Visibility) -> Vec<Vec<usize>> {
+        self.get_all_consts(visibility)
+            .iter()
+            .map(|constant| constant.dims().to_vec())
+            .collect()
     }
 
-    /// Replaces all constants in the model with the provided values (in order of indexing)
-    pub fn replace_consts(&mut self, consts: Vec<ValTensor<Fp>>) {
+    /// Replaces the constants whose params have the given visibility with the provided values (in order of indexing)
+    pub fn replace_consts(&mut self, consts: Vec<ValTensor<Fp>>, visibility: Visibility) {
         let mut const_idx = 0;
-        for node in self.graph.nodes.values_mut() {
+        let param_visibilities = self.param_visibilities.clone();
+        let default_visibility = self.visibility.params;
+        for (idx, node) in self.graph.nodes.iter_mut() {
             match node {
                 NodeType::Node(n) => {
+                    if param_visibilities
+                        .get(idx)
+                        .cloned()
+                        .unwrap_or(default_visibility)
+                        != visibility
+                    {
+                        continue;
+                    }
                     let boxed_op = n.opkind.clone_dyn();
                     if let Some(constant) = boxed_op
                         .as_any()
@@ -828,21 +1526,502 @@ impl Model {
                     };
                 }
                 NodeType::SubGraph { model, .. } => {
-                    model.replace_consts(consts.clone());
+                    model.replace_consts(consts.clone(), visibility);
                 }
             }
         }
     }
 
+    /// Shapes of the computational graph's inputs and outputs (in order of appearance) committed to with [Visibility::KZGCommit]
+    pub fn kzg_commit_shapes(&self) -> Vec<Vec<usize>> {
+        let inputs = self
+            .graph
+            .input_shapes()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.visibility.input_at(*i).is_kzgcommit());
+        let outputs = self
+            .graph
+            .output_shapes()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.visibility.output_at(*i).is_kzgcommit());
+        inputs.chain(outputs).map(|(_, shape)| shape).collect()
+    }
+
+    /// The number of elements encrypted by each ElGamal module layout: the encrypted inputs, params and outputs
+    /// (in that order, skipping any that are empty). Each layout appends its own c1 to the instances, and the
+    /// ciphertexts of all layouts are concatenated.
+    pub fn encrypted_lens(&self) -> Vec<usize> {
+        let num_elements = |shapes: Vec<Vec<usize>>| {
+            shapes
+                .iter()
+                .map(|shape| shape.iter().product::<usize>())
+                .sum::<usize>()
+        };
+        let inputs = self
+            .graph
+            .input_shapes()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.visibility.input_at(*i).is_encrypted())
+            .map(|(_, shape)| shape)
+            .collect();
+        let outputs = self
+            .graph
+            .output_shapes()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.visibility.output_at(*i).is_encrypted())
+            .map(|(_, shape)| shape)
+            .collect();
+        vec![
+            num_elements(inputs),
+            num_elements(self.const_shapes(Visibility::Encrypted)),
+            num_elements(outputs),
+        ]
+        .into_iter()
+        .filter(|len| *len > 0)
+        .collect()
+    }
+
+    /// The number of elements of each tensor encrypted with the Poseidon cipher: each symencrypted input, the
+    /// flattened symencrypted params and each symencrypted output (in that order, skipping the params if there are
+    /// none). Each tensor appends its key hash, nonce and ciphertexts to the cipher instance column.
+    pub fn symencrypted_lens(&self) -> Vec<usize> {
+        let num_elements = |shape: &Vec<usize>| shape.iter().product::<usize>();
+        let inputs = self
+            .graph
+            .input_shapes()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.visibility.input_at(*i).is_symencrypted())
+            .map(|(_, shape)| num_elements(&shape));
+        let params = self
+            .const_shapes(Visibility::SymEncrypted)
+            .iter()
+            .map(num_elements)
+            .sum::<usize>();
+        let outputs = self
+            .graph
+            .output_shapes()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.visibility.output_at(*i).is_symencrypted())
+            .map(|(_, shape)| num_elements(&shape));
+        inputs
+            .chain(Some(params).filter(|len| *len > 0))
+            .chain(outputs)
+            .collect()
+    }
+
     /// Shapes of the computational graph's public inputs (if any)
     pub fn instance_shapes(&self) -> Vec<Vec<usize>> {
         let mut instance_shapes = vec![];
-        if self.visibility.input.is_public() {
-            instance_shapes.extend(self.graph.input_shapes());
+        for (i, shape) in self.graph.input_shapes().into_iter().enumerate() {
+            if self.visibility.input_at(i).is_public() {
+                instance_shapes.push(shape);
+            }
         }
-        if self.visibility.output.is_public() {
-            instance_shapes.extend(self.graph.output_shapes());
+        for (i, shape) in self.graph.output_shapes().into_iter().enumerate() {
+            if self.visibility.output_at(i).is_public() {
+                instance_shapes.push(shape);
+            }
         }
         instance_shapes
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::ops::Constant;
+    use crate::circuit::poly::PolyOp;
+    use crate::graph::modules::GraphModules;
+    use crate::graph::registry::{ModuleContext, ModuleRegistry, ProverInputs};
+    use crate::graph::utilities::quantized_tensor_to_valtensor;
+    use crate::graph::GraphCircuit;
+    use halo2_proofs::plonk::Circuit;
+
+    fn node(opkind: Box<dyn Op<Fp>>, inputs: Vec<usize>, idx: usize) -> NodeType {
+        NodeType::Node(Node {
+            opkind,
+            out_scale: 0,
+            inputs,
+            out_dims: vec![4],
+            idx,
+        })
+    }
+
+    /// (x + x) + (x * x), where the sum and product of the input are independent of one another
+    fn diamond() -> Model {
+        let graph = ParsedNodes {
+            nodes: BTreeMap::from([
+                (0, node(Box::new(Input { scale: 0 }), vec![], 0)),
+                (1, node(Box::new(PolyOp::Add { a: None }), vec![0, 0], 1)),
+                (2, node(Box::new(PolyOp::Mult { a: None }), vec![0, 0], 2)),
+                (3, node(Box::new(PolyOp::Add { a: None }), vec![1, 2], 3)),
+            ]),
+            inputs: vec![0],
+            outputs: vec![3],
+        };
+        Model {
+            graph,
+            ..Default::default()
+        }
+    }
+
+    fn run_args() -> RunArgs {
+        RunArgs {
+            logrows: 10,
+            ..Default::default()
+        }
+    }
+
+    fn load_relu(scale_overrides: BTreeMap<usize, u32>) -> Model {
+        let run_args = RunArgs {
+            scale: 7,
+            batch_size: 1,
+            ..run_args()
+        };
+        Model::new_with_scale_overrides(
+            &mut std::fs::File::open("examples/onnx/1l_relu/network.onnx").unwrap(),
+            run_args,
+            scale_overrides,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn nodes_keep_the_global_scale_without_overrides() {
+        let model = load_relu(BTreeMap::new());
+        for node in model.graph.nodes.values() {
+            assert_eq!(node.out_scales(), vec![7]);
+        }
+        assert_eq!(model.graph.get_output_scales(), vec![7]);
+    }
+
+    #[test]
+    fn scale_overrides_apply_to_their_node() {
+        let output = load_relu(BTreeMap::new()).graph.outputs[0];
+        let model = load_relu(BTreeMap::from([(output, 3)]));
+        assert_eq!(model.graph.get_output_scales(), vec![3]);
+    }
+
+    #[test]
+    fn costs_count_the_lookups_of_every_op() {
+        let graph = ParsedNodes {
+            nodes: BTreeMap::from([
+                (0, node(Box::new(Input { scale: 0 }), vec![], 0)),
+                (1, node(Box::new(LookupOp::ReLU { scale: 1 }), vec![0], 1)),
+                (
+                    2,
+                    node(Box::new(HybridOp::Softmax { scales: (1, 1) }), vec![1], 2),
+                ),
+            ]),
+            inputs: vec![0],
+            outputs: vec![2],
+        };
+        let model = Model {
+            graph,
+            ..Default::default()
+        };
+        let layout = model
+            .dummy_layout(&run_args(), &model.graph.input_shapes())
+            .unwrap();
+
+        let lookups = |idx: usize| {
+            layout
+                .node_costs
+                .iter()
+                .find(|c| c.idx == idx)
+                .unwrap()
+                .lookups
+                .clone()
+        };
+        assert!(lookups(0).is_empty());
+        assert_eq!(
+            lookups(1),
+            BTreeMap::from([(format!("{:?}", LookupOp::ReLU { scale: 1 }), 4)])
+        );
+        // softmax looks up the exponent and the reciprocal of its sum
+        let softmax = lookups(2);
+        assert!(softmax.keys().any(|op| op.starts_with("Exp")));
+        assert!(softmax.keys().any(|op| op.starts_with("Recip")));
+    }
+
+    #[test]
+    fn costs_count_each_constant_once() {
+        let constant = quantized_tensor_to_valtensor(
+            Tensor::new(Some(&[1, 2, 3, 4]), &[4]).unwrap(),
+            0,
+            Visibility::Public,
+        )
+        .unwrap();
+        let graph = ParsedNodes {
+            nodes: BTreeMap::from([
+                (0, node(Box::new(Input { scale: 0 }), vec![], 0)),
+                (
+                    1,
+                    node(Box::new(PolyOp::Add { a: Some(constant) }), vec![0], 1),
+                ),
+                (
+                    2,
+                    node(Box::new(PolyOp::OneHotCheck { axis: 0 }), vec![0], 2),
+                ),
+            ]),
+            inputs: vec![0],
+            outputs: vec![1, 2],
+        };
+        let model = Model {
+            graph,
+            ..Default::default()
+        };
+        let layout = model
+            .dummy_layout(&run_args(), &model.graph.input_shapes())
+            .unwrap();
+
+        let constants = |idx: usize| {
+            layout
+                .node_costs
+                .iter()
+                .find(|c| c.idx == idx)
+                .unwrap()
+                .constants
+        };
+        assert_eq!(constants(0), 0);
+        // one per element of the public param
+        assert_eq!(constants(1), 4);
+        // the unit the sum is checked against is assigned once and then copied
+        assert_eq!(constants(2), 1);
+    }
+
+    #[test]
+    fn instances_follow_the_per_tensor_visibilities() {
+        let mut model = diamond();
+        model.graph.inputs = vec![0, 0];
+        model.graph.outputs = vec![3, 1];
+        model.visibility = VarVisibility::new(
+            run_args(),
+            vec![Visibility::Private, Visibility::Public],
+            vec![Visibility::Public, Visibility::Private],
+            BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(model.input_instance_idx(0), None);
+        assert_eq!(model.input_instance_idx(1), Some(0));
+        assert_eq!(model.output_instance_idx(0), Some(1));
+        assert_eq!(model.output_instance_idx(1), None);
+        assert_eq!(model.instance_shapes(), vec![vec![4], vec![4]]);
+    }
+
+    #[test]
+    fn symencrypted_lens_hold_one_entry_per_tensor() {
+        let mut model = diamond();
+        model.graph.inputs = vec![0, 0];
+        model.graph.outputs = vec![3, 1];
+        model.visibility = VarVisibility::new(
+            run_args(),
+            vec![Visibility::Private, Visibility::SymEncrypted],
+            vec![Visibility::SymEncrypted, Visibility::SymEncrypted],
+            BTreeMap::new(),
+        )
+        .unwrap();
+        // unlike the ElGamal layouts, each tensor has its own key hash and nonce
+        assert_eq!(model.symencrypted_lens(), vec![4, 4, 4]);
+        assert!(model.encrypted_lens().is_empty());
+    }
+
+    #[test]
+    fn param_names_match_the_initializers_a_node_reads() {
+        use tract_onnx::prelude::*;
+        let mut typed = TypedModel::default();
+        let input = typed.add_source("input", f32::fact([4])).unwrap();
+        let bias = typed.add_const("dense.bias", tensor1(&[1f32; 4])).unwrap();
+        let sum = typed
+            .wire_node(
+                "Add_0",
+                tract_onnx::tract_core::ops::math::add(),
+                &[input, bias],
+            )
+            .unwrap();
+        typed.set_output_outlets(&sum).unwrap();
+
+        assert!(Model::initializer_names(&typed, input.node).is_empty());
+        assert_eq!(
+            Model::initializer_names(&typed, bias.node),
+            vec!["dense.bias"]
+        );
+        assert_eq!(
+            Model::initializer_names(&typed, sum[0].node),
+            vec!["dense.bias"]
+        );
+
+        let visibility = VarVisibility {
+            param_overrides: BTreeMap::from([("dense.bias".to_string(), Visibility::Public)]),
+            ..Default::default()
+        };
+        let params_at = |i| visibility.params_at(i, &Model::initializer_names(&typed, i));
+        assert_eq!(params_at(sum[0].node), Visibility::Public);
+        assert_eq!(params_at(input.node), Visibility::Private);
+    }
+
+    #[test]
+    fn independent_nodes_get_disjoint_rows() {
+        let model = diamond();
+        assert_eq!(
+            model.graph.independent_sets(),
+            vec![vec![0], vec![1, 2], vec![3]]
+        );
+
+        let layout = model
+            .dummy_layout(&run_args(), &model.graph.input_shapes())
+            .unwrap();
+        let ranges = &layout.node_offsets.ranges;
+        assert_eq!(ranges.len(), 4);
+        for ((a, r), (b, s)) in ranges.iter().tuple_combinations() {
+            assert!(
+                r.end <= s.start || s.end <= r.start,
+                "nodes {} ({:?}) and {} ({:?}) overlap",
+                a,
+                r,
+                b,
+                s
+            );
+        }
+        assert_eq!(layout.node_offsets.end, ranges[&3].end);
+    }
+
+    #[test]
+    fn allocated_constraints_skip_the_dummy_layout() {
+        let run_args = RunArgs {
+            allocated_constraints: Some(100),
+            ..run_args()
+        };
+        let settings = diamond().gen_params(run_args, CheckMode::UNSAFE).unwrap();
+        assert_eq!(settings.num_constraints, 100);
+        assert_eq!(settings.node_offsets, NodeOffsets::default());
+
+        let settings = diamond().gen_params(run_args(), CheckMode::UNSAFE).unwrap();
+        assert_eq!(settings.node_offsets.ranges.len(), 4);
+    }
+
+    #[test]
+    fn node_offsets_match_the_columns_of_the_circuit() {
+        let circuit = GraphCircuit::new(diamond(), run_args(), CheckMode::UNSAFE).unwrap();
+        let mut cs = ConstraintSystem::default();
+        let config = GraphCircuit::configure_with_params(&mut cs, circuit.settings.clone());
+        let node_offsets = &circuit.settings.node_offsets;
+        assert_eq!(node_offsets.ranges.len(), 4);
+        assert_eq!(
+            node_offsets.col_size,
+            config.model_config.base.output.col_size()
+        );
+    }
+
+    #[test]
+    fn parallel_layout_matches_sequential_layout() {
+        let model = diamond();
+        let layout = model
+            .dummy_layout(&run_args(), &model.graph.input_shapes())
+            .unwrap();
+        let mut config = ModelConfig {
+            base: PolyConfig::dummy(run_args().logrows as usize),
+            vars: ModelVars::new_dummy(),
+        };
+
+        let input: Tensor<Value<Fp>> = Tensor::new(Some(&[1i128, 2, 3, 4]), &[4]).unwrap().into();
+        let inputs = BTreeMap::from([(0, ValTensor::from(input))]);
+
+        let mut region = RegionCtx::new_dummy(0);
+        let sequential = model
+            .layout_nodes(&mut config, &mut region, &mut inputs.clone())
+            .unwrap();
+        let sequential_rows = region.offset();
+
+        let mut region = RegionCtx::new_dummy(0);
+        let parallel = model
+            .layout_nodes_parallel(
+                &config,
+                &mut region,
+                &mut inputs.clone(),
+                &layout.node_offsets,
+            )
+            .unwrap();
+
+        assert_eq!(region.offset(), sequential_rows);
+        assert_eq!(
+            parallel[0].get_int_evals().unwrap(),
+            sequential[0].get_int_evals().unwrap()
+        );
+        assert_eq!(parallel[0].get_int_evals().unwrap(), vec![3, 8, 15, 24]);
+    }
+
+    #[test]
+    fn params_hash_matches_the_forward_pass_over_the_hashed_params_only() {
+        let constant = |values: [i128; 4], visibility: Visibility| {
+            let quantized = quantized_tensor_to_valtensor(
+                Tensor::new(Some(&values), &[4]).unwrap(),
+                0,
+                visibility,
+            )
+            .unwrap();
+            Box::new(Constant::new(quantized, Tensor::new(None, &[4]).unwrap()))
+        };
+        // x + a + b, where a is hashed and b is kept private by a per-node override
+        let graph = ParsedNodes {
+            nodes: BTreeMap::from([
+                (0, node(Box::new(Input { scale: 0 }), vec![], 0)),
+                (
+                    1,
+                    node(constant([1, 2, 3, 4], Visibility::Hashed), vec![], 1),
+                ),
+                (
+                    2,
+                    node(constant([5, 6, 7, 8], Visibility::Private), vec![], 2),
+                ),
+                (3, node(Box::new(PolyOp::Add { a: None }), vec![0, 1], 3)),
+                (4, node(Box::new(PolyOp::Add { a: None }), vec![3, 2], 4)),
+            ]),
+            inputs: vec![0],
+            outputs: vec![4],
+        };
+        let run_args = RunArgs {
+            param_visibility: Visibility::Hashed,
+            ..run_args()
+        };
+        let visibility = VarVisibility {
+            param_overrides: BTreeMap::from([("2".to_string(), Visibility::Private)]),
+            ..VarVisibility::from_args(run_args).unwrap()
+        };
+        let model = Model {
+            graph,
+            visibility,
+            param_visibilities: BTreeMap::from([(2, Visibility::Private)]),
+            ..Default::default()
+        };
+        let circuit = GraphCircuit::new(model, run_args, CheckMode::UNSAFE).unwrap();
+
+        let params_hash = circuit.params_hash().unwrap().unwrap();
+        let forward = circuit.forward().unwrap();
+        let processed_params = forward.processed_params.unwrap().poseidon_hash().unwrap();
+        assert_eq!(processed_params, Some(vec![params_hash]));
+
+        // only the hashed params are hashed
+        let hash = |values: &[i128]| {
+            GraphModules::forward(
+                &ModuleRegistry::default(),
+                &[Tensor::new(Some(values), &[values.len()]).unwrap()],
+                Visibility::Hashed,
+                &ModuleContext::from(&circuit.settings),
+                &ProverInputs::default(),
+            )
+            .unwrap()
+            .poseidon_hash()
+            .unwrap()
+            .unwrap()[0]
+        };
+        assert_eq!(params_hash, hash(&[1, 2, 3, 4]));
+        assert_ne!(params_hash, hash(&[1, 2, 3, 4, 5, 6, 7, 8]));
+    }
+}
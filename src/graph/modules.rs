@@ -1,128 +1,171 @@
-use crate::circuit::modules::elgamal::{ElGamalConfig, ElGamalGadget, ElGamalVariables};
+use crate::circuit::modules::elgamal::{
+    ElGamalConfig, ElGamalGadget, ElGamalRecipient, ElGamalVariables,
+};
+use crate::circuit::modules::poseidon::cipher::{
+    PoseidonCipherChip, PoseidonCipherConfig, PoseidonCipherVariables,
+};
+use crate::circuit::modules::poseidon::length_prefixed::PoseidonLengthPrefixedChip;
+use crate::circuit::modules::poseidon::membership::{
+    PoseidonMembershipChip, PoseidonMembershipConfig, PoseidonMembershipVariables,
+};
+use crate::circuit::modules::poseidon::merkle::PoseidonMerkleChip;
 use crate::circuit::modules::poseidon::spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH};
 use crate::circuit::modules::poseidon::{PoseidonChip, PoseidonConfig};
-use crate::circuit::modules::Module;
+use crate::circuit::modules::schnorr::{SchnorrConfig, SchnorrGadget, SchnorrVariables};
+use crate::circuit::modules::{DummyAssignment, Module, ModuleLayouter};
+use crate::commands::HashMode;
 use crate::fieldutils::i128_to_felt;
 use crate::tensor::{Tensor, ValTensor, ValType};
 use halo2_proofs::circuit::{Layouter, Value};
 use halo2_proofs::plonk::{ConstraintSystem, Error};
 use halo2curves::bn256::Fr as Fp;
 use itertools::Itertools;
+use log::error;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
 
+use super::registry::{
+    DynLayouter, ErasedLayouter, GraphModule, ModuleContext, ModuleRegistry, ProverInputs,
+};
 use super::GraphWitness;
-use super::{VarVisibility, Visibility};
+use super::{GraphError, VarVisibility, Visibility};
 
 const POSEIDON_LEN_GRAPH: usize = 10;
 
-// TODO: Need a dummy pass module to get the exact size of each module, this is a rough estimate
-const POSEIDON_CONSTRAINTS_ESTIMATE: usize = 44;
-const ELGAMAL_CONSTRAINTS_ESTIMATE: usize = 44;
-// 2^15
-const POSEIDOIN_FIXED_COST_ESTIMATE: usize = 128;
-// 2^15
-const ELGAMAL_FIXED_COST_ESTIMATE: usize = 32768;
+/// The name of the Poseidon module, processing [Visibility::Hashed] values
+pub const POSEIDON: &str = "poseidon";
+/// The name of the ElGamal module, processing [Visibility::Encrypted] values
+pub const ELGAMAL: &str = "elgamal";
+/// The name of the Poseidon cipher module, processing [Visibility::SymEncrypted] values
+pub const CIPHER: &str = "cipher";
+/// The name of the Schnorr module, processing [Visibility::Signed] values
+pub const SCHNORR: &str = "schnorr";
+/// The name of the Poseidon Merkle membership module, processing [Visibility::Member] values
+pub const MEMBERSHIP: &str = "membership";
+/// The names of the built-in modules
+pub const BUILTIN_MODULES: [&str; 5] = [POSEIDON, ELGAMAL, CIPHER, SCHNORR, MEMBERSHIP];
+
+/// The key the Poseidon hashes are stored under in witnesses, kept from before modules were keyed by name
+const POSEIDON_HASH_KEY: &str = "poseidon_hash";
+
+/// The visibilities that require processing by a module among `visibilities`, in the order their modules are laid out
+/// (by module name)
+pub fn module_visibilities(visibilities: &[Visibility]) -> Vec<Visibility> {
+    visibilities
+        .iter()
+        .filter(|v| v.module_name().is_some())
+        .copied()
+        .sorted_by(|a, b| a.module_name().cmp(&b.module_name()))
+        .dedup()
+        .collect()
+}
 
 /// Poseidon module type
 pub type ModulePoseidon =
     PoseidonChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, POSEIDON_LEN_GRAPH>;
-/// Poseidon module config
+/// Poseidon Merkle tree module type
+pub type ModulePoseidonMerkle = PoseidonMerkleChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE>;
+/// Length-prefixed Poseidon module type
+pub type ModulePoseidonLengthPrefixed =
+    PoseidonLengthPrefixedChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, POSEIDON_LEN_GRAPH>;
+/// Poseidon module config (shared by all the Poseidon hashing modules)
 pub type ModulePoseidonConfig = PoseidonConfig<POSEIDON_WIDTH, POSEIDON_RATE>;
+/// Poseidon cipher module type
+pub type ModulePoseidonCipher = PoseidonCipherChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE>;
+/// Poseidon cipher module config
+pub type ModulePoseidonCipherConfig = PoseidonCipherConfig<POSEIDON_WIDTH, POSEIDON_RATE>;
+/// Poseidon Merkle membership module type (hashing rows as the flat Poseidon module does)
+pub type ModulePoseidonMembership =
+    PoseidonMembershipChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, POSEIDON_LEN_GRAPH>;
+/// Poseidon Merkle membership module config
+pub type ModulePoseidonMembershipConfig = PoseidonMembershipConfig<POSEIDON_WIDTH, POSEIDON_RATE>;
+/// Schnorr signature module type (signing the same digest as the flat Poseidon module)
+pub type ModuleSchnorr = SchnorrGadget<POSEIDON_LEN_GRAPH>;
 
-///
-#[derive(Clone, Debug, Default)]
+/// The config of the modules a circuit is laid out with
+#[derive(Clone, Default)]
 pub struct ModuleConfigs {
-    /// Poseidon
-    poseidon: Option<ModulePoseidonConfig>,
-    /// ElGamal
-    elgamal: Option<ElGamalConfig>,
+    /// The configured modules and their configs, in the order of their instance columns (by name)
+    modules: Vec<(Arc<dyn GraphModule>, Arc<dyn Any + Send + Sync>)>,
+    /// The settings the modules are laid out with
+    ctx: ModuleContext,
 }
 
 impl ModuleConfigs {
-    /// Create new module configs from visibility of each variable
-    pub fn from_visibility(
+    /// Configures the modules that use instances according to `module_sizes`, in the order of their instance columns.
+    /// Errors if any of these modules isn't in `registry`.
+    pub fn configure(
         cs: &mut ConstraintSystem<Fp>,
-        visibility: VarVisibility,
-        module_size: ModuleSizes,
-    ) -> Self {
-        let mut config = Self::default();
-
-        if (visibility.input.is_hashed()
-            || visibility.output.is_hashed()
-            || visibility.params.is_hashed())
-            && module_size.poseidon.1[0] > 0
-        {
-            config.poseidon = Some(ModulePoseidon::configure(cs))
+        registry: &ModuleRegistry,
+        module_sizes: &ModuleSizes,
+        ctx: ModuleContext,
+    ) -> Result<Self, GraphError> {
+        let mut configs = Self {
+            ctx,
+            ..Self::default()
         };
+        for name in module_sizes.configured() {
+            let module = registry.get(name)?;
+            let config = module.configure(cs);
+            configs.modules.push((module.clone(), config));
+        }
+        Ok(configs)
+    }
 
-        if (visibility.input.is_encrypted()
-            || visibility.output.is_encrypted()
-            || visibility.params.is_encrypted())
-            && module_size.elgamal.1[2] > 0
-        {
-            config.elgamal = Some(ElGamalGadget::configure(cs))
-        };
-        config
+    /// The number of configured modules, each of which is laid out in its own module space
+    pub fn len(&self) -> usize {
+        self.modules.len()
     }
-}
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-/// Module variable settings
-pub struct ModuleVarSettings {
-    ///
-    elgamal: Option<ElGamalVariables>,
-}
+    /// Whether no modules are configured
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
 
-impl ModuleVarSettings {
-    /// Create new module variable settings
-    pub fn new(elgamal: ElGamalVariables) -> Self {
-        ModuleVarSettings {
-            elgamal: Some(elgamal),
-        }
+    /// The module space, module and config of the module named `name`
+    fn get(&self, name: &str) -> Option<(usize, &dyn GraphModule, &(dyn Any + Send + Sync))> {
+        self.modules
+            .iter()
+            .enumerate()
+            .find(|(_, (module, _))| module.name() == name)
+            .map(|(i, (module, config))| (i, module.as_ref(), config.as_ref()))
     }
 }
 
-impl Default for ModuleVarSettings {
-    fn default() -> Self {
-        let dummy_elgamal = ElGamalVariables::default();
-        ModuleVarSettings {
-            elgamal: Some(dummy_elgamal),
-        }
+impl std::fmt::Debug for ModuleConfigs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModuleConfigs")
+            .field(
+                "modules",
+                &self.modules.iter().map(|(m, _)| m.name()).collect_vec(),
+            )
+            .field("ctx", &self.ctx)
+            .finish()
     }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
-/// Module input settings
+/// The results of the modules laying out the inputs, params and outputs
 pub struct ModuleSettings {
     ///
-    pub input: ModuleVarSettings,
+    pub input: ModuleForwardResult,
     ///
-    pub params: ModuleVarSettings,
+    pub params: ModuleForwardResult,
     ///
-    pub output: ModuleVarSettings,
+    pub output: ModuleForwardResult,
 }
 
 impl From<&GraphWitness> for ModuleSettings {
     fn from(graph_input: &GraphWitness) -> Self {
-        let mut settings = Self::default();
-
-        if let Some(processed_inputs) = &graph_input.processed_inputs {
-            if let Some(elgamal_result) = &processed_inputs.elgamal {
-                settings.input = ModuleVarSettings::new(elgamal_result.variables.clone());
-            }
-        }
-        if let Some(processed_params) = &graph_input.processed_params {
-            if let Some(elgamal_result) = &processed_params.elgamal {
-                settings.params = ModuleVarSettings::new(elgamal_result.variables.clone());
-            }
+        ModuleSettings {
+            input: graph_input.processed_inputs.clone().unwrap_or_default(),
+            params: graph_input.processed_params.clone().unwrap_or_default(),
+            output: graph_input.processed_outputs.clone().unwrap_or_default(),
         }
-        if let Some(processed_outputs) = &graph_input.processed_outputs {
-            if let Some(elgamal_result) = &processed_outputs.elgamal {
-                settings.output = ModuleVarSettings::new(elgamal_result.variables.clone());
-            }
-        }
-
-        settings
     }
 }
 
@@ -135,89 +178,188 @@ pub struct ElGamalResult {
     pub ciphertexts: Vec<Vec<Fp>>,
 }
 
-/// Result from a forward pass
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct ModuleForwardResult {
-    /// The inputs of the forward pass for poseidon
-    pub poseidon_hash: Option<Vec<Fp>>,
-    /// The outputs of the forward pass for ElGamal
-    pub elgamal: Option<ElGamalResult>,
+/// Result from the Poseidon cipher
+pub struct CipherResult {
+    /// Poseidon cipher variables (the key and the nonce of the first tensor)
+    pub variables: PoseidonCipherVariables,
+    /// The key hash, nonce and ciphertexts of each tensor, concatenated
+    pub ciphertexts: Vec<Fp>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+/// Result from Schnorr
+pub struct SchnorrResult {
+    /// Schnorr variables (the signature of each tensor)
+    pub variables: SchnorrVariables,
+    /// The public key of the signer of each tensor
+    pub instances: Vec<Vec<Fp>>,
 }
 
-/// Result from a forward pass
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct ModuleInstances {
-    poseidon: Vec<Fp>,
-    elgamal: Vec<Vec<Fp>>,
+/// Result from the Poseidon Merkle membership
+pub struct MembershipResult {
+    /// Membership variables (the authentication path of each tensor)
+    pub variables: PoseidonMembershipVariables,
+    /// The dataset root each tensor belongs to
+    pub roots: Vec<Fp>,
 }
 
-impl ModuleInstances {
-    /// Flatten the instances in order of module config
-    pub fn flatten(&self) -> Vec<Vec<Fp>> {
-        let mut instances = vec![];
-        // check if poseidon is empty
-        if !self.poseidon.is_empty() {
-            // we push as its a 1D vector
-            instances.push(self.poseidon.clone());
+/// Result from a forward pass: the result of each module that processed the tensors, keyed by module name
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<String, serde_json::Value>",
+    into = "BTreeMap<String, serde_json::Value>"
+)]
+pub struct ModuleForwardResult(BTreeMap<String, serde_json::Value>);
+
+impl ModuleForwardResult {
+    /// The result of the module named `name`
+    pub fn get(&self, name: &str) -> Option<&serde_json::Value> {
+        self.0.get(name)
+    }
+
+    /// The result of the module named `name`, deserialized (e.g as an [ElGamalResult])
+    pub fn parse<T: DeserializeOwned>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>, Box<dyn std::error::Error>> {
+        match self.0.get(name) {
+            Some(result) => Ok(Some(serde_json::from_value(result.clone())?)),
+            None => Ok(None),
         }
-        if !self.elgamal.is_empty() {
-            // we extend as its a 2D vector
-            instances.extend(self.elgamal.clone());
+    }
+
+    /// The Poseidon hashes of the hashed tensors
+    pub fn poseidon_hash(&self) -> Result<Option<Vec<Fp>>, Box<dyn std::error::Error>> {
+        self.parse(POSEIDON)
+    }
+
+    /// Sets the result of the module named `name`
+    pub fn insert(&mut self, name: &str, result: serde_json::Value) {
+        self.0.insert(name.to_string(), result);
+    }
+
+    /// The results of each module, in order of module name
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &serde_json::Value)> {
+        self.0.iter()
+    }
+
+    /// Whether no module produced a result
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<BTreeMap<String, serde_json::Value>> for ModuleForwardResult {
+    fn from(mut results: BTreeMap<String, serde_json::Value>) -> Self {
+        // earlier witnesses hold a null for each of the built-in modules that didn't run
+        results.retain(|_, result| !result.is_null());
+        if let Some(hashes) = results.remove(POSEIDON_HASH_KEY) {
+            results.insert(POSEIDON.to_string(), hashes);
         }
-        instances
+        ModuleForwardResult(results)
     }
 }
 
-/// Offset for the instances
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct ModuleInstanceOffset {
-    poseidon: Vec<usize>,
-    elgamal: Vec<usize>,
+impl From<ModuleForwardResult> for BTreeMap<String, serde_json::Value> {
+    fn from(mut results: ModuleForwardResult) -> Self {
+        if let Some(hashes) = results.0.remove(POSEIDON) {
+            results.0.insert(POSEIDON_HASH_KEY.to_string(), hashes);
+        }
+        results.0
+    }
 }
 
+/// Offset for the instances of each module, keyed by module name
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ModuleInstanceOffset(BTreeMap<String, Vec<usize>>);
+
 impl ModuleInstanceOffset {
     ///
     pub fn new() -> Self {
-        ModuleInstanceOffset {
-            poseidon: vec![0; crate::circuit::modules::poseidon::NUM_INSTANCE_COLUMNS],
-            elgamal: vec![0; crate::circuit::modules::elgamal::NUM_INSTANCE_COLUMNS],
-        }
+        Self::default()
+    }
+
+    /// The offsets of each of the instance columns of a module
+    fn of(&mut self, module: &dyn GraphModule) -> &mut Vec<usize> {
+        self.0
+            .entry(module.name().to_string())
+            .or_insert_with(|| vec![0; module.num_instance_columns()])
     }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
-///
+/// The sizes of the modules of a circuit
 pub struct ModuleSizes {
-    poseidon: (usize, Vec<usize>),
-    elgamal: (usize, Vec<usize>),
+    /// The number of rows, and of instances per instance column, of each module, keyed by module name
+    #[serde(flatten)]
+    modules: BTreeMap<String, (usize, Vec<usize>)>,
+    /// The number of hashes of the hashed inputs, which come first in the Poseidon instance column
+    #[serde(default)]
+    hashed_inputs: usize,
+    /// The number of hashes of the hashed outputs, which come last in the Poseidon instance column
+    #[serde(default)]
+    hashed_outputs: usize,
 }
 
 impl ModuleSizes {
     /// Create new module sizes
     pub fn new() -> Self {
-        ModuleSizes {
-            poseidon: (
-                0,
-                vec![0; crate::circuit::modules::poseidon::NUM_INSTANCE_COLUMNS],
-            ),
-            elgamal: (
-                0,
-                vec![0; crate::circuit::modules::elgamal::NUM_INSTANCE_COLUMNS],
-            ),
+        Self::default()
+    }
+
+    /// The modules that use instances, which are the modules the circuit configures, in order of module name
+    pub fn configured(&self) -> impl Iterator<Item = &str> {
+        self.modules
+            .iter()
+            .filter(|(_, (_, instances))| instances.iter().sum::<usize>() > 0)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// The indices of the instance columns of the module named `name` among the instance columns of the modules,
+    /// or `None` if the module isn't configured
+    pub fn instance_columns(&self, name: &str) -> Option<Range<usize>> {
+        let mut start = 0;
+        for configured in self.configured() {
+            let num_columns = self.modules[configured].1.len();
+            if configured == name {
+                return Some(start..start + num_columns);
+            }
+            start += num_columns;
         }
+        None
+    }
+
+    /// The positions of the hashes of the hashed inputs in the Poseidon instance column
+    pub fn input_hash_positions(&self) -> Range<usize> {
+        0..self.hashed_inputs
+    }
+
+    /// The positions of the hashes of the hashed outputs in the Poseidon instance column
+    pub fn output_hash_positions(&self) -> Range<usize> {
+        let num_hashes = self
+            .modules
+            .get(POSEIDON)
+            .map(|(_, instances)| instances.iter().sum::<usize>())
+            .unwrap_or(0);
+        num_hashes.saturating_sub(self.hashed_outputs)..num_hashes
     }
 
     /// Get the number of constraints
     pub fn max_constraints(&self) -> usize {
-        self.poseidon.0.max(self.elgamal.0)
+        self.modules
+            .values()
+            .map(|(rows, _)| *rows)
+            .max()
+            .unwrap_or(0)
     }
+
     /// Get the number of instances
     pub fn num_instances(&self) -> Vec<usize> {
-        // concat
-        self.poseidon
-            .1
-            .iter()
-            .chain(self.elgamal.1.iter())
+        // concat, in the order the modules' instance columns are configured in
+        self.configured()
+            .flat_map(|name| self.modules[name].1.iter())
             .copied()
             .collect_vec()
     }
@@ -228,235 +370,866 @@ impl ModuleSizes {
 pub struct GraphModules;
 
 impl GraphModules {
-    /// Get the instances for the module
-    fn instances_from_visibility(
-        visibility: Visibility,
-        module_res: &Option<ModuleForwardResult>,
-        instances: &mut ModuleInstances,
-    ) {
-        if visibility.is_hashed() {
-            instances
-                .poseidon
-                .extend(module_res.clone().unwrap().poseidon_hash.unwrap());
-        } else if visibility.is_encrypted() {
-            let ciphers = module_res.clone().unwrap().elgamal.unwrap().ciphertexts;
-            if instances.elgamal.is_empty() {
-                instances.elgamal = ciphers;
-            } else if !ciphers[2].is_empty() {
-                for (i, c) in ciphers.iter().enumerate().take(instances.elgamal.len()) {
-                    instances.elgamal[i].extend(c);
-                }
+    /// Get the instances of the results of a set of tensors, appending each column of each module's
+    fn extend_instances(
+        registry: &ModuleRegistry,
+        names: impl IntoIterator<Item = String>,
+        module_res: &ModuleForwardResult,
+        instances: &mut BTreeMap<String, Vec<Vec<Fp>>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for name in names {
+            let result = module_res
+                .get(&name)
+                .ok_or_else(|| format!("the witness holds no {} results", name))?;
+            let columns = registry.get(&name)?.instances(result)?;
+            let module_instances = instances.entry(name).or_default();
+            if module_instances.len() < columns.len() {
+                module_instances.resize(columns.len(), vec![]);
+            }
+            for (i, column) in columns.into_iter().enumerate() {
+                module_instances[i].extend(column);
             }
         }
+        Ok(())
+    }
+
+    /// The names of the modules processing a set of tensors with per-tensor visibilities
+    fn module_names(visibilities: &[Visibility]) -> Vec<String> {
+        module_visibilities(visibilities)
+            .iter()
+            .filter_map(|v| v.module_name().map(str::to_string))
+            .collect()
     }
 
     /// Generate the public inputs for the circuit
-    pub fn public_inputs(data: &GraphWitness, visibility: VarVisibility) -> Vec<Vec<Fp>> {
-        let mut instances = ModuleInstances::default();
-        Self::instances_from_visibility(visibility.input, &data.processed_inputs, &mut instances);
-        Self::instances_from_visibility(visibility.params, &data.processed_params, &mut instances);
-        Self::instances_from_visibility(visibility.output, &data.processed_outputs, &mut instances);
-
-        instances.flatten()
-    }
-
-    fn num_constraint_given_shapes(
-        visibility: Visibility,
-        shapes: Vec<Vec<usize>>,
-        sizes: &mut ModuleSizes,
-    ) {
-        if visibility.is_hashed() {
-            for shape in shapes {
-                let total_len = shape.iter().product::<usize>();
-                sizes.poseidon.0 += POSEIDON_CONSTRAINTS_ESTIMATE * total_len;
-                if total_len > 0 {
-                    sizes.poseidon.0 +=
-                        POSEIDOIN_FIXED_COST_ESTIMATE * ((sizes.elgamal.0 == 0) as usize);
-                    sizes.poseidon.1[0] += 1;
-                }
-            }
-        } else if visibility.is_encrypted() {
-            let total_len = shapes
+    pub fn public_inputs(
+        registry: &ModuleRegistry,
+        data: &GraphWitness,
+        visibility: VarVisibility,
+    ) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        let mut instances = BTreeMap::new();
+        let mut extend =
+            |names: Vec<String>, module_res: &Option<ModuleForwardResult>| match module_res {
+                Some(res) => Self::extend_instances(registry, names, res, &mut instances),
+                None if names.is_empty() => Ok(()),
+                None => Err(format!(
+                    "the witness holds no results for the {} modules",
+                    names.join(", ")
+                )
+                .into()),
+            };
+        extend(
+            Self::module_names(&visibility.inputs_for(data.input_data.len())),
+            &data.processed_inputs,
+        )?;
+        // the params of each visibility are processed by whichever modules produced a result
+        let params_names = data
+            .processed_params
+            .iter()
+            .flat_map(|res| res.iter().map(|(name, _)| name.clone()))
+            .collect();
+        extend(params_names, &data.processed_params)?;
+        extend(
+            Self::module_names(&visibility.outputs_for(data.output_data.len())),
+            &data.processed_outputs,
+        )?;
+
+        // flatten the instances in order of module name, skipping the modules without any
+        Ok(instances
+            .into_values()
+            .filter(|columns| columns.iter().any(|c| !c.is_empty()))
+            .flatten()
+            .collect())
+    }
+
+    /// Groups tensors with per-tensor visibilities by the module that processes them
+    fn group_by_visibility(
+        visibilities: &[Visibility],
+        shapes: &[Vec<usize>],
+    ) -> Vec<(Visibility, Vec<Vec<usize>>)> {
+        module_visibilities(visibilities)
+            .into_iter()
+            .map(|visibility| {
+                let group = shapes
+                    .iter()
+                    .zip(visibilities)
+                    .filter(|(_, v)| **v == visibility)
+                    .map(|(s, _)| s.clone())
+                    .collect_vec();
+                (visibility, group)
+            })
+            .filter(|(_, group)| !group.is_empty())
+            .collect()
+    }
+
+    /// Lays out each group of tensors (in the order the circuit does) with dummy values and results, as during
+    /// keygen, and returns the number of rows the module uses.
+    fn dummy_layout(
+        module: &dyn GraphModule,
+        groups: &[Vec<Vec<usize>>],
+        ctx: &ModuleContext,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = module.configure(&mut cs);
+
+        let mut assignment = DummyAssignment;
+        let mut layouter = ModuleLayouter::new(&mut assignment, cs.constants().clone())?;
+        let mut instance_offsets = vec![0; module.num_instance_columns()];
+
+        for shapes in groups {
+            let values = shapes
                 .iter()
-                .map(|x| x.iter().product::<usize>())
-                .sum::<usize>();
-            // 3 constraints for each ciphertext c1 and sk
-            if total_len > 0 {
-                // add the 1 time fixed cost of maingate + ecc chips
-                sizes.elgamal.0 += ELGAMAL_FIXED_COST_ESTIMATE * ((sizes.elgamal.0 == 0) as usize);
-                sizes.elgamal.1[1] += 3;
-            }
-            // 1 constraint for each ciphertext c2 elem
-            for shape in shapes {
-                let total_len = shape.iter().product::<usize>();
-                sizes.elgamal.0 += ELGAMAL_CONSTRAINTS_ESTIMATE * total_len;
-                if total_len > 0 {
-                    sizes.elgamal.1[2] += 1;
-                }
-            }
+                .filter(|shape| shape.iter().product::<usize>() > 0)
+                .map(|shape| Tensor::<Value<Fp>>::new(None, shape).map(ValTensor::from))
+                .collect::<Result<Vec<_>, _>>()?;
+            module.layout(
+                config.as_ref(),
+                &mut layouter,
+                &values,
+                None,
+                &mut instance_offsets,
+                ctx,
+            )?;
         }
+
+        Ok(layouter.num_rows())
     }
-    /// Get the number of constraints and instances for the module
+
+    /// Get the number of constraints and instances for the modules.
+    /// `params_shapes` holds the (flattened) shape of the params of each visibility.
+    /// The number of constraints of each module is measured by a dummy layout of the module.
     pub fn num_constraints_and_instances(
+        registry: &ModuleRegistry,
         input_shapes: Vec<Vec<usize>>,
-        params_shapes: Vec<Vec<usize>>,
+        params_shapes: Vec<(Visibility, Vec<usize>)>,
         output_shapes: Vec<Vec<usize>>,
         visibility: VarVisibility,
-    ) -> ModuleSizes {
+        ctx: &ModuleContext,
+    ) -> Result<ModuleSizes, Box<dyn std::error::Error>> {
         let mut module_sizes = ModuleSizes::new();
 
-        Self::num_constraint_given_shapes(visibility.input, input_shapes, &mut module_sizes);
-        Self::num_constraint_given_shapes(visibility.params, params_shapes, &mut module_sizes);
-        Self::num_constraint_given_shapes(visibility.output, output_shapes, &mut module_sizes);
+        // hashes are laid out in the order of the tensors, as many as the Poseidon module lays out in the hash mode
+        let num_hashes =
+            |visibilities: Vec<Visibility>, shapes: &[Vec<usize>]| -> Result<usize, GraphError> {
+                let lens = visibilities
+                    .iter()
+                    .zip(shapes)
+                    .filter(|(v, _)| v.is_hashed())
+                    .map(|(_, shape)| shape.iter().product::<usize>())
+                    .collect_vec();
+                if lens.is_empty() {
+                    return Ok(0);
+                }
+                Ok(registry
+                    .get(POSEIDON)?
+                    .num_instances(&lens, ctx)
+                    .iter()
+                    .sum())
+            };
+        module_sizes.hashed_inputs =
+            num_hashes(visibility.inputs_for(input_shapes.len()), &input_shapes)?;
+        module_sizes.hashed_outputs =
+            num_hashes(visibility.outputs_for(output_shapes.len()), &output_shapes)?;
 
-        module_sizes
-    }
+        // the inputs, params and outputs are laid out in this order by the circuit
+        let mut groups =
+            Self::group_by_visibility(&visibility.inputs_for(input_shapes.len()), &input_shapes);
+        groups.extend(
+            params_shapes
+                .into_iter()
+                .map(|(params_visibility, shape)| (params_visibility, vec![shape])),
+        );
+        groups.extend(Self::group_by_visibility(
+            &visibility.outputs_for(output_shapes.len()),
+            &output_shapes,
+        ));
 
-    /// Layout the module
-    fn layout_module(
-        module: &mut impl Module<Fp>,
-        layouter: &mut impl Layouter<Fp>,
-        values: &mut [Vec<ValTensor<Fp>>],
-        instance_offset: &mut [usize],
-    ) -> Result<(), Error> {
-        // reserve module 0 for ... modules
-
-        values.iter_mut().for_each(|x| {
-            // hash the input and replace the constrained cells in the input
-            let cloned_x = (*x).clone();
-            let dims = cloned_x[0].dims();
-            x[0] = module
-                .layout(layouter, &cloned_x, instance_offset.to_owned())
-                .unwrap();
-            x[0].reshape(dims).unwrap();
-            for (i, inc) in module
-                .instance_increment_input(x.iter().map(|x| x.len()).collect())
+        let group_visibilities = groups.iter().map(|(v, _)| *v).collect_vec();
+        for module_visibility in module_visibilities(&group_visibilities) {
+            let name = match module_visibility.module_name() {
+                Some(name) => name,
+                None => continue,
+            };
+            let module = registry.get(name)?;
+            let module_groups = groups
                 .iter()
-                .enumerate()
-            {
-                instance_offset[i] += inc;
+                .filter(|(v, _)| *v == module_visibility)
+                .map(|(_, shapes)| shapes.clone())
+                .collect_vec();
+
+            let (num_rows, num_instances) = module_sizes
+                .modules
+                .entry(name.to_string())
+                .or_insert_with(|| (0, vec![0; module.num_instance_columns()]));
+            for shapes in module_groups.iter() {
+                let lens = shapes
+                    .iter()
+                    .map(|shape| shape.iter().product::<usize>())
+                    .collect_vec();
+                let increments = module.num_instances(&lens, ctx);
+                if num_instances.len() < increments.len() {
+                    num_instances.resize(increments.len(), 0);
+                }
+                for (i, inc) in increments.into_iter().enumerate() {
+                    num_instances[i] += inc;
+                }
             }
-            // increment the instance offset to make way for future module layouts
-        });
 
-        for (i, inc) in module.instance_increment_module().iter().enumerate() {
-            instance_offset[i] += inc;
+            let module_groups = module_groups
+                .into_iter()
+                .filter(|shapes| shapes.iter().any(|s| s.iter().product::<usize>() > 0))
+                .collect_vec();
+            if !module_groups.is_empty() {
+                *num_rows = Self::dummy_layout(module.as_ref(), &module_groups, ctx)?;
+            }
         }
 
-        Ok(())
+        Ok(module_sizes)
     }
 
-    /// Layout the module
+    /// Layout the module processing the values with `element_visibility`
     pub fn layout(
         layouter: &mut impl Layouter<Fp>,
         configs: &ModuleConfigs,
         values: &mut [ValTensor<Fp>],
         element_visibility: Visibility,
         instance_offset: &mut ModuleInstanceOffset,
-        module_settings: &ModuleVarSettings,
+        module_results: &ModuleForwardResult,
     ) -> Result<(), Error> {
-        // If the module is hashed, then we need to hash the inputs
-        if element_visibility.is_hashed() && !values.is_empty() {
-            // reserve module 0 for poseidon modules
-            layouter.assign_region(|| "_enter_module_0", |_| Ok(()))?;
-            // config for poseidon
-            let poseidon_config = configs.poseidon.clone().unwrap();
-            // create the module
-            let mut chip = ModulePoseidon::new(poseidon_config);
-            // concat values and sk to get the inputs
-            let mut inputs = values.iter_mut().map(|x| vec![x.clone()]).collect_vec();
-            // layout the module
-            Self::layout_module(
-                &mut chip,
-                layouter,
-                &mut inputs,
-                &mut instance_offset.poseidon,
-            )?;
-            // replace the inputs with the outputs
-            values.iter_mut().enumerate().for_each(|(i, x)| {
-                x.clone_from(&inputs[i][0]);
-            });
-
-        // If the module is encrypted, then we need to encrypt the inputs
-        } else if element_visibility.is_encrypted() && !values.is_empty() {
-            // reserve module 1 for elgamal modules
-            layouter.assign_region(|| "_enter_module_1", |_| Ok(()))?;
-            // config for elgamal
-            let elgamal_config = configs.elgamal.clone().unwrap();
-            // create the module
-            let mut chip = ElGamalGadget::new(elgamal_config);
-            // load the variables
-            let variables = module_settings.elgamal.as_ref().unwrap().clone();
-            chip.load_variables(variables.clone());
-            // load the sk:
-            let sk: Tensor<ValType<Fp>> =
-                Tensor::new(Some(&[Value::known(variables.sk).into()]), &[1]).unwrap();
-            // concat values and sk to get the inputs
-            let mut inputs = values
-                .iter_mut()
-                .map(|x| vec![x.clone(), sk.clone().into()])
+        let name = match element_visibility.module_name() {
+            Some(name) if !values.is_empty() => name,
+            _ => return Ok(()),
+        };
+        let (module_space, module, config) = configs.get(name).ok_or_else(|| {
+            error!("the {} module is not configured", name);
+            Error::Synthesis
+        })?;
+        // each module is laid out in its own module space
+        layouter.assign_region(|| format!("_enter_module_{}", module_space), |_| Ok(()))?;
+        let outputs = module.layout(
+            config,
+            layouter,
+            values,
+            module_results.get(name),
+            instance_offset.of(module),
+            &configs.ctx,
+        )?;
+        if outputs.len() != values.len() {
+            error!(
+                "the {} module laid out {} tensors instead of {}",
+                name,
+                outputs.len(),
+                values.len()
+            );
+            return Err(Error::Synthesis);
+        }
+        // replace the inputs with the outputs
+        for (x, mut output) in values.iter_mut().zip(outputs) {
+            output.reshape(x.dims()).map_err(|_| Error::Synthesis)?;
+            *x = output;
+        }
+        Ok(())
+    }
+
+    /// Layout the modules for a set of tensors with per-tensor visibilities, processing each group of
+    /// tensors sharing a visibility in a single module layout
+    pub fn layout_per_tensor(
+        layouter: &mut impl Layouter<Fp>,
+        configs: &ModuleConfigs,
+        values: &mut [ValTensor<Fp>],
+        visibilities: &[Visibility],
+        instance_offset: &mut ModuleInstanceOffset,
+        module_results: &ModuleForwardResult,
+    ) -> Result<(), Error> {
+        for visibility in module_visibilities(visibilities) {
+            let idxs = (0..values.len())
+                .filter(|i| visibilities.get(*i) == Some(&visibility))
                 .collect_vec();
-            // layout the module
-            Self::layout_module(
-                &mut chip,
+            if idxs.is_empty() {
+                continue;
+            }
+            let mut group = idxs.iter().map(|i| values[*i].clone()).collect_vec();
+            Self::layout(
                 layouter,
-                &mut inputs,
-                &mut instance_offset.elgamal,
+                configs,
+                &mut group,
+                visibility,
+                instance_offset,
+                module_results,
             )?;
-            // replace the inputs with the outputs
-            values.iter_mut().enumerate().for_each(|(i, x)| {
-                x.clone_from(&inputs[i][0]);
-            });
+            for (i, v) in idxs.into_iter().zip(group) {
+                values[i] = v;
+            }
         }
-
         Ok(())
     }
 
-    /// Run forward pass
+    /// Run the forward pass of the modules over a set of tensors with per-tensor visibilities, processing each
+    /// group of tensors sharing a visibility together.
+    /// Returns `None` if none of the tensors require processing.
+    pub fn forward_per_tensor(
+        registry: &ModuleRegistry,
+        inputs: &[Tensor<i128>],
+        visibilities: &[Visibility],
+        ctx: &ModuleContext,
+        prover: &ProverInputs,
+    ) -> Result<Option<ModuleForwardResult>, Box<dyn std::error::Error>> {
+        let mut res = ModuleForwardResult::default();
+        for visibility in module_visibilities(visibilities) {
+            let group = inputs
+                .iter()
+                .zip(visibilities)
+                .filter(|(_, v)| **v == visibility)
+                .map(|(t, _)| t.clone())
+                .collect_vec();
+            if group.is_empty() {
+                continue;
+            }
+            res.0
+                .extend(Self::forward(registry, &group, visibility, ctx, prover)?.0);
+        }
+        if res.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(res))
+    }
+
+    /// Run the forward pass of the module processing a group of tensors with `element_visibility`.
+    /// Encrypted tensors are encrypted to `ctx.elgamal_recipient`. If the recipient is the prover, they are
+    /// encrypted to `prover.elgamal_keypair` if supplied, and to a freshly generated keypair otherwise.
+    /// Symencrypted tensors are encrypted under `prover.cipher_key` if supplied, and under a freshly generated key
+    /// otherwise. Signed tensors are verified against `prover.signatures` (one per tensor), and member tensors are
+    /// looked up in `prover.dataset`.
     pub fn forward(
+        registry: &ModuleRegistry,
         inputs: &[Tensor<i128>],
         element_visibility: Visibility,
+        ctx: &ModuleContext,
+        prover: &ProverInputs,
     ) -> Result<ModuleForwardResult, Box<dyn std::error::Error>> {
-        let mut rng = &mut rand::thread_rng();
-        let mut poseidon_hash = None;
-        let mut elgamal = None;
-
-        if element_visibility.is_hashed() {
-            let field_elements = inputs.iter().fold(vec![], |mut acc, x| {
-                let field_elements = x.iter().map(|x| i128_to_felt::<Fp>(*x)).collect();
-                let res = ModulePoseidon::run(field_elements).unwrap()[0].clone();
-                acc.extend(res);
-                acc
-            });
-            poseidon_hash = Some(field_elements);
+        let name = element_visibility.module_name().ok_or_else(|| {
+            format!(
+                "{} values are not processed by a module",
+                element_visibility
+            )
+        })?;
+        let module = registry.get(name)?;
+        let field_elements = inputs
+            .iter()
+            .map(|x| x.iter().map(|x| i128_to_felt::<Fp>(*x)).collect_vec())
+            .collect_vec();
+        let result = module
+            .run(&field_elements, ctx, prover)
+            .map_err(|e| format!("{} module: {}", name, e))?;
+
+        let mut res = ModuleForwardResult::default();
+        res.insert(name, result);
+        Ok(res)
+    }
+}
+
+/// The number of tensors with any elements among tensors of `lens` elements
+fn num_nonempty(lens: &[usize]) -> usize {
+    lens.iter().filter(|len| **len > 0).count()
+}
+
+/// The config of a built-in module
+fn downcast<T: 'static>(config: &(dyn Any + Send + Sync)) -> Result<&T, Error> {
+    config.downcast_ref::<T>().ok_or_else(|| {
+        error!("a module was laid out with the config of another module");
+        Error::Synthesis
+    })
+}
+
+/// The result of a built-in module, which defaults when laying out without witnesses
+fn parse_result<T: DeserializeOwned + Default>(
+    result: Option<&serde_json::Value>,
+) -> Result<T, Error> {
+    match result {
+        Some(result) => serde_json::from_value(result.clone()).map_err(|e| {
+            error!("failed to read a module's result from the witness: {}", e);
+            Error::Synthesis
+        }),
+        None => Ok(T::default()),
+    }
+}
+
+/// A single value as a tensor, e.g a key appended to each of the tensors a module lays out
+fn single_value(value: Fp) -> Result<ValTensor<Fp>, Error> {
+    let t: Tensor<ValType<Fp>> =
+        Tensor::new(Some(&[Value::known(value).into()]), &[1]).map_err(|_| Error::Synthesis)?;
+    Ok(t.into())
+}
+
+/// Lays out the tensors (each followed by `extra`, e.g a key) with a module chip, constraining the chip's instances
+/// from `instance_offsets` on, and returns the laid out tensors
+fn layout_chip(
+    chip: &mut impl Module<Fp>,
+    layouter: &mut dyn DynLayouter,
+    inputs: &[ValTensor<Fp>],
+    extra: Option<ValTensor<Fp>>,
+    instance_offsets: &mut [usize],
+) -> Result<Vec<ValTensor<Fp>>, Error> {
+    let mut layouter = ErasedLayouter::new(layouter);
+    let mut values = inputs
+        .iter()
+        .map(|x| {
+            std::iter::once(x.clone())
+                .chain(extra.clone())
+                .collect_vec()
+        })
+        .collect_vec();
+
+    for x in values.iter_mut() {
+        // lay out the input and replace the constrained cells in the input
+        let cloned_x = (*x).clone();
+        let dims = cloned_x[0].dims();
+        x[0] = chip.layout(&mut layouter, &cloned_x, instance_offsets.to_owned())?;
+        x[0].reshape(dims).map_err(|_| Error::Synthesis)?;
+        // increment the instance offset to make way for future module layouts
+        for (i, inc) in chip
+            .instance_increment_input(x.iter().map(|x| x.len()).collect())
+            .iter()
+            .enumerate()
+        {
+            instance_offsets[i] += inc;
         }
+    }
+
+    for (i, inc) in chip.instance_increment_module().iter().enumerate() {
+        instance_offsets[i] += inc;
+    }
+
+    Ok(values.into_iter().map(|mut x| x.swap_remove(0)).collect())
+}
+
+/// The Poseidon module, hashing each tensor with the [HashMode] of the settings
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PoseidonModule;
+
+impl GraphModule for PoseidonModule {
+    fn name(&self) -> &'static str {
+        POSEIDON
+    }
+
+    fn num_instance_columns(&self) -> usize {
+        crate::circuit::modules::poseidon::NUM_INSTANCE_COLUMNS
+    }
 
-        if element_visibility.is_encrypted() {
-            let variables = ElGamalVariables::gen_random(&mut rng);
+    fn configure(&self, cs: &mut ConstraintSystem<Fp>) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(ModulePoseidon::configure(cs))
+    }
+
+    fn num_instances(&self, lens: &[usize], _: &ModuleContext) -> Vec<usize> {
+        // the hash of each tensor
+        vec![num_nonempty(lens)]
+    }
+
+    fn run(
+        &self,
+        inputs: &[Vec<Fp>],
+        ctx: &ModuleContext,
+        _: &ProverInputs,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let mut hashes = vec![];
+        for x in inputs {
+            let res = match ctx.hash_mode {
+                HashMode::Flat => ModulePoseidon::run(x.clone()),
+                HashMode::Merkle => ModulePoseidonMerkle::run(x.clone()),
+                HashMode::LengthPrefixed => ModulePoseidonLengthPrefixed::run(x.clone()),
+            }?;
+            hashes.extend(res.into_iter().next().ok_or("no hash was returned")?);
+        }
+        Ok(serde_json::to_value(hashes)?)
+    }
 
-            let elgamal_outputs = inputs.iter().fold(vec![], |mut acc: Vec<Vec<Fp>>, x| {
-                let field_elements = x.iter().map(|x| i128_to_felt::<Fp>(*x)).collect();
-                let ciphers = ElGamalGadget::run((field_elements, variables.clone())).unwrap();
+    fn instances(
+        &self,
+        result: &serde_json::Value,
+    ) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        Ok(vec![serde_json::from_value(result.clone())?])
+    }
 
-                if acc.is_empty() {
-                    ciphers
-                } else {
-                    for i in 0..acc.len() {
-                        acc[i].extend(ciphers[i].clone());
-                    }
-                    acc
+    fn layout(
+        &self,
+        config: &(dyn Any + Send + Sync),
+        layouter: &mut dyn DynLayouter,
+        inputs: &[ValTensor<Fp>],
+        _: Option<&serde_json::Value>,
+        instance_offsets: &mut [usize],
+        ctx: &ModuleContext,
+    ) -> Result<Vec<ValTensor<Fp>>, Error> {
+        let config = downcast::<ModulePoseidonConfig>(config)?.clone();
+        match ctx.hash_mode {
+            HashMode::Flat => layout_chip(
+                &mut ModulePoseidon::new(config),
+                layouter,
+                inputs,
+                None,
+                instance_offsets,
+            ),
+            HashMode::Merkle => layout_chip(
+                &mut ModulePoseidonMerkle::new(config),
+                layouter,
+                inputs,
+                None,
+                instance_offsets,
+            ),
+            HashMode::LengthPrefixed => layout_chip(
+                &mut ModulePoseidonLengthPrefixed::new(config),
+                layouter,
+                inputs,
+                None,
+                instance_offsets,
+            ),
+        }
+    }
+}
+
+/// The ElGamal module, encrypting the tensors of a group under the same variables
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ElGamalModule;
+
+impl GraphModule for ElGamalModule {
+    fn name(&self) -> &'static str {
+        ELGAMAL
+    }
+
+    fn num_instance_columns(&self) -> usize {
+        crate::circuit::modules::elgamal::NUM_INSTANCE_COLUMNS
+    }
+
+    fn configure(&self, cs: &mut ConstraintSystem<Fp>) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(ElGamalGadget::configure(cs))
+    }
+
+    fn num_instances(&self, lens: &[usize], ctx: &ModuleContext) -> Vec<usize> {
+        // c1 and the sk hash (or recipient pk) of each module layout
+        let num_c1 = if lens.iter().sum::<usize>() > 0 {
+            ctx.elgamal_recipient.num_c1_instances()
+        } else {
+            0
+        };
+        vec![0, num_c1, num_nonempty(lens)]
+    }
+
+    fn run(
+        &self,
+        inputs: &[Vec<Fp>],
+        ctx: &ModuleContext,
+        prover: &ProverInputs,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let variables = ElGamalVariables::gen_random_for_recipient(
+            &mut rand::thread_rng(),
+            ctx.elgamal_recipient,
+            prover.elgamal_keypair,
+        );
+
+        let mut ciphertexts: Vec<Vec<Fp>> = vec![];
+        for x in inputs {
+            let ciphers = ElGamalGadget::run((x.clone(), variables.clone()))?;
+            if ciphertexts.is_empty() {
+                ciphertexts = ciphers;
+            } else {
+                // the tensors share the same variables, and c1 is laid out once per module layout so we
+                // only extend the ciphertexts
+                ciphertexts[2].extend(ciphers[2].clone());
+            }
+        }
+
+        Ok(serde_json::to_value(ElGamalResult {
+            variables,
+            ciphertexts,
+        })?)
+    }
+
+    fn instances(
+        &self,
+        result: &serde_json::Value,
+    ) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        let ciphertexts = serde_json::from_value::<ElGamalResult>(result.clone())?.ciphertexts;
+        // a layout without ciphertexts doesn't lay out c1 either
+        if ciphertexts.get(2).map_or(true, |c| c.is_empty()) {
+            return Ok(vec![vec![]; self.num_instance_columns()]);
+        }
+        Ok(ciphertexts)
+    }
+
+    fn layout(
+        &self,
+        config: &(dyn Any + Send + Sync),
+        layouter: &mut dyn DynLayouter,
+        inputs: &[ValTensor<Fp>],
+        result: Option<&serde_json::Value>,
+        instance_offsets: &mut [usize],
+        ctx: &ModuleContext,
+    ) -> Result<Vec<ValTensor<Fp>>, Error> {
+        let mut chip = ElGamalGadget::new(downcast::<ElGamalConfig>(config)?.clone());
+        // load the variables, encrypting to the recipient of the settings (which the vk was generated for).
+        // A fixed public key is part of the circuit, whereas a public key that is a public input is the one the
+        // witness was encrypted to (the settings' standing in for it when there's no witness, as at keygen)
+        let mut variables = parse_result::<ElGamalResult>(result)?.variables;
+        match ctx.elgamal_recipient {
+            ElGamalRecipient::FixedPublicKey(pk) => variables.pk = pk,
+            ElGamalRecipient::PublicKey(pk) if variables.pk == ElGamalVariables::default().pk => {
+                variables.pk = pk
+            }
+            _ => {}
+        }
+        variables.recipient = match ctx.elgamal_recipient {
+            ElGamalRecipient::PublicKey(_) => ElGamalRecipient::PublicKey(variables.pk),
+            recipient => recipient,
+        };
+        chip.load_variables(variables.clone());
+        // the sk (or the randomness if the prover isn't the recipient) follows each tensor
+        let sk = single_value(variables.secret())?;
+        layout_chip(&mut chip, layouter, inputs, Some(sk), instance_offsets)
+    }
+}
+
+/// The Poseidon cipher module, encrypting each tensor of a group under the shared key and its own nonce
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CipherModule;
+
+impl GraphModule for CipherModule {
+    fn name(&self) -> &'static str {
+        CIPHER
+    }
+
+    fn num_instance_columns(&self) -> usize {
+        crate::circuit::modules::poseidon::NUM_INSTANCE_COLUMNS
+    }
+
+    fn configure(&self, cs: &mut ConstraintSystem<Fp>) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(ModulePoseidonCipher::configure(cs))
+    }
+
+    fn num_instances(&self, lens: &[usize], _: &ModuleContext) -> Vec<usize> {
+        // the key hash and nonce, followed by the ciphertexts, of each tensor
+        vec![lens.iter().filter(|len| **len > 0).map(|len| 2 + len).sum()]
+    }
+
+    fn run(
+        &self,
+        inputs: &[Vec<Fp>],
+        _: &ModuleContext,
+        prover: &ProverInputs,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let mut rng = rand::thread_rng();
+        let variables = match prover.cipher_key {
+            Some(key) => PoseidonCipherVariables::gen_random_for_key(&mut rng, key.0),
+            None => PoseidonCipherVariables::gen_random(&mut rng),
+        };
+
+        let mut ciphertexts = vec![];
+        for (i, x) in inputs.iter().enumerate() {
+            // each tensor is encrypted under its own nonce
+            let res = ModulePoseidonCipher::run((x.clone(), variables.for_tensor(i)))?;
+            ciphertexts.extend(res.into_iter().next().ok_or("no ciphertext was returned")?);
+        }
+
+        Ok(serde_json::to_value(CipherResult {
+            variables,
+            ciphertexts,
+        })?)
+    }
+
+    fn instances(
+        &self,
+        result: &serde_json::Value,
+    ) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        Ok(vec![
+            serde_json::from_value::<CipherResult>(result.clone())?.ciphertexts,
+        ])
+    }
+
+    fn layout(
+        &self,
+        config: &(dyn Any + Send + Sync),
+        layouter: &mut dyn DynLayouter,
+        inputs: &[ValTensor<Fp>],
+        result: Option<&serde_json::Value>,
+        instance_offsets: &mut [usize],
+        _: &ModuleContext,
+    ) -> Result<Vec<ValTensor<Fp>>, Error> {
+        let mut chip =
+            ModulePoseidonCipher::new(downcast::<ModulePoseidonCipherConfig>(config)?.clone());
+        // the nonces are read from the instances, so we only need to load the key, which follows each tensor
+        let key = single_value(parse_result::<CipherResult>(result)?.variables.key)?;
+        layout_chip(&mut chip, layouter, inputs, Some(key), instance_offsets)
+    }
+}
+
+/// The Schnorr module, verifying each tensor of a group against the signature at its position
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SchnorrModule;
+
+impl GraphModule for SchnorrModule {
+    fn name(&self) -> &'static str {
+        SCHNORR
+    }
+
+    fn num_instance_columns(&self) -> usize {
+        crate::circuit::modules::schnorr::NUM_INSTANCE_COLUMNS
+    }
+
+    fn configure(&self, cs: &mut ConstraintSystem<Fp>) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(ModuleSchnorr::configure(cs))
+    }
+
+    fn num_instances(&self, lens: &[usize], _: &ModuleContext) -> Vec<usize> {
+        // the public key of the signer of each tensor
+        vec![0, 2 * num_nonempty(lens)]
+    }
+
+    fn run(
+        &self,
+        inputs: &[Vec<Fp>],
+        _: &ModuleContext,
+        prover: &ProverInputs,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        if prover.signatures.len() != inputs.len() {
+            return Err(format!(
+                "expected {} signatures for the signed tensors, got {}",
+                inputs.len(),
+                prover.signatures.len()
+            )
+            .into());
+        }
+
+        let mut instances: Vec<Vec<Fp>> = vec![];
+        for (i, (x, signature)) in inputs.iter().zip(prover.signatures).enumerate() {
+            let res = ModuleSchnorr::run((x.clone(), *signature))
+                .map_err(|e| format!("signed tensor {}: {}", i, e))?;
+            if instances.is_empty() {
+                instances = res;
+            } else {
+                for (j, r) in res.into_iter().enumerate() {
+                    instances[j].extend(r);
                 }
-            });
+            }
+        }
+
+        Ok(serde_json::to_value(SchnorrResult {
+            variables: SchnorrVariables::gen_random(
+                &mut rand::thread_rng(),
+                prover.signatures.to_vec(),
+            ),
+            instances,
+        })?)
+    }
 
-            elgamal = Some(ElGamalResult {
-                variables,
-                ciphertexts: elgamal_outputs,
-            });
+    fn instances(
+        &self,
+        result: &serde_json::Value,
+    ) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_value::<SchnorrResult>(result.clone())?.instances)
+    }
+
+    fn layout(
+        &self,
+        config: &(dyn Any + Send + Sync),
+        layouter: &mut dyn DynLayouter,
+        inputs: &[ValTensor<Fp>],
+        result: Option<&serde_json::Value>,
+        instance_offsets: &mut [usize],
+        _: &ModuleContext,
+    ) -> Result<Vec<ValTensor<Fp>>, Error> {
+        let mut chip = ModuleSchnorr::new(downcast::<SchnorrConfig>(config)?.clone());
+        chip.load_variables(parse_result::<SchnorrResult>(result)?.variables);
+        // verify each tensor against the signature at its position
+        let mut outputs = vec![];
+        for (i, input) in inputs.iter().enumerate() {
+            chip.select_tensor(i);
+            outputs.extend(layout_chip(
+                &mut chip,
+                layouter,
+                std::slice::from_ref(input),
+                None,
+                instance_offsets,
+            )?);
         }
+        Ok(outputs)
+    }
+}
 
-        Ok(ModuleForwardResult {
-            poseidon_hash,
-            elgamal,
-        })
+/// The Poseidon Merkle membership module, proving each tensor of a group is a row of the dataset
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MembershipModule;
+
+impl GraphModule for MembershipModule {
+    fn name(&self) -> &'static str {
+        MEMBERSHIP
+    }
+
+    fn num_instance_columns(&self) -> usize {
+        crate::circuit::modules::poseidon::NUM_INSTANCE_COLUMNS
+    }
+
+    fn configure(&self, cs: &mut ConstraintSystem<Fp>) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(ModulePoseidonMembership::configure(cs))
+    }
+
+    fn num_instances(&self, lens: &[usize], _: &ModuleContext) -> Vec<usize> {
+        // the dataset root of each tensor
+        vec![num_nonempty(lens)]
+    }
+
+    fn run(
+        &self,
+        inputs: &[Vec<Fp>],
+        _: &ModuleContext,
+        prover: &ProverInputs,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let tree = prover
+            .dataset
+            .ok_or("member tensors require a dataset tree")?;
+
+        let mut paths = vec![];
+        let mut roots = vec![];
+        for (i, x) in inputs.iter().enumerate() {
+            let path = tree
+                .path(ModulePoseidonMembership::leaf(x.clone()))
+                .ok_or(format!("member tensor {} is not a row of the dataset", i))?;
+            let res = ModulePoseidonMembership::run((x.clone(), path.clone()))?;
+            roots.extend(res.into_iter().next().ok_or("no root was returned")?);
+            paths.push(path);
+        }
+
+        Ok(serde_json::to_value(MembershipResult {
+            variables: PoseidonMembershipVariables {
+                depth: tree.depth(),
+                paths,
+            },
+            roots,
+        })?)
+    }
+
+    fn instances(
+        &self,
+        result: &serde_json::Value,
+    ) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+        Ok(vec![
+            serde_json::from_value::<MembershipResult>(result.clone())?.roots,
+        ])
+    }
+
+    fn layout(
+        &self,
+        config: &(dyn Any + Send + Sync),
+        layouter: &mut dyn DynLayouter,
+        inputs: &[ValTensor<Fp>],
+        result: Option<&serde_json::Value>,
+        instance_offsets: &mut [usize],
+        ctx: &ModuleContext,
+    ) -> Result<Vec<ValTensor<Fp>>, Error> {
+        let mut chip = ModulePoseidonMembership::new(
+            downcast::<ModulePoseidonMembershipConfig>(config)?.clone(),
+        );
+        // load the paths, at the depth of the settings (which the vk was generated for)
+        let mut variables = parse_result::<MembershipResult>(result)?.variables;
+        variables.depth = ctx.membership_depth;
+        chip.load_variables(variables);
+        layout_chip(&mut chip, layouter, inputs, None, instance_offsets)
     }
 }
@@ -0,0 +1,398 @@
+use super::model::{NodeType, ParsedNodes};
+use super::utilities::quantized_tensor_to_valtensor;
+use super::Visibility;
+use crate::circuit::poly::PolyOp;
+use crate::circuit::{Constant, Op, Rescaled};
+use crate::graph::scale_to_multiplier;
+use crate::tensor::Tensor;
+use halo2curves::bn256::Fr as Fp;
+use itertools::Itertools;
+use log::debug;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+impl ParsedNodes {
+    /// Runs the graph optimization passes over the parsed nodes, in order:
+    /// constant folding, einsum + bias fusion, merging of chained [Rescaled] ops and dead node elimination.
+    /// # Arguments
+    /// * `param_visibility` - The visibility of the model's params, used for any folded constants.
+    /// * `param_visibilities` - Per-node param visibilities, overriding `param_visibility` for the folded constants of those nodes.
+    pub fn optimize(
+        &mut self,
+        param_visibility: Visibility,
+        param_visibilities: &BTreeMap<usize, Visibility>,
+    ) -> Result<(), Box<dyn Error>> {
+        let num_nodes = self.nodes.len();
+
+        self.fold_constants(param_visibility, param_visibilities)?;
+        self.fuse_einsum_bias();
+        self.merge_rescaled();
+        self.eliminate_dead_nodes();
+
+        for node in self.nodes.values_mut() {
+            if let NodeType::SubGraph { model, .. } = node {
+                model
+                    .graph
+                    .optimize(param_visibility, &model.param_visibilities)?;
+            }
+        }
+
+        debug!(
+            "graph optimization passes reduced {} nodes to {}",
+            num_nodes,
+            self.nodes.len()
+        );
+
+        Ok(())
+    }
+
+    /// Replaces nodes whose inputs are all constants with a single constant holding the node's (quantized) output.
+    /// The folded constant takes the node's entry in `param_visibilities` if there is one, and `param_visibility` otherwise.
+    pub fn fold_constants(
+        &mut self,
+        param_visibility: Visibility,
+        param_visibilities: &BTreeMap<usize, Visibility>,
+    ) -> Result<(), Box<dyn Error>> {
+        let indices = self.nodes.keys().cloned().collect_vec();
+        for idx in indices {
+            let n = match self.nodes.get(&idx) {
+                Some(NodeType::Node(n)) => n,
+                _ => continue,
+            };
+            if n.inputs.is_empty() || n.opkind.is_input() || is_constant(&*n.opkind) {
+                continue;
+            }
+
+            let mut inputs = vec![];
+            for i in &n.inputs {
+                match self.nodes.get(i) {
+                    Some(NodeType::Node(input)) => match constant_values(&*input.opkind) {
+                        Some(t) => inputs.push(t),
+                        None => break,
+                    },
+                    _ => break,
+                }
+            }
+            if inputs.len() != n.inputs.len() {
+                continue;
+            }
+
+            let output = Op::<Fp>::f(&*n.opkind, &inputs)?.output;
+            let multiplier = scale_to_multiplier(n.out_scale) as f32;
+            let raw_values = output.map(|x| x as f32 / multiplier);
+            let visibility = param_visibilities
+                .get(&idx)
+                .cloned()
+                .unwrap_or(param_visibility);
+            let quantized_values = quantized_tensor_to_valtensor(output, n.out_scale, visibility)?;
+
+            debug!("folding {}: {} into a constant", idx, n.opkind.as_string());
+
+            if let Some(NodeType::Node(n)) = self.nodes.get_mut(&idx) {
+                n.opkind = Box::new(Constant::new(quantized_values, raw_values));
+                n.inputs = vec![];
+            }
+        }
+        Ok(())
+    }
+
+    /// Fuses an einsum followed by a constant bias addition into a single [PolyOp::Einsum] with a bias.
+    pub fn fuse_einsum_bias(&mut self) {
+        let consumers = self.num_consumers();
+        let indices = self.nodes.keys().cloned().collect_vec();
+        for idx in indices {
+            let (bias, einsum_idx) = match self.nodes.get(&idx) {
+                Some(NodeType::Node(n)) if n.inputs.len() == 1 => {
+                    match n.opkind.as_any().downcast_ref::<PolyOp<Fp>>() {
+                        Some(PolyOp::Add { a: Some(bias) }) => (bias.clone(), n.inputs[0]),
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+
+            // the einsum output must not be used anywhere else
+            if self.outputs.contains(&einsum_idx) || consumers.get(&einsum_idx) != Some(&1) {
+                continue;
+            }
+
+            let (fused, inputs) = match self.nodes.get(&einsum_idx) {
+                // the bias is added at the einsum's output scale, so we leave mismatched scales unfused
+                Some(NodeType::Node(e)) if e.out_scale != bias.scale() => continue,
+                Some(NodeType::Node(e)) => match with_einsum_bias(&*e.opkind, bias) {
+                    Some(fused) => (fused, e.inputs.clone()),
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            debug!("fusing einsum {} with bias add {}", einsum_idx, idx);
+
+            if let Some(NodeType::Node(n)) = self.nodes.get_mut(&idx) {
+                n.opkind = fused;
+                n.inputs = inputs;
+            }
+            self.nodes.remove(&einsum_idx);
+        }
+    }
+
+    /// Merges [Rescaled] ops that directly wrap another [Rescaled] op into a single rescaling.
+    pub fn merge_rescaled(&mut self) {
+        for node in self.nodes.values_mut() {
+            if let NodeType::Node(n) = node {
+                while let Some(merged) = merge_rescaled_op(&*n.opkind) {
+                    n.opkind = merged;
+                }
+            }
+        }
+    }
+
+    /// Removes nodes that the graph's outputs do not depend on.
+    /// Graphs containing subgraphs are left untouched, as subgraph layouts resolve their inputs by index.
+    pub fn eliminate_dead_nodes(&mut self) {
+        if self
+            .nodes
+            .values()
+            .any(|n| matches!(n, NodeType::SubGraph { .. }))
+        {
+            return;
+        }
+
+        let mut live = BTreeSet::new();
+        let mut stack = self.outputs.clone();
+        // inputs are always kept so the model's inputs line up with the data fed to it
+        stack.extend(self.inputs.iter());
+        while let Some(idx) = stack.pop() {
+            if live.insert(idx) {
+                if let Some(node) = self.nodes.get(&idx) {
+                    stack.extend(node.inputs());
+                }
+            }
+        }
+
+        self.nodes.retain(|idx, _| {
+            let keep = live.contains(idx);
+            if !keep {
+                debug!("removing dead node {}", idx);
+            }
+            keep
+        });
+    }
+
+    /// Counts the number of times each node is used as an input to another node.
+    fn num_consumers(&self) -> BTreeMap<usize, usize> {
+        let mut consumers = BTreeMap::new();
+        for node in self.nodes.values() {
+            for i in node.inputs() {
+                *consumers.entry(i).or_insert(0) += 1;
+            }
+        }
+        consumers
+    }
+}
+
+fn is_constant(op: &dyn Op<Fp>) -> bool {
+    op.as_any().downcast_ref::<Constant<Fp>>().is_some()
+}
+
+fn constant_values(op: &dyn Op<Fp>) -> Option<Tensor<i128>> {
+    let constant = op.as_any().downcast_ref::<Constant<Fp>>()?;
+    let values = &constant.quantized_values;
+    Tensor::new(Some(&values.get_int_evals().ok()?), values.dims()).ok()
+}
+
+/// Returns the einsum op (possibly wrapped in a [Rescaled]) with the bias attached, if it is an einsum without a bias.
+fn with_einsum_bias(
+    op: &dyn Op<Fp>,
+    bias: crate::tensor::ValTensor<Fp>,
+) -> Option<Box<dyn Op<Fp>>> {
+    if let Some(PolyOp::Einsum {
+        equation,
+        bias: None,
+    }) = op.as_any().downcast_ref::<PolyOp<Fp>>()
+    {
+        return Some(Box::new(PolyOp::Einsum {
+            equation: equation.clone(),
+            bias: Some(bias),
+        }));
+    }
+    let rescaled = op.as_any().downcast_ref::<Rescaled<Fp>>()?;
+    Some(Box::new(Rescaled {
+        inner: with_einsum_bias(&*rescaled.inner, bias)?,
+        scale: rescaled.scale.clone(),
+    }))
+}
+
+fn merge_rescaled_op(op: &dyn Op<Fp>) -> Option<Box<dyn Op<Fp>>> {
+    let outer = op.as_any().downcast_ref::<Rescaled<Fp>>()?;
+    let inner = outer.inner.as_any().downcast_ref::<Rescaled<Fp>>()?;
+    // the multipliers can only be combined if both ops rescale the same inputs of an op of the same arity
+    let inputs = |r: &Rescaled<Fp>| r.scale.iter().map(|(i, _)| *i).collect_vec();
+    if inputs(outer) != inputs(inner) {
+        return None;
+    }
+    let scale = outer
+        .scale
+        .iter()
+        .zip(inner.scale.iter())
+        .map(|((i, a), (_, b))| (*i, a * b))
+        .collect_vec();
+    Some(Box::new(Rescaled {
+        inner: inner.inner.clone(),
+        scale,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::Input;
+    use crate::graph::model::Model;
+    use crate::graph::Node;
+    use crate::tensor::ValType;
+
+    fn node(idx: usize, opkind: Box<dyn Op<Fp>>, inputs: Vec<usize>) -> NodeType {
+        NodeType::Node(Node {
+            opkind,
+            out_scale: 0,
+            inputs,
+            out_dims: vec![2],
+            idx,
+        })
+    }
+
+    fn constant(values: &[i128]) -> Box<dyn Op<Fp>> {
+        let t = Tensor::new(Some(values), &[values.len()]).unwrap();
+        Box::new(Constant::new(
+            quantized_tensor_to_valtensor(t.clone(), 0, Visibility::Private).unwrap(),
+            t.map(|x| x as f32),
+        ))
+    }
+
+    #[test]
+    fn folds_constants_and_removes_dead_nodes() {
+        let mut graph = ParsedNodes {
+            nodes: BTreeMap::from([
+                (0, node(0, Box::new(Input { scale: 0 }), vec![])),
+                (1, node(1, constant(&[1, 2]), vec![])),
+                (2, node(2, constant(&[3, 4]), vec![])),
+                (3, node(3, Box::new(PolyOp::Add { a: None }), vec![1, 2])),
+                (4, node(4, Box::new(PolyOp::Add { a: None }), vec![0, 3])),
+                (5, node(5, Box::new(PolyOp::Identity), vec![0])),
+            ]),
+            inputs: vec![0],
+            outputs: vec![4],
+        };
+        graph
+            .optimize(Visibility::Private, &BTreeMap::new())
+            .unwrap();
+
+        let model = Model {
+            graph,
+            ..Default::default()
+        };
+        assert_eq!(model.graph.nodes.len(), 3);
+        let inputs = [Tensor::new(Some(&[10, 20]), &[2]).unwrap()];
+        let res = model.forward(&inputs).unwrap();
+        assert_eq!(res.outputs[0], Tensor::new(Some(&[14, 26]), &[2]).unwrap());
+    }
+
+    #[test]
+    fn folded_constants_take_the_node_param_visibility() {
+        let mut graph = ParsedNodes {
+            nodes: BTreeMap::from([
+                (0, node(0, Box::new(Input { scale: 0 }), vec![])),
+                (1, node(1, constant(&[1, 2]), vec![])),
+                (2, node(2, Box::new(PolyOp::Identity), vec![1])),
+                (3, node(3, Box::new(PolyOp::Identity), vec![1])),
+                (4, node(4, Box::new(PolyOp::Add { a: None }), vec![2, 3])),
+                (5, node(5, Box::new(PolyOp::Add { a: None }), vec![0, 4])),
+            ]),
+            inputs: vec![0],
+            outputs: vec![5],
+        };
+        graph
+            .fold_constants(
+                Visibility::Private,
+                &BTreeMap::from([(4, Visibility::Public)]),
+            )
+            .unwrap();
+
+        let is_fixed = |idx: usize| match graph.nodes.get(&idx) {
+            Some(NodeType::Node(n)) => {
+                let constant = n.opkind.as_any().downcast_ref::<Constant<Fp>>().unwrap();
+                let inner = constant.quantized_values.get_inner_tensor().unwrap();
+                inner.iter().all(|v| matches!(v, ValType::Constant(_)))
+            }
+            _ => panic!("node {} is missing", idx),
+        };
+        assert!(!is_fixed(2));
+        assert!(is_fixed(4));
+    }
+
+    #[test]
+    fn leaves_bias_with_mismatched_scale_unfused() {
+        let bias = quantized_tensor_to_valtensor(
+            Tensor::new(Some(&[1, 2]), &[2]).unwrap(),
+            1,
+            Visibility::Private,
+        )
+        .unwrap();
+        let einsum = PolyOp::Einsum {
+            equation: "i->i".to_string(),
+            bias: None,
+        };
+        let mut graph = ParsedNodes {
+            nodes: BTreeMap::from([
+                (0, node(0, Box::new(Input { scale: 0 }), vec![])),
+                (1, node(1, Box::new(einsum), vec![0])),
+                (2, node(2, Box::new(PolyOp::Add { a: Some(bias) }), vec![1])),
+            ]),
+            inputs: vec![0],
+            outputs: vec![2],
+        };
+        graph.fuse_einsum_bias();
+        assert_eq!(graph.nodes.len(), 3);
+    }
+
+    #[test]
+    fn merges_chained_rescaled() {
+        let op: Box<dyn Op<Fp>> = Box::new(Rescaled {
+            inner: Box::new(Rescaled {
+                inner: Box::new(PolyOp::Identity),
+                scale: vec![(0, 2)],
+            }),
+            scale: vec![(0, 4)],
+        });
+        let merged = merge_rescaled_op(&*op).unwrap();
+        let merged = merged.as_any().downcast_ref::<Rescaled<Fp>>().unwrap();
+        assert_eq!(merged.scale, vec![(0, 8)]);
+        assert!(merged
+            .inner
+            .as_any()
+            .downcast_ref::<Rescaled<Fp>>()
+            .is_none());
+    }
+
+    #[test]
+    fn keeps_rescaled_ops_of_different_arity_apart() {
+        let chained = |outer: Vec<(usize, u128)>, inner: Vec<(usize, u128)>| -> Box<dyn Op<Fp>> {
+            Box::new(Rescaled {
+                inner: Box::new(Rescaled {
+                    inner: Box::new(PolyOp::Add { a: None }),
+                    scale: inner,
+                }),
+                scale: outer,
+            })
+        };
+        assert!(merge_rescaled_op(&*chained(vec![(0, 4)], vec![(0, 2), (1, 2)])).is_none());
+        assert!(merge_rescaled_op(&*chained(vec![(0, 4), (1, 2)], vec![(0, 2)])).is_none());
+        // the same arity but different inputs
+        assert!(merge_rescaled_op(&*chained(vec![(1, 4)], vec![(0, 2)])).is_none());
+
+        let merged =
+            merge_rescaled_op(&*chained(vec![(0, 4), (1, 2)], vec![(0, 2), (1, 3)])).unwrap();
+        let merged = merged.as_any().downcast_ref::<Rescaled<Fp>>().unwrap();
+        assert_eq!(merged.scale, vec![(0, 8), (1, 6)]);
+    }
+}
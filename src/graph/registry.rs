@@ -0,0 +1,687 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use halo2_proofs::circuit::{Cell, Layouter, Region, Table, Value};
+use halo2_proofs::plonk::{Challenge, Column, ConstraintSystem, Error as PlonkError, Instance};
+use halo2curves::bn256::Fr as Fp;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::circuit::modules::elgamal::{ElGamalKeypair, ElGamalRecipient};
+use crate::circuit::modules::poseidon::cipher::PoseidonCipherKey;
+use crate::circuit::modules::poseidon::membership::DatasetTree;
+use crate::circuit::modules::schnorr::SchnorrSignature;
+use crate::commands::HashMode;
+use crate::tensor::ValTensor;
+
+use super::modules::{
+    CipherModule, ElGamalModule, MembershipModule, ModuleSizes, PoseidonModule, SchnorrModule,
+    BUILTIN_MODULES,
+};
+use super::{GraphError, GraphSettings, VarVisibility};
+
+/// The visibility names that cannot be used to register a module under.
+const RESERVED_NAMES: [&str; 8] = [
+    "private",
+    "public",
+    "hashed",
+    "encrypted",
+    "kzgcommit",
+    "symencrypted",
+    "signed",
+    "member",
+];
+
+/// The maximum length of the name of a user-defined module.
+pub const MAX_MODULE_NAME_LEN: usize = 32;
+
+/// The settings the modules of a circuit are laid out with (and its verifying key depends on).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ModuleContext {
+    /// Which Poseidon module hashed values are laid out with
+    pub hash_mode: HashMode,
+    /// Who encrypted values are encrypted to
+    pub elgamal_recipient: ElGamalRecipient,
+    /// The depth of the dataset tree member values belong to
+    pub membership_depth: usize,
+}
+
+impl From<&GraphSettings> for ModuleContext {
+    fn from(settings: &GraphSettings) -> Self {
+        ModuleContext {
+            hash_mode: settings.run_args.hash_mode,
+            elgamal_recipient: settings.elgamal_recipient,
+            membership_depth: settings.run_args.membership_depth,
+        }
+    }
+}
+
+/// What the prover runs the modules with, besides the values of the tensors.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProverInputs<'a> {
+    /// The keypair encrypted values are encrypted to if the prover is the recipient (a fresh one otherwise)
+    pub elgamal_keypair: Option<&'a ElGamalKeypair>,
+    /// The key symencrypted values are encrypted under (a fresh one otherwise)
+    pub cipher_key: Option<&'a PoseidonCipherKey>,
+    /// The signature of each signed tensor, in order
+    pub signatures: &'a [SchnorrSignature],
+    /// The dataset member tensors are rows of
+    pub dataset: Option<&'a DatasetTree>,
+}
+
+/// A module processing the inputs, params or outputs of a circuit with a given visibility, e.g hashing them or
+/// encrypting them. The built-in modules process the hashed, encrypted, symencrypted, signed and member values, and
+/// user-defined modules (e.g a custom commitment or an attestation over the values of a tensor) the values with a
+/// `custom:<name>` visibility.
+///
+/// Unlike [crate::circuit::modules::Module], the trait is object safe such that modules can be added to a
+/// [ModuleRegistry] from other crates. The tensors sharing a visibility among the inputs (resp. the params, the
+/// outputs) are processed together, as a group. Each module is laid out in its own module space of the
+/// [crate::circuit::modules::ModulePlanner], and the modules' instance columns are ordered by module name.
+pub trait GraphModule: fmt::Debug + Send + Sync {
+    /// The name the module is registered under, and selected with (as `custom:<name>`).
+    fn name(&self) -> &'static str;
+    /// The number of instance columns the module configures.
+    fn num_instance_columns(&self) -> usize;
+    /// Configures the module's columns and gates, returning its config.
+    fn configure(&self, cs: &mut ConstraintSystem<Fp>) -> Arc<dyn Any + Send + Sync>;
+    /// The number of values each of the module's instance columns holds for a group of tensors of `lens` elements.
+    fn num_instances(&self, lens: &[usize], ctx: &ModuleContext) -> Vec<usize>;
+    /// Runs the module on the (quantized) elements of a group of tensors, returning the result stored in the
+    /// witness under the module's name.
+    fn run(
+        &self,
+        inputs: &[Vec<Fp>],
+        ctx: &ModuleContext,
+        prover: &ProverInputs,
+    ) -> Result<serde_json::Value, Box<dyn Error>>;
+    /// The values of each of the module's instance columns, given the result of [GraphModule::run].
+    fn instances(&self, result: &serde_json::Value) -> Result<Vec<Vec<Fp>>, Box<dyn Error>>;
+    /// Lays out a group of tensors, constraining the module's instances from `instance_offsets` on (and advancing
+    /// them past the instances it uses), and returns the assigned tensors (whose cells are constrained to equal the
+    /// tensors' in the rest of the circuit). `result` is the result returned by [GraphModule::run], which is `None`
+    /// when the circuit is laid out without witnesses.
+    fn layout(
+        &self,
+        config: &(dyn Any + Send + Sync),
+        layouter: &mut dyn DynLayouter,
+        inputs: &[ValTensor<Fp>],
+        result: Option<&serde_json::Value>,
+        instance_offsets: &mut [usize],
+        ctx: &ModuleContext,
+    ) -> Result<Vec<ValTensor<Fp>>, PlonkError>;
+}
+
+/// The modules a circuit can be laid out with, keyed by name: the built-in modules, followed by any user-defined ones.
+/// A circuit's registry is held by its [GraphSettings] but isn't serialized, such that user-defined modules must be
+/// registered (under the same names) wherever settings, witnesses or keys using them are loaded. User-defined modules
+/// are thus library-only: the CLI and the Python bindings only know of the built-in modules, and reject settings
+/// whose modules aren't all built-in.
+#[derive(Clone)]
+pub struct ModuleRegistry {
+    modules: BTreeMap<&'static str, Arc<dyn GraphModule>>,
+}
+
+impl Default for ModuleRegistry {
+    fn default() -> Self {
+        let builtins: [Arc<dyn GraphModule>; 5] = [
+            Arc::new(PoseidonModule),
+            Arc::new(ElGamalModule),
+            Arc::new(CipherModule),
+            Arc::new(SchnorrModule),
+            Arc::new(MembershipModule),
+        ];
+        ModuleRegistry {
+            modules: builtins.into_iter().map(|m| (m.name(), m)).collect(),
+        }
+    }
+}
+
+impl ModuleRegistry {
+    /// Registers a module, such that inputs, params and outputs can be given a `custom:<name>` visibility.
+    pub fn register(&mut self, module: Arc<dyn GraphModule>) -> Result<(), GraphError> {
+        let name = ModuleName::new(module.name())?;
+        if self.modules.contains_key(name.as_str()) {
+            return Err(GraphError::ModuleAlreadyRegistered(name.to_string()));
+        }
+        self.modules.insert(module.name(), module);
+        Ok(())
+    }
+
+    /// The module registered as `name`.
+    pub fn get(&self, name: &str) -> Result<&Arc<dyn GraphModule>, GraphError> {
+        self.modules
+            .get(name)
+            .ok_or_else(|| GraphError::UnregisteredModule(name.to_string()))
+    }
+
+    /// The names of the registered modules, in order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.modules.keys().copied()
+    }
+
+    /// Checks that the modules the `custom:<name>` visibilities select are registered.
+    pub fn check(&self, visibility: &VarVisibility) -> Result<(), GraphError> {
+        for module in visibility.custom_modules() {
+            self.get(module.as_str())?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the modules configured according to `module_sizes` are registered.
+    pub fn check_sizes(&self, module_sizes: &ModuleSizes) -> Result<(), GraphError> {
+        for name in module_sizes.configured() {
+            self.get(name)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ModuleRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+// registries are compared by the names of their modules, as settings are
+impl PartialEq for ModuleRegistry {
+    fn eq(&self, other: &Self) -> bool {
+        self.names().eq(other.names())
+    }
+}
+
+/// The name of a user-defined module, as selected by a `custom:<name>` visibility: an identifier of at most
+/// [MAX_MODULE_NAME_LEN] ascii characters, which isn't the name of a built-in visibility or module.
+// the name is stored inline such that visibilities stay `Copy`, and zero-padded such that names are ordered alphabetically
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModuleName {
+    bytes: [u8; MAX_MODULE_NAME_LEN],
+    len: u8,
+}
+
+impl ModuleName {
+    /// Checks that `name` can be given to a user-defined module.
+    pub fn new(name: &str) -> Result<Self, GraphError> {
+        let is_identifier = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if name.is_empty()
+            || name.len() > MAX_MODULE_NAME_LEN
+            || !is_identifier
+            || RESERVED_NAMES.contains(&name)
+            || BUILTIN_MODULES.contains(&name)
+        {
+            return Err(GraphError::ReservedModuleName(name.to_string()));
+        }
+        let mut bytes = [0; MAX_MODULE_NAME_LEN];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Ok(ModuleName {
+            bytes,
+            len: name.len() as u8,
+        })
+    }
+
+    /// The name, as a string.
+    pub fn as_str(&self) -> &str {
+        // the bytes were copied from a string
+        std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ModuleName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Debug for ModuleName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for ModuleName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ModuleName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        ModuleName::new(&name).map_err(serde::de::Error::custom)
+    }
+}
+
+/// An object safe counterpart of [Layouter], which [GraphModule]s are laid out with.
+/// [ErasedLayouter] turns it back into a [Layouter], such that the gadgets of a module can be laid out with it.
+pub trait DynLayouter {
+    /// See [Layouter::assign_region].
+    fn assign_region_dyn(
+        &mut self,
+        name: &dyn Fn() -> String,
+        assignment: &mut dyn FnMut(Region<'_, Fp>) -> Result<(), PlonkError>,
+    ) -> Result<(), PlonkError>;
+    /// See [Layouter::assign_table].
+    fn assign_table_dyn(
+        &mut self,
+        name: &dyn Fn() -> String,
+        assignment: &mut dyn FnMut(Table<'_, Fp>) -> Result<(), PlonkError>,
+    ) -> Result<(), PlonkError>;
+    /// See [Layouter::constrain_instance].
+    fn constrain_instance_dyn(
+        &mut self,
+        cell: Cell,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<(), PlonkError>;
+    /// See [Layouter::get_challenge].
+    fn get_challenge_dyn(&self, challenge: Challenge) -> Value<Fp>;
+    /// See [Layouter::push_namespace].
+    fn push_namespace_dyn(&mut self, name: String);
+    /// See [Layouter::pop_namespace].
+    fn pop_namespace_dyn(&mut self, gadget_name: Option<String>);
+}
+
+impl<L: Layouter<Fp>> DynLayouter for L {
+    fn assign_region_dyn(
+        &mut self,
+        name: &dyn Fn() -> String,
+        assignment: &mut dyn FnMut(Region<'_, Fp>) -> Result<(), PlonkError>,
+    ) -> Result<(), PlonkError> {
+        self.assign_region(name, |region| assignment(region))
+    }
+
+    fn assign_table_dyn(
+        &mut self,
+        name: &dyn Fn() -> String,
+        assignment: &mut dyn FnMut(Table<'_, Fp>) -> Result<(), PlonkError>,
+    ) -> Result<(), PlonkError> {
+        self.assign_table(name, |table| assignment(table))
+    }
+
+    fn constrain_instance_dyn(
+        &mut self,
+        cell: Cell,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<(), PlonkError> {
+        self.constrain_instance(cell, column, row)
+    }
+
+    fn get_challenge_dyn(&self, challenge: Challenge) -> Value<Fp> {
+        self.get_challenge(challenge)
+    }
+
+    fn push_namespace_dyn(&mut self, name: String) {
+        self.push_namespace(|| name)
+    }
+
+    fn pop_namespace_dyn(&mut self, gadget_name: Option<String>) {
+        self.pop_namespace(gadget_name)
+    }
+}
+
+/// A [Layouter] wrapping a [DynLayouter].
+pub struct ErasedLayouter<'a> {
+    inner: &'a mut dyn DynLayouter,
+}
+
+impl<'a> ErasedLayouter<'a> {
+    /// Wraps a [DynLayouter].
+    pub fn new(inner: &'a mut dyn DynLayouter) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a> Layouter<Fp> for ErasedLayouter<'a> {
+    type Root = Self;
+
+    fn assign_region<A, AR, N, NR>(&mut self, name: N, mut assignment: A) -> Result<AR, PlonkError>
+    where
+        A: FnMut(Region<'_, Fp>) -> Result<AR, PlonkError>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        // the assignment may be called more than once (e.g to measure the shape of the region), we return the last result
+        let mut result = None;
+        self.inner
+            .assign_region_dyn(&|| name().into(), &mut |region| {
+                result = Some(assignment(region)?);
+                Ok(())
+            })?;
+        result.ok_or(PlonkError::Synthesis)
+    }
+
+    fn assign_table<A, N, NR>(&mut self, name: N, mut assignment: A) -> Result<(), PlonkError>
+    where
+        A: FnMut(Table<'_, Fp>) -> Result<(), PlonkError>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        self.inner
+            .assign_table_dyn(&|| name().into(), &mut |table| assignment(table))
+    }
+
+    fn constrain_instance(
+        &mut self,
+        cell: Cell,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<(), PlonkError> {
+        self.inner.constrain_instance_dyn(cell, column, row)
+    }
+
+    fn get_challenge(&self, challenge: Challenge) -> Value<Fp> {
+        self.inner.get_challenge_dyn(challenge)
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.push_namespace_dyn(name_fn().into())
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.inner.pop_namespace_dyn(gadget_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::modules::{GraphModules, ModuleConfigs, POSEIDON};
+    use crate::graph::Visibility;
+    use crate::tensor::{Tensor, ValType};
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Advice, Circuit},
+    };
+    use halo2curves::ff::Field;
+
+    /// A module exposing the elements of each tensor as instances
+    #[derive(Debug)]
+    struct Exposed(&'static str);
+
+    #[derive(Clone, Debug)]
+    struct ExposedConfig {
+        advice: Column<Advice>,
+        instance: Column<Instance>,
+    }
+
+    impl GraphModule for Exposed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn num_instance_columns(&self) -> usize {
+            1
+        }
+
+        fn configure(&self, cs: &mut ConstraintSystem<Fp>) -> Arc<dyn Any + Send + Sync> {
+            let advice = cs.advice_column();
+            let instance = cs.instance_column();
+            cs.enable_equality(advice);
+            cs.enable_equality(instance);
+            Arc::new(ExposedConfig { advice, instance })
+        }
+
+        fn num_instances(&self, lens: &[usize], _: &ModuleContext) -> Vec<usize> {
+            vec![lens.iter().sum()]
+        }
+
+        fn run(
+            &self,
+            inputs: &[Vec<Fp>],
+            _: &ModuleContext,
+            _: &ProverInputs,
+        ) -> Result<serde_json::Value, Box<dyn Error>> {
+            Ok(serde_json::to_value(inputs.concat())?)
+        }
+
+        fn instances(&self, result: &serde_json::Value) -> Result<Vec<Vec<Fp>>, Box<dyn Error>> {
+            Ok(vec![serde_json::from_value(result.clone())?])
+        }
+
+        fn layout(
+            &self,
+            config: &(dyn Any + Send + Sync),
+            layouter: &mut dyn DynLayouter,
+            inputs: &[ValTensor<Fp>],
+            _: Option<&serde_json::Value>,
+            instance_offsets: &mut [usize],
+            _: &ModuleContext,
+        ) -> Result<Vec<ValTensor<Fp>>, PlonkError> {
+            let config = config
+                .downcast_ref::<ExposedConfig>()
+                .ok_or(PlonkError::Synthesis)?;
+            let mut layouter = ErasedLayouter::new(layouter);
+            let mut outputs = vec![];
+            for input in inputs {
+                let values = input.get_inner().map_err(|_| PlonkError::Synthesis)?;
+                let cells = layouter.assign_region(
+                    || "exposed",
+                    |mut region| {
+                        values
+                            .iter()
+                            .enumerate()
+                            .map(|(i, v)| region.assign_advice(|| "value", config.advice, i, || *v))
+                            .collect::<Result<Vec<_>, _>>()
+                    },
+                )?;
+                for (i, cell) in cells.iter().enumerate() {
+                    layouter.constrain_instance(
+                        cell.cell(),
+                        config.instance,
+                        instance_offsets[0] + i,
+                    )?;
+                }
+                instance_offsets[0] += cells.len();
+                let mut output: ValTensor<Fp> = cells
+                    .into_iter()
+                    .map(ValType::PrevAssigned)
+                    .collect::<Tensor<_>>()
+                    .into();
+                output
+                    .reshape(input.dims())
+                    .map_err(|_| PlonkError::Synthesis)?;
+                outputs.push(output);
+            }
+            Ok(outputs)
+        }
+    }
+
+    struct ExposedCircuit {
+        module: Arc<dyn GraphModule>,
+        inputs: Vec<ValTensor<Fp>>,
+    }
+
+    impl Circuit<Fp> for ExposedCircuit {
+        type Config = Arc<dyn Any + Send + Sync>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                module: self.module.clone(),
+                inputs: self.inputs.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            Exposed("exposed").configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), PlonkError> {
+            self.module.layout(
+                config.as_ref(),
+                &mut layouter,
+                &self.inputs,
+                None,
+                &mut [0],
+                &ModuleContext::default(),
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_visibility_selects_module_by_name() {
+        let visibility = Visibility::from("custom:selected");
+        assert_eq!(
+            visibility,
+            Visibility::Custom(ModuleName::new("selected").unwrap())
+        );
+        assert_eq!(visibility.to_string(), "custom:selected");
+        assert_eq!(visibility.module_name(), Some("selected"));
+        assert_eq!(Visibility::Hashed.module_name(), Some(POSEIDON));
+
+        // visibilities are deserialized whichever modules are registered
+        let serialized = serde_json::to_string(&visibility).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Visibility>(&serialized).unwrap(),
+            visibility
+        );
+
+        // but a circuit can only use registered modules
+        let var_visibility = VarVisibility {
+            input: visibility,
+            ..Default::default()
+        };
+        let mut registry = ModuleRegistry::default();
+        assert!(registry.check(&var_visibility).is_err());
+        registry.register(Arc::new(Exposed("selected"))).unwrap();
+        assert!(registry.check(&var_visibility).is_ok());
+        assert_eq!(registry.get("selected").unwrap().name(), "selected");
+        assert!(registry.get("unregistered").is_err());
+    }
+
+    #[test]
+    fn reserved_module_names_are_rejected() {
+        let mut registry = ModuleRegistry::default();
+        for name in [
+            "", "hashed", "public", "member", "poseidon", "elgamal", "a b",
+        ] {
+            assert!(registry.register(Arc::new(Exposed(name))).is_err());
+        }
+        const LONG_NAME: &str = "a_name_longer_than_thirty_two_chars";
+        assert!(LONG_NAME.len() > MAX_MODULE_NAME_LEN);
+        assert!(registry.register(Arc::new(Exposed(LONG_NAME))).is_err());
+
+        // a name can only be registered once
+        registry.register(Arc::new(Exposed("twice"))).unwrap();
+        assert!(registry.register(Arc::new(Exposed("twice"))).is_err());
+    }
+
+    #[test]
+    fn module_names_are_ordered_alphabetically() {
+        let names = ["b", "ab", "abc", "a"].map(|n| ModuleName::new(n).unwrap());
+        let mut sorted = names.to_vec();
+        sorted.sort();
+        assert_eq!(
+            sorted.iter().map(|n| n.as_str()).collect::<Vec<_>>(),
+            vec!["a", "ab", "abc", "b"]
+        );
+    }
+
+    #[test]
+    fn custom_module_instances_follow_the_builtins_by_name() {
+        let mut registry = ModuleRegistry::default();
+        registry.register(Arc::new(Exposed("exposed"))).unwrap();
+        let visibility = VarVisibility {
+            input: Visibility::from("custom:exposed"),
+            output: Visibility::Hashed,
+            ..Default::default()
+        };
+        let sizes = GraphModules::num_constraints_and_instances(
+            &registry,
+            vec![vec![3], vec![2]],
+            vec![],
+            vec![vec![4]],
+            visibility,
+            &ModuleContext::default(),
+        )
+        .unwrap();
+        // "exposed" comes before "poseidon"
+        assert_eq!(sizes.num_instances(), vec![5, 1]);
+        assert_eq!(sizes.instance_columns("exposed"), Some(0..1));
+        assert_eq!(sizes.instance_columns(POSEIDON), Some(1..2));
+        assert_eq!(sizes.output_hash_positions(), 0..1);
+
+        // configuring a circuit requires its modules to be registered
+        let mut cs = ConstraintSystem::<Fp>::default();
+        assert!(ModuleRegistry::default().check_sizes(&sizes).is_err());
+        assert!(ModuleConfigs::configure(
+            &mut cs,
+            &ModuleRegistry::default(),
+            &sizes,
+            ModuleContext::default()
+        )
+        .is_err());
+        assert!(registry.check_sizes(&sizes).is_ok());
+        let configs =
+            ModuleConfigs::configure(&mut cs, &registry, &sizes, ModuleContext::default()).unwrap();
+        assert_eq!(configs.len(), 2);
+
+        // sizing a circuit requires its modules to be registered
+        assert!(GraphModules::num_constraints_and_instances(
+            &ModuleRegistry::default(),
+            vec![vec![3]],
+            vec![],
+            vec![vec![4]],
+            VarVisibility {
+                input: Visibility::from("custom:exposed"),
+                output: Visibility::Hashed,
+                ..Default::default()
+            },
+            &ModuleContext::default(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn custom_module_lays_out_through_erased_layouter() {
+        let mut registry = ModuleRegistry::default();
+        registry.register(Arc::new(Exposed("exposed"))).unwrap();
+        let module = registry.get("exposed").unwrap().clone();
+
+        let values = [(1..=4u64), (5..=6u64)]
+            .map(|r| r.map(Fp::from).collect::<Vec<_>>())
+            .to_vec();
+        let res = module
+            .run(&values, &ModuleContext::default(), &ProverInputs::default())
+            .unwrap();
+        let instances = module.instances(&res).unwrap();
+        assert_eq!(instances, vec![values.concat()]);
+
+        let inputs = values
+            .iter()
+            .map(|v| {
+                let t: Tensor<Value<Fp>> = v.iter().map(|v| Value::known(*v)).collect();
+                t.into()
+            })
+            .collect();
+        let circuit = ExposedCircuit { module, inputs };
+
+        let prover = MockProver::run(5, &circuit, instances.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut tampered = instances;
+        tampered[0][5] += Fp::ONE;
+        let prover = MockProver::run(5, &circuit, tampered).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -424,6 +424,7 @@ pub fn new_op_from_onnx(
             let axes = &op.axes;
             Box::new(PolyOp::Einsum {
                 equation: axes.to_string(),
+                bias: None,
             })
         }
         "Softmax" => {
@@ -777,7 +778,14 @@ pub fn tensor_to_valtensor<F: PrimeField + TensorType + PartialOrd>(
                 ))
             })
             .into(),
-        Visibility::Private | Visibility::Hashed | Visibility::Encrypted => const_value
+        Visibility::Private
+        | Visibility::Hashed
+        | Visibility::Encrypted
+        | Visibility::SymEncrypted
+        | Visibility::KZGCommit
+        | Visibility::Signed
+        | Visibility::Member
+        | Visibility::Custom(_) => const_value
             .map(|x| {
                 crate::tensor::ValType::Value(halo2_proofs::circuit::Value::known(
                     crate::fieldutils::i128_to_felt::<F>(quantize_float(&x, 0.0, scale).unwrap()),
@@ -789,6 +797,35 @@ pub fn tensor_to_valtensor<F: PrimeField + TensorType + PartialOrd>(
     Ok(value)
 }
 
+/// Converts an already quantized tensor to a [ValTensor] with a given scale.
+pub fn quantized_tensor_to_valtensor<F: PrimeField + TensorType + PartialOrd>(
+    quantized_value: Tensor<i128>,
+    scale: u32,
+    visibility: Visibility,
+) -> Result<ValTensor<F>, Box<dyn std::error::Error>> {
+    let mut value: ValTensor<F> = match visibility {
+        Visibility::Public => quantized_value
+            .map(|x| crate::tensor::ValType::Constant(crate::fieldutils::i128_to_felt::<F>(x)))
+            .into(),
+        Visibility::Private
+        | Visibility::Hashed
+        | Visibility::Encrypted
+        | Visibility::SymEncrypted
+        | Visibility::KZGCommit
+        | Visibility::Signed
+        | Visibility::Member
+        | Visibility::Custom(_) => quantized_value
+            .map(|x| {
+                crate::tensor::ValType::Value(halo2_proofs::circuit::Value::known(
+                    crate::fieldutils::i128_to_felt::<F>(x),
+                ))
+            })
+            .into(),
+    };
+    value.set_scale(scale);
+    Ok(value)
+}
+
 /// Flatten a vector of [ValTensor]s into a single [ValTensor].
 pub(crate) fn flatten_valtensors(
     tensors: Vec<ValTensor<Fp>>,
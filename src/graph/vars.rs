@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::error::Error;
 
 use crate::commands::RunArgs;
@@ -14,6 +15,8 @@ use pyo3::{
 
 use serde::{Deserialize, Serialize};
 
+use super::modules::{CIPHER, ELGAMAL, MEMBERSHIP, POSEIDON, SCHNORR};
+use super::registry::ModuleName;
 use super::*;
 
 /// Label enum to track whether model input, model parameters, and model output are public, private, or hashed
@@ -22,12 +25,23 @@ pub enum Visibility {
     /// Mark an item as private to the prover (not in the proof submitted for verification)
     #[default]
     Private,
-    /// Mark an item as public (sent in the proof submitted for verification)
+    /// Mark an item as public (sent in the proof submitted for verification). Public params are assigned as constants, i.e. to
+    /// the fixed constants column, whose commitment is part of the verifying key (so a vk identifies the model's weights).
     Public,
     /// Mark an item as publicly committed to (hash sent in the proof submitted for verification)
     Hashed,
     /// Mark an item as encrypted (public key and encrypted message sent in the proof submitted for verificatio)
     Encrypted,
+    /// Mark an item as committed to (assigned to a dedicated unblinded advice column, whose KZG commitment is part of the proof). Only applies to inputs and outputs.
+    KZGCommit,
+    /// Mark an item as encrypted with a Poseidon stream cipher under a shared key (key hash, nonce and encrypted message sent in the proof submitted for verification)
+    SymEncrypted,
+    /// Mark an input as signed (verified against a Schnorr signature over its Poseidon hash, whose public key is sent in the proof submitted for verification). Only applies to inputs.
+    Signed,
+    /// Mark an input as a member of a committed dataset (proven to be a row of the dataset whose Merkle root is sent in the proof submitted for verification). Only applies to inputs.
+    Member,
+    /// Mark an item as processed by a user-defined module (see [crate::graph::registry::ModuleRegistry::register]), whose instances are sent in the proof submitted for verification
+    Custom(ModuleName),
 }
 
 impl<'a> From<&'a str> for Visibility {
@@ -37,7 +51,17 @@ impl<'a> From<&'a str> for Visibility {
             "public" => Visibility::Public,
             "hashed" => Visibility::Hashed,
             "encrypted" => Visibility::Encrypted,
-            _ => panic!("Invalid visibility string"),
+            "kzgcommit" => Visibility::KZGCommit,
+            "symencrypted" => Visibility::SymEncrypted,
+            "signed" => Visibility::Signed,
+            "member" => Visibility::Member,
+            _ => match s
+                .strip_prefix("custom:")
+                .and_then(|n| ModuleName::new(n).ok())
+            {
+                Some(module) => Visibility::Custom(module),
+                None => panic!("Invalid visibility string"),
+            },
         }
     }
 }
@@ -51,6 +75,11 @@ impl IntoPy<PyObject> for Visibility {
             Visibility::Public => "public".to_object(py),
             Visibility::Hashed => "hashed".to_object(py),
             Visibility::Encrypted => "encrypted".to_object(py),
+            Visibility::KZGCommit => "kzgcommit".to_object(py),
+            Visibility::SymEncrypted => "symencrypted".to_object(py),
+            Visibility::Signed => "signed".to_object(py),
+            Visibility::Member => "member".to_object(py),
+            Visibility::Custom(module) => format!("custom:{}", module).to_object(py),
         }
     }
 }
@@ -66,7 +95,17 @@ impl<'source> FromPyObject<'source> for Visibility {
             "public" => Ok(Visibility::Public),
             "hashed" => Ok(Visibility::Hashed),
             "encrypted" => Ok(Visibility::Encrypted),
-            _ => Err(PyValueError::new_err("Invalid value for Visibility")),
+            "kzgcommit" => Ok(Visibility::KZGCommit),
+            "symencrypted" => Ok(Visibility::SymEncrypted),
+            "signed" => Ok(Visibility::Signed),
+            "member" => Ok(Visibility::Member),
+            s => match s
+                .strip_prefix("custom:")
+                .and_then(|n| ModuleName::new(n).ok())
+            {
+                Some(module) => Ok(Visibility::Custom(module)),
+                None => Err(PyValueError::new_err("Invalid value for Visibility")),
+            },
         }
     }
 }
@@ -85,8 +124,45 @@ impl Visibility {
         matches!(&self, Visibility::Encrypted)
     }
     #[allow(missing_docs)]
+    pub fn is_kzgcommit(&self) -> bool {
+        matches!(&self, Visibility::KZGCommit)
+    }
+    #[allow(missing_docs)]
+    pub fn is_symencrypted(&self) -> bool {
+        matches!(&self, Visibility::SymEncrypted)
+    }
+    #[allow(missing_docs)]
+    pub fn is_signed(&self) -> bool {
+        matches!(&self, Visibility::Signed)
+    }
+    #[allow(missing_docs)]
+    pub fn is_member(&self) -> bool {
+        matches!(&self, Visibility::Member)
+    }
+    #[allow(missing_docs)]
+    pub fn is_custom(&self) -> bool {
+        matches!(&self, Visibility::Custom(_))
+    }
+    #[allow(missing_docs)]
     pub fn requires_processing(&self) -> bool {
-        matches!(&self, Visibility::Encrypted) | matches!(&self, Visibility::Hashed)
+        matches!(&self, Visibility::Encrypted)
+            | matches!(&self, Visibility::Hashed)
+            | matches!(&self, Visibility::SymEncrypted)
+            | matches!(&self, Visibility::Signed)
+            | matches!(&self, Visibility::Member)
+            | matches!(&self, Visibility::Custom(_))
+    }
+    /// The name of the module (see [crate::graph::registry::ModuleRegistry]) processing items with this visibility, if any
+    pub fn module_name(&self) -> Option<&str> {
+        match self {
+            Visibility::Hashed => Some(POSEIDON),
+            Visibility::Encrypted => Some(ELGAMAL),
+            Visibility::SymEncrypted => Some(CIPHER),
+            Visibility::Signed => Some(SCHNORR),
+            Visibility::Member => Some(MEMBERSHIP),
+            Visibility::Custom(module) => Some(module.as_str()),
+            _ => None,
+        }
     }
 }
 impl std::fmt::Display for Visibility {
@@ -96,6 +172,11 @@ impl std::fmt::Display for Visibility {
             Visibility::Public => write!(f, "public"),
             Visibility::Hashed => write!(f, "hashed"),
             Visibility::Encrypted => write!(f, "encrypted"),
+            Visibility::KZGCommit => write!(f, "kzgcommit"),
+            Visibility::SymEncrypted => write!(f, "symencrypted"),
+            Visibility::Signed => write!(f, "signed"),
+            Visibility::Member => write!(f, "member"),
+            Visibility::Custom(module) => write!(f, "custom:{}", module),
         }
     }
 }
@@ -109,6 +190,15 @@ pub struct VarVisibility {
     pub params: Visibility,
     /// Output of the model or computational graph
     pub output: Visibility,
+    /// Per-input visibilities, overriding `input` for the inputs they cover
+    #[serde(default)]
+    pub inputs: Vec<Visibility>,
+    /// Per-output visibilities, overriding `output` for the outputs they cover
+    #[serde(default)]
+    pub outputs: Vec<Visibility>,
+    /// Per-node param visibilities, keyed by node index or Onnx initializer name, overriding `params` for the nodes they match
+    #[serde(default)]
+    pub param_overrides: BTreeMap<String, Visibility>,
 }
 impl std::fmt::Display for VarVisibility {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -116,7 +206,26 @@ impl std::fmt::Display for VarVisibility {
             f,
             "(inputs: {}, params: {}, outputs: {})",
             self.input, self.params, self.output
-        )
+        )?;
+        if !self.inputs.is_empty() || !self.outputs.is_empty() {
+            write!(
+                f,
+                " (per-input: [{}], per-output: [{}])",
+                self.inputs.iter().join(", "),
+                self.outputs.iter().join(", ")
+            )?;
+        }
+        if !self.param_overrides.is_empty() {
+            write!(
+                f,
+                " (per-param: [{}])",
+                self.param_overrides
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .join(", ")
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -124,26 +233,156 @@ impl VarVisibility {
     /// Read from cli args whether the model input, model parameters, and model output are Public or Private to the prover.
     /// Place in [VarVisibility] struct.
     pub fn from_args(args: RunArgs) -> Result<Self, Box<dyn Error>> {
-        let input_vis = args.input_visibility;
-        let params_vis = args.param_visibility;
-        let output_vis = args.output_visibility;
-        if !output_vis.is_public()
-            & !params_vis.is_public()
-            & !input_vis.is_public()
-            & !output_vis.is_hashed()
-            & !params_vis.is_hashed()
-            & !input_vis.is_hashed()
-            & !output_vis.is_encrypted()
-            & !params_vis.is_encrypted()
-            & !input_vis.is_encrypted()
-        {
+        Self::new(args, vec![], vec![], BTreeMap::new())
+    }
+
+    /// Reads the visibilities from the [RunArgs] of a [GraphSettings], along with any per-input and per-output visibilities.
+    pub fn from_settings(settings: &GraphSettings) -> Result<Self, Box<dyn Error>> {
+        Self::new(
+            settings.run_args,
+            settings.input_visibilities.clone(),
+            settings.output_visibilities.clone(),
+            settings.param_visibilities.clone(),
+        )
+    }
+
+    /// Combines the global visibilities in `args` with per-input, per-output and per-node param visibilities.
+    /// At least one of the visibilities must make something public, hashed or encrypted, only inputs can be signed or members of a dataset,
+    /// and params can't be committed to with [Visibility::KZGCommit].
+    /// Proving accuracy (see [RunArgs::prove_accuracy]) requires hashed inputs, a public output and non-public params.
+    pub fn new(
+        args: RunArgs,
+        inputs: Vec<Visibility>,
+        outputs: Vec<Visibility>,
+        param_overrides: BTreeMap<String, Visibility>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let visibility = Self {
+            input: args.input_visibility,
+            params: args.param_visibility,
+            output: args.output_visibility,
+            inputs,
+            outputs,
+            param_overrides,
+        };
+        if !visibility.any(|v| {
+            v.is_public()
+                || v.is_hashed()
+                || v.is_encrypted()
+                || v.is_symencrypted()
+                || v.is_kzgcommit()
+                || v.is_signed()
+                || v.is_member()
+                || v.is_custom()
+        }) {
             return Err(Box::new(GraphError::Visibility));
         }
-        Ok(Self {
-            input: input_vis,
-            params: params_vis,
-            output: output_vis,
-        })
+        // an accuracy proof only reveals the number of correct rows and the hashes of the rows and labels
+        if args.prove_accuracy
+            && (!visibility.input.is_hashed()
+                || !visibility.inputs.iter().all(Visibility::is_hashed)
+                || !visibility.output.is_public()
+                || !visibility.outputs.iter().all(Visibility::is_public)
+                || visibility.any_params(Visibility::is_public))
+        {
+            return Err(Box::new(GraphError::AccuracyHead(
+                "the inputs must be hashed, the output public and the params not public"
+                    .to_string(),
+            )));
+        }
+        for input_only in [Visibility::Signed, Visibility::Member] {
+            if visibility.output == input_only
+                || visibility.outputs.contains(&input_only)
+                || visibility.any_params(|v| *v == input_only)
+            {
+                return Err(Box::new(GraphError::InputOnlyVisibility(input_only)));
+            }
+        }
+        if visibility.any_params(Visibility::is_kzgcommit) {
+            return Err(Box::new(GraphError::NoParamVisibility(
+                Visibility::KZGCommit,
+            )));
+        }
+        Ok(visibility)
+    }
+
+    /// The visibility of the `i`th input.
+    pub fn input_at(&self, i: usize) -> Visibility {
+        self.inputs.get(i).cloned().unwrap_or(self.input)
+    }
+
+    /// The visibility of the `i`th output.
+    pub fn output_at(&self, i: usize) -> Visibility {
+        self.outputs.get(i).cloned().unwrap_or(self.output)
+    }
+
+    /// The param visibility set for a node, matching the Onnx names of the initializers it reads
+    /// (see [super::Model::initializer_names]) before its index, if any.
+    pub fn param_override(&self, idx: usize, initializers: &[&str]) -> Option<Visibility> {
+        initializers
+            .iter()
+            .find_map(|name| self.param_overrides.get(*name))
+            .or_else(|| self.param_overrides.get(&idx.to_string()))
+            .cloned()
+    }
+
+    /// The visibility of the params of a node (falling back on the global param visibility).
+    pub fn params_at(&self, idx: usize, initializers: &[&str]) -> Visibility {
+        self.param_override(idx, initializers)
+            .unwrap_or(self.params)
+    }
+
+    /// The visibilities of the first `n` inputs.
+    pub fn inputs_for(&self, n: usize) -> Vec<Visibility> {
+        (0..n).map(|i| self.input_at(i)).collect()
+    }
+
+    /// The visibilities of the first `n` outputs.
+    pub fn outputs_for(&self, n: usize) -> Vec<Visibility> {
+        (0..n).map(|i| self.output_at(i)).collect()
+    }
+
+    /// Whether any of the global or per-tensor visibilities satisfies `f`.
+    pub fn any(&self, f: impl Fn(&Visibility) -> bool) -> bool {
+        [self.input, self.params, self.output]
+            .iter()
+            .chain(self.inputs.iter())
+            .chain(self.outputs.iter())
+            .chain(self.param_overrides.values())
+            .any(f)
+    }
+
+    /// Whether the global param visibility or any of the per-node param visibilities satisfies `f`.
+    pub fn any_params(&self, f: impl Fn(&Visibility) -> bool) -> bool {
+        std::iter::once(&self.params)
+            .chain(self.param_overrides.values())
+            .any(f)
+    }
+
+    /// The user-defined modules any of the visibilities use, ordered by name.
+    pub fn custom_modules(&self) -> Vec<ModuleName> {
+        [self.input, self.params, self.output]
+            .iter()
+            .chain(self.inputs.iter())
+            .chain(self.outputs.iter())
+            .chain(self.param_overrides.values())
+            .filter_map(|v| match v {
+                Visibility::Custom(module) => Some(*module),
+                _ => None,
+            })
+            .sorted()
+            .dedup()
+            .collect()
+    }
+    /// The visibilities of the modules processing the inputs, params and outputs, in the order their modules are laid out.
+    pub fn module_visibilities(&self) -> Vec<Visibility> {
+        let visibilities = [self.input, self.params, self.output]
+            .iter()
+            .chain(self.inputs.iter())
+            .chain(self.outputs.iter())
+            .chain(self.param_overrides.values())
+            .copied()
+            .collect_vec();
+        modules::module_visibilities(&visibilities)
     }
 }
 
@@ -156,6 +395,8 @@ pub struct ModelVars<F: PrimeField + TensorType + PartialOrd> {
     pub fixed: Vec<VarTensor>,
     #[allow(missing_docs)]
     pub instances: Vec<ValTensor<F>>,
+    /// The dedicated columns of each input and output with [Visibility::KZGCommit] (inputs first).
+    pub kzg_commits: Vec<VarTensor>,
 }
 
 impl<F: PrimeField + TensorType + PartialOrd> ModelVars<F> {
@@ -172,7 +413,8 @@ impl<F: PrimeField + TensorType + PartialOrd> ModelVars<F> {
             .map(|_| VarTensor::new_advice(cs, logrows, var_len))
             .collect_vec();
         let mut fixed = vec![];
-        if visibility.params == Visibility::Public {
+        // public params are represented as constants
+        if visibility.any_params(Visibility::is_public) {
             fixed = (0..1)
                 .map(|_| VarTensor::new_fixed(cs, logrows, var_len))
                 .collect_vec();
@@ -185,15 +427,145 @@ impl<F: PrimeField + TensorType + PartialOrd> ModelVars<F> {
             advices,
             fixed,
             instances,
+            kzg_commits: vec![],
         }
     }
 
+    /// Allocate a dedicated unblinded advice column (or columns of `col_size` rows, if it doesn't fit in one)
+    /// for each tensor with [Visibility::KZGCommit].
+    pub fn configure_kzg_commits(
+        &mut self,
+        cs: &mut ConstraintSystem<F>,
+        col_size: usize,
+        shapes: &[Vec<usize>],
+    ) {
+        self.kzg_commits = shapes
+            .iter()
+            .map(|shape| VarTensor::new_unblinded_advice(cs, col_size, shape.iter().product()))
+            .collect();
+    }
+
     /// Allocate all columns that will be assigned to by a model.
     pub fn new_dummy() -> Self {
         ModelVars {
             advices: vec![],
             fixed: vec![],
             instances: vec![],
+            kzg_commits: vec![],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::utilities::quantized_tensor_to_valtensor;
+    use crate::tensor::{Tensor, ValType};
+    use halo2curves::bn256::Fr as Fp;
+
+    fn private_args() -> RunArgs {
+        RunArgs {
+            input_visibility: Visibility::Private,
+            param_visibility: Visibility::Private,
+            output_visibility: Visibility::Private,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn per_tensor_visibilities_override_the_global_ones() {
+        let visibility = VarVisibility::new(
+            private_args(),
+            vec![Visibility::Private, Visibility::Public],
+            vec![Visibility::Hashed],
+            BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            visibility.inputs_for(3),
+            vec![Visibility::Private, Visibility::Public, Visibility::Private]
+        );
+        assert_eq!(visibility.output_at(0), Visibility::Hashed);
+        assert_eq!(visibility.output_at(1), Visibility::Private);
+    }
+
+    #[test]
+    fn initializer_names_take_precedence_over_indices() {
+        let visibility = VarVisibility {
+            param_overrides: BTreeMap::from([
+                ("3".to_string(), Visibility::Hashed),
+                ("dense.weight".to_string(), Visibility::Public),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            visibility.params_at(3, &["dense.weight"]),
+            Visibility::Public
+        );
+        assert_eq!(visibility.params_at(3, &["dense.bias"]), Visibility::Hashed);
+        assert_eq!(visibility.params_at(4, &[]), Visibility::Private);
+    }
+
+    #[test]
+    fn params_cannot_be_kzg_committed() {
+        let args = RunArgs {
+            output_visibility: Visibility::KZGCommit,
+            ..private_args()
+        };
+        assert!(VarVisibility::new(args, vec![], vec![], BTreeMap::new()).is_ok());
+        let args = RunArgs {
+            param_visibility: Visibility::KZGCommit,
+            ..args
+        };
+        assert!(VarVisibility::new(args, vec![], vec![], BTreeMap::new()).is_err());
+        assert!(VarVisibility::new(
+            private_args(),
+            vec![Visibility::Public],
+            vec![],
+            BTreeMap::from([("0".to_string(), Visibility::KZGCommit)])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn public_params_are_constants() {
+        let vars = |params: Visibility| {
+            let visibility = VarVisibility {
+                params,
+                ..Default::default()
+            };
+            let mut cs = ConstraintSystem::<Fp>::default();
+            ModelVars::new(&mut cs, 10, 16, vec![], visibility, 0)
+        };
+        // public params are assigned to the constants column, which is committed to in the vk
+        assert!(vars(Visibility::Private).fixed.is_empty());
+        assert_eq!(vars(Visibility::Public).fixed.len(), 1);
+        let constants = quantized_tensor_to_valtensor::<Fp>(
+            Tensor::new(Some(&[1, 2]), &[2]).unwrap(),
+            0,
+            Visibility::Public,
+        )
+        .unwrap();
+        assert!(
+            matches!(constants, ValTensor::Value { inner, .. } if inner.iter().all(|v| matches!(v, ValType::Constant(_))))
+        );
+    }
+
+    #[test]
+    fn rejects_all_private_and_signed_outputs() {
+        assert!(VarVisibility::new(
+            private_args(),
+            vec![Visibility::Private],
+            vec![Visibility::Private],
+            BTreeMap::new()
+        )
+        .is_err());
+        assert!(VarVisibility::new(
+            private_args(),
+            vec![Visibility::Public],
+            vec![Visibility::Signed],
+            BTreeMap::new()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use halo2_proofs::arithmetic::{best_fft, best_multiexp};
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::poly::EvaluationDomain;
+use halo2curves::bn256::{Fr, G1Affine, G1};
+use halo2curves::ff::Field;
+use halo2curves::group::{Curve, Group};
+use instant::Instant;
+use itertools::Itertools;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+
+/// Size in bytes of a serialized scalar, and of a compressed point in a proof transcript.
+const SCALAR_BYTES: usize = 32;
+/// Size in bytes of an uncompressed point, as written to keys using `SerdeFormat::RawBytes`.
+const RAW_POINT_BYTES: usize = 64;
+/// Number of distinct opening points in a GWC multiopen proof for a typical circuit (x, ωx, ω⁻¹x, ω^last x).
+const OPENING_POINTS: usize = 4;
+
+/// The shape of a constraint system, as far as it affects key sizes, proof sizes and proving time.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CircuitShape {
+    /// log2 of the number of rows
+    pub logrows: u32,
+    /// degree of the constraint system
+    pub degree: usize,
+    /// number of advice columns
+    pub advice_columns: usize,
+    /// number of fixed columns (excluding selectors)
+    pub fixed_columns: usize,
+    /// number of selectors, which are compressed into at most as many fixed columns at keygen
+    pub selectors: usize,
+    /// number of instance columns
+    pub instance_columns: usize,
+    /// number of lookup arguments
+    pub lookups: usize,
+    /// number of columns in the permutation argument
+    pub permutation_columns: usize,
+    /// number of (column, rotation) queries over advice, fixed and instance columns
+    pub queries: usize,
+}
+
+impl CircuitShape {
+    /// Extracts the shape of a configured [ConstraintSystem] with `2^logrows` rows.
+    pub fn from_cs(cs: &ConstraintSystem<Fr>, logrows: u32) -> Self {
+        CircuitShape {
+            logrows,
+            degree: cs.degree(),
+            advice_columns: cs.num_advice_columns(),
+            fixed_columns: cs.num_fixed_columns(),
+            selectors: cs.num_selectors(),
+            instance_columns: cs.num_instance_columns(),
+            lookups: cs.lookups().len(),
+            permutation_columns: cs.permutation().get_columns().len(),
+            queries: cs.advice_queries().len()
+                + cs.fixed_queries().len()
+                + cs.instance_queries().len(),
+        }
+    }
+
+    /// log2 of the size of the extended domain the quotient polynomial is computed over.
+    pub fn extended_logrows(&self) -> u32 {
+        let quotient_poly_degree = (self.degree.max(2) - 1) as u64;
+        let n = 1u64 << self.logrows;
+        let mut extended_k = self.logrows;
+        while (1u64 << extended_k) < n * quotient_poly_degree {
+            extended_k += 1;
+        }
+        extended_k
+    }
+
+    /// Number of grand product columns needed by the permutation argument.
+    fn permutation_products(&self) -> usize {
+        let chunk_len = self.degree.max(3) - 2;
+        (self.permutation_columns + chunk_len - 1) / chunk_len
+    }
+
+    /// Number of polynomial commitments in a proof (each an MSM of size `2^logrows` for the prover).
+    fn num_commitments(&self) -> usize {
+        self.advice_columns
+            // permuted input, permuted table and grand product per lookup
+            + 3 * self.lookups
+            + self.permutation_products()
+            // quotient polynomial pieces and the vanishing argument's random polynomial
+            + (self.degree.max(2) - 1)
+            + 1
+            + OPENING_POINTS
+    }
+
+    /// Number of evaluations in a proof.
+    fn num_evaluations(&self) -> usize {
+        self.queries
+            + self.permutation_columns
+            + 3 * self.permutation_products()
+            + 5 * self.lookups
+            + 1
+    }
+}
+
+/// Timings of the core proving primitives on this machine, used to scale the estimates.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MicroBenchmark {
+    /// log2 of the size of the benchmarked MSM and FFT
+    pub logsize: u32,
+    /// time taken by an MSM of size `2^logsize`, in seconds
+    pub msm_secs: f64,
+    /// time taken by an FFT of size `2^logsize`, in seconds
+    pub fft_secs: f64,
+}
+
+impl MicroBenchmark {
+    /// Times an MSM and an FFT of size `2^logsize`.
+    pub fn run(logsize: u32) -> Self {
+        let n = 1 << logsize;
+        let scalars = (0..n).map(|_| Fr::random(OsRng)).collect_vec();
+        let generator = G1::generator();
+        let bases: Vec<G1Affine> = scalars
+            .iter()
+            .map(|s| (generator * s).to_affine())
+            .collect();
+
+        let start = Instant::now();
+        let _ = best_multiexp(&scalars, &bases);
+        let msm_secs = start.elapsed().as_secs_f64();
+
+        let domain = EvaluationDomain::<Fr>::new(1, logsize);
+        let mut values = scalars;
+        let start = Instant::now();
+        best_fft(&mut values, domain.get_omega(), logsize);
+        let fft_secs = start.elapsed().as_secs_f64();
+
+        MicroBenchmark {
+            logsize,
+            msm_secs,
+            fft_secs,
+        }
+    }
+
+    /// Extrapolated time of an MSM of size `2^logsize` (Pippenger's is roughly `n / log n`).
+    pub fn msm_secs_at(&self, logsize: u32) -> f64 {
+        let ratio = 2f64.powi(logsize as i32 - self.logsize as i32);
+        // log n is floored at 1 so that sizes of 1 (logsize 0) don't divide by zero
+        self.msm_secs * ratio * (self.logsize.max(1) as f64 / logsize.max(1) as f64)
+    }
+
+    /// Extrapolated time of an FFT of size `2^logsize` (roughly `n log n`).
+    pub fn fft_secs_at(&self, logsize: u32) -> f64 {
+        let ratio = 2f64.powi(logsize as i32 - self.logsize as i32);
+        self.fft_secs * ratio * (logsize.max(1) as f64 / self.logsize.max(1) as f64)
+    }
+}
+
+/// Predicted resource usage of a circuit.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Estimate {
+    /// the shape of the circuit the estimate is for
+    pub shape: CircuitShape,
+    /// the benchmark the proving time was extrapolated from
+    pub benchmark: MicroBenchmark,
+    /// predicted size of the serialized verifying key, in bytes
+    pub vk_bytes: usize,
+    /// predicted size of the serialized proving key, in bytes
+    pub pk_bytes: usize,
+    /// predicted memory needed to generate the proving key, in bytes
+    pub keygen_memory_bytes: usize,
+    /// predicted size of a proof, in bytes
+    pub proof_bytes: usize,
+    /// predicted proving time (excluding witness generation), in seconds
+    pub proving_secs: f64,
+}
+
+impl Estimate {
+    /// Predicts key sizes, proof size and proving time for `shape` using the timings in `benchmark`.
+    pub fn new(shape: &CircuitShape, benchmark: &MicroBenchmark) -> Self {
+        let n = 1usize << shape.logrows;
+        let extended_logrows = shape.extended_logrows();
+        let extended_n = 1usize << extended_logrows;
+
+        let fixed = shape.fixed_columns + shape.selectors;
+        let permutation = shape.permutation_columns;
+
+        // commitments to the fixed and permutation columns, plus the raw selector bitmaps
+        let vk_bytes = RAW_POINT_BYTES * (fixed + permutation) + shape.selectors * ((n + 7) / 8);
+
+        // each fixed and permutation column is kept as values, coefficients and an extended coset,
+        // alongside the cosets of l_0, l_last and l_active_row
+        let pk_bytes = vk_bytes
+            + SCALAR_BYTES * ((fixed + permutation) * (2 * n + extended_n) + 3 * extended_n);
+
+        // keygen additionally holds the assembled advice-free circuit (fixed values and permutation mapping)
+        let keygen_memory_bytes = pk_bytes + SCALAR_BYTES * n * (fixed + 2 * permutation);
+
+        let proof_bytes = SCALAR_BYTES * (shape.num_commitments() + shape.num_evaluations());
+
+        // every committed polynomial is interpolated (an inverse FFT of size n) and committed (an MSM of size n),
+        // and every polynomial in the quotient is evaluated over the extended coset (an FFT of size extended_n)
+        let committed = shape.num_commitments();
+        let extended = shape.advice_columns
+            + fixed
+            + shape.instance_columns
+            + permutation
+            + shape.permutation_products()
+            + 5 * shape.lookups;
+        let proving_secs = committed as f64
+            * (benchmark.msm_secs_at(shape.logrows) + benchmark.fft_secs_at(shape.logrows))
+            + extended as f64 * benchmark.fft_secs_at(extended_logrows);
+
+        Estimate {
+            shape: shape.clone(),
+            benchmark: benchmark.clone(),
+            vk_bytes,
+            pk_bytes,
+            keygen_memory_bytes,
+            proof_bytes,
+            proving_secs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::lookup::LookupOp;
+    use crate::commands::RunArgs;
+    use crate::graph::modules::GraphModules;
+    use crate::graph::registry::{ModuleContext, ModuleRegistry};
+    use crate::graph::{GraphCircuit, GraphSettings, VarVisibility, Visibility};
+    use halo2_proofs::plonk::Circuit;
+
+    fn shape() -> CircuitShape {
+        CircuitShape {
+            logrows: 10,
+            degree: 5,
+            advice_columns: 3,
+            fixed_columns: 1,
+            selectors: 4,
+            instance_columns: 1,
+            lookups: 1,
+            permutation_columns: 5,
+            queries: 8,
+        }
+    }
+
+    fn benchmark() -> MicroBenchmark {
+        MicroBenchmark {
+            logsize: 10,
+            msm_secs: 1.0,
+            fft_secs: 1.0,
+        }
+    }
+
+    #[test]
+    fn estimate_matches_hand_computed_sizes() {
+        let estimate = Estimate::new(&shape(), &benchmark());
+        // 5 fixed columns (selectors included) and 5 permutation columns, with 4 selector bitmaps of 1024 bits
+        assert_eq!(estimate.vk_bytes, 64 * 10 + 4 * 128);
+        // each of the 10 columns as values and coefficients (1024 each) and an extended coset (4096),
+        // plus 3 extended cosets
+        assert_eq!(
+            estimate.pk_bytes,
+            estimate.vk_bytes + 32 * (10 * (2 * 1024 + 4096) + 3 * 4096)
+        );
+        // 17 commitments (3 advice, 3 lookup, 2 permutation products, 4 quotient pieces, 1 random, 4 openings)
+        // and 25 evaluations (8 queries, 5 permutation columns, 6 permutation products, 5 lookup, 1 random)
+        assert_eq!(estimate.proof_bytes, 32 * (17 + 25));
+        // 17 MSMs and FFTs of size 2^10, and 21 FFTs of size 2^12
+        let expected_secs = 17.0 * 2.0 + 21.0 * (4.0 * 12.0 / 10.0);
+        assert!((estimate.proving_secs - expected_secs).abs() < 1e-9);
+    }
+
+    #[test]
+    fn extrapolation_handles_logsize_zero() {
+        let benchmark = benchmark();
+        assert!(benchmark.msm_secs_at(0).is_finite());
+        assert!(benchmark.fft_secs_at(0).is_finite());
+
+        let benchmark = MicroBenchmark {
+            logsize: 0,
+            ..benchmark
+        };
+        assert!(benchmark.msm_secs_at(10).is_finite());
+        assert!(benchmark.fft_secs_at(10).is_finite());
+    }
+
+    #[test]
+    fn shape_follows_the_settings() {
+        let run_args = RunArgs {
+            logrows: 10,
+            bits: 8,
+            input_visibility: Visibility::Hashed,
+            ..Default::default()
+        };
+        let visibility = VarVisibility::from_args(run_args).unwrap();
+        let module_sizes = GraphModules::num_constraints_and_instances(
+            &ModuleRegistry::default(),
+            vec![vec![4]],
+            vec![],
+            vec![vec![4]],
+            visibility,
+            &ModuleContext {
+                hash_mode: run_args.hash_mode,
+                membership_depth: run_args.membership_depth,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let settings = GraphSettings {
+            run_args,
+            // spans 3 of the model's columns of 2^10 - 6 rows
+            num_constraints: 3000,
+            required_lookups: vec![LookupOp::ReLU { scale: 1 }],
+            module_sizes,
+            ..Default::default()
+        };
+
+        let mut cs = ConstraintSystem::<Fr>::default();
+        let _ = GraphCircuit::configure_with_params(&mut cs, settings);
+        let shape = CircuitShape::from_cs(&cs, run_args.logrows);
+
+        // one lookup argument per column of the lookup input
+        assert_eq!(shape.lookups, 3);
+        // the only instance column is the one holding the input's hash
+        assert_eq!(shape.instance_columns, 1);
+        // 3 model tensors of 3 columns each, plus the Poseidon chip's columns
+        assert!(shape.advice_columns > 9);
+
+        let estimate = Estimate::new(&shape, &benchmark());
+        assert_eq!(
+            estimate.proof_bytes,
+            SCALAR_BYTES * (shape.num_commitments() + shape.num_evaluations())
+        );
+    }
+
+    #[test]
+    fn estimates_scale_with_logrows() {
+        let shape = shape();
+        assert_eq!(shape.extended_logrows(), 12);
+
+        let benchmark = benchmark();
+        let small = Estimate::new(&shape, &benchmark);
+        let large = Estimate::new(
+            &CircuitShape {
+                logrows: 11,
+                ..shape.clone()
+            },
+            &benchmark,
+        );
+        assert!(large.pk_bytes > small.pk_bytes);
+        assert!(large.proving_secs > small.proving_secs);
+        assert_eq!(large.proof_bytes, small.proof_bytes);
+    }
+}
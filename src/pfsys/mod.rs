@@ -1,5 +1,7 @@
 /// EVM related proving and verification
 pub mod evm;
+/// Estimates of key sizes, proof sizes and proving time
+pub mod estimate;
 
 use crate::circuit::CheckMode;
 use crate::tensor::TensorType;
@@ -11,7 +13,9 @@ use halo2_proofs::plonk::{
 };
 use halo2_proofs::poly::commitment::{CommitmentScheme, Params, ParamsProver, Prover, Verifier};
 use halo2_proofs::poly::VerificationStrategy;
-use halo2_proofs::transcript::{EncodedChallenge, TranscriptReadBuffer, TranscriptWriterBuffer};
+use halo2_proofs::transcript::{
+    EncodedChallenge, TranscriptRead, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
 use halo2curves::ff::{FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
 use halo2curves::serde::SerdeObject;
 use halo2curves::CurveAffine;
@@ -343,6 +347,21 @@ where
     verify_proof::<Scheme, V, _, TR, _>(params, vk, strategy, instances, &mut transcript)
 }
 
+/// Reads the first `n` commitments from the start of a proof. As instances aren't committed to in the
+/// transcript with KZG, these are the commitments to the first `n` advice columns of the circuit, provided
+/// these are all in the first phase.
+pub fn read_advice_commitments<
+    C: CurveAffine,
+    E: EncodedChallenge<C>,
+    TR: TranscriptReadBuffer<Cursor<Vec<u8>>, C, E>,
+>(
+    proof: &[u8],
+    n: usize,
+) -> Result<Vec<C>, io::Error> {
+    let mut transcript = TR::init(Cursor::new(proof.to_vec()));
+    (0..n).map(|_| transcript.read_point()).collect()
+}
+
 /// Loads a [VerifyingKey] at `path`.
 pub fn load_vk<Scheme: CommitmentScheme, F: PrimeField + TensorType, C: Circuit<F>>(
     path: PathBuf,
@@ -449,10 +468,93 @@ mod tests {
     use std::io::copy;
 
     use super::*;
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error as PlonkError, Instance};
+    use halo2_proofs::poly::commitment::Blind;
     use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
-    use halo2curves::bn256::Bn256;
+    use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+    use halo2_proofs::transcript::{Blake2bRead, Challenge255};
+    use halo2curves::bn256::{Bn256, Fr};
+    use halo2curves::group::Curve;
     use tempfile::Builder;
 
+    /// Copies a public value into a blinded column and a private tensor into an unblinded column.
+    #[derive(Clone, Default)]
+    struct CommittedColumn {
+        values: Vec<Fr>,
+    }
+
+    impl Circuit<Fr> for CommittedColumn {
+        type Config = (Column<Instance>, Column<Advice>, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let instance = cs.instance_column();
+            let blinded = cs.advice_column();
+            let unblinded = cs.unblinded_advice_column();
+            cs.enable_equality(instance);
+            cs.enable_equality(blinded);
+            (instance, blinded, unblinded)
+        }
+
+        fn synthesize(
+            &self,
+            (instance, blinded, unblinded): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), PlonkError> {
+            let public = layouter.assign_region(
+                || "committed",
+                |mut region| {
+                    for (i, v) in self.values.iter().enumerate() {
+                        region.assign_advice(|| "", unblinded, i, || Value::known(*v))?;
+                    }
+                    region.assign_advice(|| "", blinded, 0, || Value::known(self.values[0]))
+                },
+            )?;
+            layouter.constrain_instance(public.cell(), instance, 0)
+        }
+    }
+
+    #[test]
+    fn proofs_start_with_the_advice_commitments() {
+        let values = vec![Fr::from(3), Fr::from(5), Fr::from(7)];
+        let circuit = CommittedColumn {
+            values: values.clone(),
+        };
+        let params = gen_srs::<KZGCommitmentScheme<Bn256>>(4);
+        let pk = create_keys::<KZGCommitmentScheme<Bn256>, Fr, _>(&circuit, &params).unwrap();
+        let snark = crate::execute::create_proof_circuit_kzg(
+            circuit,
+            &params,
+            vec![vec![values[0]]],
+            &pk,
+            TranscriptType::Blake,
+            SingleStrategy::new(&params),
+            CheckMode::SAFE,
+        )
+        .unwrap();
+
+        let commitments =
+            read_advice_commitments::<_, Challenge255<_>, Blake2bRead<_, _, _>>(&snark.proof, 2)
+                .unwrap();
+        // the instance isn't in the proof, and the unblinded column is committed to without a blind
+        let mut column = pk.get_vk().get_domain().empty_lagrange();
+        for (i, v) in values.iter().enumerate() {
+            column[i] = *v;
+        }
+        assert_eq!(
+            commitments[1],
+            params
+                .commit_lagrange(&column, Blind::default())
+                .to_affine()
+        );
+    }
+
     #[tokio::test]
     async fn test_can_load_pre_generated_srs() {
         let tmp_dir = Builder::new().prefix("example").tempdir().unwrap();
@@ -1,5 +1,6 @@
 use crate::circuit::{CheckMode, Tolerance};
-use crate::commands::{CalibrationTarget, RunArgs, StrategyType};
+use crate::circuit::modules::elgamal::{ElGamalKeypair, ElGamalRecipient};
+use crate::commands::{CalibrationTarget, HashMode, RunArgs, StrategyType};
 use crate::graph::{Model, Visibility, GraphWitness};
 use crate::pfsys::{
     gen_srs as ezkl_gen_srs, save_params,
@@ -35,6 +36,12 @@ struct PyRunArgs {
     #[pyo3(get, set)]
     pub param_visibility: Visibility,
     #[pyo3(get, set)]
+    pub hash_mode: HashMode,
+    #[pyo3(get, set)]
+    pub membership_depth: usize,
+    #[pyo3(get, set)]
+    pub prove_accuracy: bool,
+    #[pyo3(get, set)]
     pub batch_size: usize,
     #[pyo3(get, set)]
     pub allocated_constraints: Option<usize>,
@@ -54,6 +61,9 @@ impl PyRunArgs {
             input_visibility: "public".into(),
             output_visibility: "public".into(),
             param_visibility: "private".into(),
+            hash_mode: HashMode::default(),
+            membership_depth: 0,
+            prove_accuracy: false,
             batch_size: 1,
             allocated_constraints: None,
         }
@@ -72,6 +82,9 @@ impl From<PyRunArgs> for RunArgs {
             input_visibility: py_run_args.input_visibility,
             output_visibility: py_run_args.output_visibility,
             param_visibility: py_run_args.param_visibility,
+            hash_mode: py_run_args.hash_mode,
+            membership_depth: py_run_args.membership_depth,
+            prove_accuracy: py_run_args.prove_accuracy,
             allocated_constraints: py_run_args.allocated_constraints,
             batch_size: py_run_args.batch_size,
         }
@@ -111,15 +124,46 @@ fn gen_srs(srs_path: PathBuf, logrows: usize) -> PyResult<()> {
     model,
     output,
     py_run_args = None,
+    output_tolerances = None,
+    input_visibilities = None,
+    output_visibilities = None,
+    elgamal_pk_path = None,
+    elgamal_fixed_pk = false,
 ))]
 fn gen_settings(
     model: PathBuf,
     output: PathBuf,
     py_run_args: Option<PyRunArgs>,
+    output_tolerances: Option<Vec<Tolerance>>,
+    input_visibilities: Option<Vec<Visibility>>,
+    output_visibilities: Option<Vec<Visibility>>,
+    elgamal_pk_path: Option<PathBuf>,
+    elgamal_fixed_pk: bool,
 ) -> Result<bool, PyErr> {
     let run_args: RunArgs = py_run_args.unwrap_or_else(PyRunArgs::new).into();
 
-    crate::execute::gen_circuit_settings(model, output, run_args).map_err(|e| {
+    let elgamal_recipient = match elgamal_pk_path {
+        None => ElGamalRecipient::Prover,
+        Some(path) => {
+            let pk = ElGamalKeypair::load_pk(&path)
+                .map_err(|_| PyIOError::new_err("Failed to load ElGamal public key"))?;
+            if elgamal_fixed_pk {
+                ElGamalRecipient::FixedPublicKey(pk)
+            } else {
+                ElGamalRecipient::PublicKey(pk)
+            }
+        }
+    };
+
+    crate::execute::gen_circuit_settings(
+        model,
+        output,
+        run_args,
+        output_tolerances.unwrap_or_default(),
+        input_visibilities.unwrap_or_default(),
+        output_visibilities.unwrap_or_default(),
+        elgamal_recipient,
+    ).map_err(|e| {
         let err_str = format!("Failed to generate settings: {}", e);
         PyRuntimeError::new_err(err_str)})?;
 
@@ -133,23 +177,44 @@ fn gen_settings(
     model,
     settings,
     target,
+    max_error=None,
 ))]
 fn calibrate_settings(
     data:  PathBuf,
     model: PathBuf,
     settings: PathBuf,
     target: Option<CalibrationTarget>,
+    max_error: Option<f32>,
 ) -> Result<bool, PyErr> {
 
     let target = target.unwrap_or(CalibrationTarget::Resources);
 
-    crate::execute::calibrate(model, data, settings, target).map_err(|e| {
+    crate::execute::calibrate(model, data, settings, target, max_error).map_err(|e| {
         let err_str = format!("Failed to calibrate settings: {}", e);
         PyRuntimeError::new_err(err_str)})?;
 
     Ok(true)
 }
 
+/// reports the error of the quantized model's outputs against the float model
+#[pyfunction(signature = (
+    data,
+    model,
+    settings_path,
+    output=None,
+))]
+fn accuracy(
+    data: PathBuf,
+    model: PathBuf,
+    settings_path: PathBuf,
+    output: Option<PathBuf>,
+) -> PyResult<bool> {
+    crate::execute::accuracy(model, data, settings_path, output).map_err(|e| {
+        let err_str = format!("Failed to measure accuracy: {}", e);
+        PyRuntimeError::new_err(err_str)})?;
+    Ok(true)
+}
+
 /// runs the forward pass operation
 #[pyfunction(signature = (
     data,
@@ -158,6 +223,12 @@ fn calibrate_settings(
     settings_path, 
     scale=None, 
     batch_size=None,  
+    activations_path=None,
+    elgamal_keypair_path=None,
+    elgamal_pk_path=None,
+    cipher_key_path=None,
+    input_signatures_path=None,
+    dataset_tree_path=None,
 ))]
 fn gen_witness(
     data: PathBuf,
@@ -166,8 +237,14 @@ fn gen_witness(
     settings_path: PathBuf,
     scale: Option<u32>,
     batch_size: Option<usize>,
+    activations_path: Option<PathBuf>,
+    elgamal_keypair_path: Option<PathBuf>,
+    elgamal_pk_path: Option<PathBuf>,
+    cipher_key_path: Option<PathBuf>,
+    input_signatures_path: Option<PathBuf>,
+    dataset_tree_path: Option<PathBuf>,
 ) -> PyResult<PyObject> {
-    let output: GraphWitness = crate::execute::gen_witness(model, data, output, scale, batch_size, settings_path)
+    let output: GraphWitness = crate::execute::gen_witness(model, data, output, scale, batch_size, settings_path, activations_path, elgamal_keypair_path, elgamal_pk_path, cipher_key_path, input_signatures_path, dataset_tree_path)
         .map_err(|e| {
             let err_str = format!("Failed to run generate witness: {}", e);
             PyRuntimeError::new_err(err_str)})?;
@@ -176,6 +253,28 @@ fn gen_witness(
     })
 }
 
+/// compares the activations stored by gen_witness against the float activations of the model
+#[pyfunction(signature = (
+    data,
+    model,
+    activations_path,
+    settings_path,
+    output=None,
+))]
+fn compare_activations(
+    data: PathBuf,
+    model: PathBuf,
+    activations_path: PathBuf,
+    settings_path: PathBuf,
+    output: Option<PathBuf>,
+) -> PyResult<bool> {
+    crate::execute::compare_activations(model, data, activations_path, settings_path, output)
+        .map_err(|e| {
+            let err_str = format!("Failed to compare activations: {}", e);
+            PyRuntimeError::new_err(err_str)})?;
+    Ok(true)
+}
+
 /// mocks the prover
 #[pyfunction(signature = (
     witness,
@@ -269,6 +368,28 @@ fn verify(
 
 }
 
+/// verifies a chain of proofs, each of whose hashed outputs are the hashed inputs of the next
+#[pyfunction(signature = (
+    proof_paths,
+    settings_paths,
+    vk_paths,
+    srs_path,
+))]
+fn verify_chain(
+    proof_paths: Vec<PathBuf>,
+    settings_paths: Vec<PathBuf>,
+    vk_paths: Vec<PathBuf>,
+    srs_path: PathBuf,
+) -> Result<bool, PyErr> {
+
+    crate::execute::verify_chain(proof_paths, settings_paths, vk_paths, srs_path).map_err(|e| {
+        let err_str = format!("Failed to run verify_chain: {}", e);
+        PyRuntimeError::new_err(err_str)})?;
+
+    Ok(true)
+
+}
+
 /// creates an aggregated proof
 #[pyfunction(signature = (
     proof_path,
@@ -440,6 +561,133 @@ fn print_proof_hex(proof_path: PathBuf) -> Result<String, PyErr> {
     Ok(hex::encode(proof.proof))
 }
 
+/// generates an ElGamal keypair
+#[pyfunction(signature = (
+    output,
+    pk_output = None,
+))]
+fn gen_elgamal_keypair(output: PathBuf, pk_output: Option<PathBuf>) -> Result<bool, PyErr> {
+    crate::execute::gen_elgamal_keypair(output, pk_output).map_err(|e| {
+        let err_str = format!("Failed to generate ElGamal keypair: {}", e);
+        PyRuntimeError::new_err(err_str)})?;
+    Ok(true)
+}
+
+/// decrypts the ElGamal ciphertexts contained in a proof's instances
+#[pyfunction(signature = (
+    proof_path,
+    settings_path,
+    keypair_path,
+))]
+fn elgamal_decrypt(
+    proof_path: PathBuf,
+    settings_path: PathBuf,
+    keypair_path: PathBuf,
+) -> Result<Vec<Vec<i128>>, PyErr> {
+    crate::execute::elgamal_decrypt(proof_path, settings_path, keypair_path).map_err(|e| {
+        let err_str = format!("Failed to decrypt: {}", e);
+        PyRuntimeError::new_err(err_str)})
+}
+
+/// generates a key for the Poseidon cipher
+#[pyfunction(signature = (
+    output,
+))]
+fn gen_cipher_key(output: PathBuf) -> Result<bool, PyErr> {
+    crate::execute::gen_cipher_key(output).map_err(|e| {
+        let err_str = format!("Failed to generate cipher key: {}", e);
+        PyRuntimeError::new_err(err_str)})?;
+    Ok(true)
+}
+
+/// decrypts the Poseidon cipher ciphertexts contained in a proof's instances
+#[pyfunction(signature = (
+    proof_path,
+    settings_path,
+    key_path,
+))]
+fn decrypt(
+    proof_path: PathBuf,
+    settings_path: PathBuf,
+    key_path: PathBuf,
+) -> Result<Vec<Vec<i128>>, PyErr> {
+    crate::execute::decrypt(proof_path, settings_path, key_path).map_err(|e| {
+        let err_str = format!("Failed to decrypt: {}", e);
+        PyRuntimeError::new_err(err_str)})
+}
+
+/// generates a Schnorr keypair
+#[pyfunction(signature = (
+    output,
+))]
+fn gen_schnorr_keypair(output: PathBuf) -> Result<bool, PyErr> {
+    crate::execute::gen_schnorr_keypair(output).map_err(|e| {
+        let err_str = format!("Failed to generate Schnorr keypair: {}", e);
+        PyRuntimeError::new_err(err_str)})?;
+    Ok(true)
+}
+
+/// signs the inputs with signed visibility of a data file
+#[pyfunction(signature = (
+    data,
+    model,
+    settings_path,
+    keypair_path,
+    output=None,
+))]
+fn sign_inputs(
+    data: PathBuf,
+    model: PathBuf,
+    settings_path: PathBuf,
+    keypair_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<usize, PyErr> {
+    let signatures = crate::execute::sign_inputs(model, data, settings_path, keypair_path, output).map_err(|e| {
+        let err_str = format!("Failed to sign inputs: {}", e);
+        PyRuntimeError::new_err(err_str)})?;
+    Ok(signatures.len())
+}
+
+/// builds the tree committing to a dataset, returning its depth
+#[pyfunction(signature = (
+    data,
+    settings_path,
+    output=None,
+))]
+fn build_dataset_tree(
+    data: PathBuf,
+    settings_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<usize, PyErr> {
+    let tree = crate::execute::build_dataset_tree(data, settings_path, output).map_err(|e| {
+        let err_str = format!("Failed to build dataset tree: {}", e);
+        PyRuntimeError::new_err(err_str)})?;
+    Ok(tree.depth())
+}
+
+/// computes the hashes identifying a model, returning them (as hex strings) keyed by name
+#[pyfunction(signature = (
+    model,
+    settings_path,
+    vk_path,
+    output=None,
+))]
+fn fingerprint(
+    model: PathBuf,
+    settings_path: PathBuf,
+    vk_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<std::collections::HashMap<&'static str, Option<String>>, PyErr> {
+    let fingerprint = crate::execute::fingerprint(model, settings_path, vk_path, output).map_err(|e| {
+        let err_str = format!("Failed to compute fingerprint: {}", e);
+        PyRuntimeError::new_err(err_str)})?;
+    Ok(std::collections::HashMap::from([
+        ("params_hash", fingerprint.params_hash.map(|h| format!("{:?}", h))),
+        ("settings_hash", Some(format!("{:?}", fingerprint.settings_hash))),
+        ("vk_hash", Some(format!("{:?}", fingerprint.vk_hash))),
+    ]))
+}
+
 // Python Module
 #[pymodule]
 fn ezkl_lib(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
@@ -448,19 +696,30 @@ fn ezkl_lib(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyRunArgs>()?;
     m.add_function(wrap_pyfunction!(table, m)?)?;
     m.add_function(wrap_pyfunction!(mock, m)?)?;
+    m.add_function(wrap_pyfunction!(compare_activations, m)?)?;
     m.add_function(wrap_pyfunction!(setup, m)?)?;
     m.add_function(wrap_pyfunction!(prove, m)?)?;
     m.add_function(wrap_pyfunction!(verify, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_chain, m)?)?;
     m.add_function(wrap_pyfunction!(gen_srs, m)?)?;
     m.add_function(wrap_pyfunction!(gen_witness, m)?)?;
     m.add_function(wrap_pyfunction!(gen_settings, m)?)?;
     m.add_function(wrap_pyfunction!(calibrate_settings, m)?)?;
+    m.add_function(wrap_pyfunction!(accuracy, m)?)?;
     m.add_function(wrap_pyfunction!(aggregate, m)?)?;
     m.add_function(wrap_pyfunction!(verify_aggr, m)?)?;
     m.add_function(wrap_pyfunction!(create_evm_verifier, m)?)?;
     m.add_function(wrap_pyfunction!(verify_evm, m)?)?;
     m.add_function(wrap_pyfunction!(create_evm_verifier_aggr, m)?)?;
     m.add_function(wrap_pyfunction!(print_proof_hex, m)?)?;
+    m.add_function(wrap_pyfunction!(gen_elgamal_keypair, m)?)?;
+    m.add_function(wrap_pyfunction!(elgamal_decrypt, m)?)?;
+    m.add_function(wrap_pyfunction!(gen_cipher_key, m)?)?;
+    m.add_function(wrap_pyfunction!(decrypt, m)?)?;
+    m.add_function(wrap_pyfunction!(gen_schnorr_keypair, m)?)?;
+    m.add_function(wrap_pyfunction!(sign_inputs, m)?)?;
+    m.add_function(wrap_pyfunction!(build_dataset_tree, m)?)?;
+    m.add_function(wrap_pyfunction!(fingerprint, m)?)?;
 
     Ok(())
 }
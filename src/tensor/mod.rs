@@ -46,6 +46,9 @@ pub enum TensorError {
     /// Significant bit truncation when instantiating
     #[error("Significant bit truncation when instantiating")]
     SigBitTruncationError,
+    /// A tensor that should be one-hot along an axis isn't
+    #[error("tensor is not one-hot along axis {0}")]
+    NotOneHot(usize),
 }
 
 /// The (inner) type of tensor elements.
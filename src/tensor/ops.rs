@@ -53,6 +53,35 @@ pub fn iff<
     masked_a + masked_b
 }
 
+/// Checks that a tensor is one-hot along an axis, i.e that its elements are 0 or 1 and that they sum to 1 along the axis.
+/// # Arguments
+/// * `a` - Tensor
+/// * `axis` - The axis along which the tensor is one-hot
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::one_hot_check;
+/// let x = Tensor::<i128>::new(
+///     Some(&[0, 1, 0, 1, 0, 0]),
+///     &[2, 3],
+/// ).unwrap();
+/// assert_eq!(one_hot_check(&x, 1).unwrap(), x);
+/// assert!(one_hot_check(&x, 0).is_err());
+/// let y = Tensor::<i128>::new(
+///     Some(&[0, 2, -1, 1, 0, 0]),
+///     &[2, 3],
+/// ).unwrap();
+/// assert!(one_hot_check(&y, 1).is_err());
+/// ```
+pub fn one_hot_check(a: &Tensor<i128>, axis: usize) -> Result<Tensor<i128>, TensorError> {
+    let is_boolean = a.iter().all(|x| *x == 0 || *x == 1);
+    let sums_to_one = sum_axes(a, &[axis])?.iter().all(|x| *x == 1);
+    if !is_boolean || !sums_to_one {
+        return Err(TensorError::NotOneHot(axis));
+    }
+    Ok(a.clone())
+}
+
 /// Resize using nearest neighbour interpolation.
 /// # Arguments
 /// * `a` - Tensor
@@ -2064,6 +2093,37 @@ pub mod nonlinearities {
         add(&[upper_bound, lower_bound]).unwrap()
     }
 
+    /// Applies range_check_abs
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `b` - Tensor
+    /// * `tol` - Single value, the largest absolute difference allowed (in fixed point)
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::range_check_abs;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[100, 200, 300, 400, 500, 600]),
+    ///     &[2, 3],
+    /// ).unwrap();
+    /// let y = Tensor::<i128>::new(
+    ///    Some(&[103, 200, 290, 404, 495, 607]),
+    ///   &[2, 3],
+    /// ).unwrap();
+    /// let result = range_check_abs(&[x, y], 5.0);
+    /// let expected = Tensor::<i128>::new(Some(&[0, 0, 1, 0, 0, 1]), &[2, 3]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn range_check_abs(t: &[Tensor<i128>], tol: f32) -> Tensor<i128> {
+        let diff: Tensor<i128> = sub(t).unwrap();
+        let upper_bound = greater_than(&diff, tol as f64);
+        let neg_diff = mult(&[diff, Tensor::<i128>::new(Some(&[-1]), &[1]).unwrap()]).unwrap();
+        let lower_bound = greater_than(&neg_diff, tol as f64);
+
+        add(&[upper_bound, lower_bound]).unwrap()
+    }
+
     /// Elementwise applies square root to a tensor of integers.
     /// # Arguments
     ///
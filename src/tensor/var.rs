@@ -61,6 +61,34 @@ impl VarTensor {
         }
     }
 
+    /// Create a new VarTensor::Advice whose columns aren't blinded, such that the commitment to each column
+    /// only depends on the values assigned to it.
+    /// Unlike [VarTensor::new_advice], the number of rows of each column is given rather than derived from the
+    /// blinding factors of `cs`, such that the way a tensor is split into columns doesn't depend on the rest of the circuit.
+    /// Arguments
+    /// * `cs` - The constraint system
+    /// * `max_rows` - The number of rows to use in each column, which must fit in the rows left by the blinding factors.
+    /// * `capacity` - The number of advice cells to allocate
+    pub fn new_unblinded_advice<F: PrimeField>(
+        cs: &mut ConstraintSystem<F>,
+        max_rows: usize,
+        capacity: usize,
+    ) -> Self {
+        let modulo = (capacity / max_rows) + 1;
+
+        let mut advices = vec![];
+        for _ in 0..modulo {
+            let col = cs.unblinded_advice_column();
+            cs.enable_equality(col);
+            advices.push(col);
+        }
+
+        VarTensor::Advice {
+            inner: advices,
+            col_size: max_rows,
+        }
+    }
+
     /// Create a new VarTensor::Dummy
     pub fn dummy(logrows: usize) -> Self {
         let base = 2u32;
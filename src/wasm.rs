@@ -58,7 +58,8 @@ pub fn gen_pk_wasm(
         halo2_proofs::poly::commitment::Params::<'_, G1Affine>::read(&mut reader).unwrap();
     // Read in circuit
     let mut circuit_reader = std::io::BufReader::new(&circuit_ser[..]);
-    let model = crate::graph::Model::new(&mut circuit_reader, circuit_settings.run_args).unwrap();
+    let model =
+        crate::graph::Model::new_from_settings(&mut circuit_reader, &circuit_settings).unwrap();
 
     let circuit = GraphCircuit::new(
         model,
@@ -199,7 +200,7 @@ pub fn prove_wasm(
 
     // read in circuit
     let mut reader = std::io::BufReader::new(&circuit_ser[..]);
-    let model = crate::graph::Model::new(&mut reader, circuit_settings.run_args).unwrap();
+    let model = crate::graph::Model::new_from_settings(&mut reader, &circuit_settings).unwrap();
 
     let mut circuit = GraphCircuit::new(
         model,
@@ -260,7 +260,10 @@ mod native_tests {
             use crate::native_tests::LARGE_TESTS;
             use test_case::test_case;
             use crate::native_tests::mock;
+            use crate::native_tests::mock_signed;
+            use crate::native_tests::mock_member;
             use crate::native_tests::kzg_prove_and_verify;
+            use crate::native_tests::kzg_prove_and_decrypt;
             use crate::native_tests::kzg_fuzz;
             use crate::native_tests::render_circuit;
             use crate::native_tests::tutorial as run_tutorial;
@@ -356,6 +359,34 @@ mod native_tests {
                 mock(test.to_string(), 7, 16, 19,"public", "private", "encrypted", 1);
             }
 
+            #(#[test_case(TESTS[N])])*
+            fn mock_symencrypted_input_(test: &str) {
+                crate::native_tests::init_binary();
+                crate::native_tests::mv_test_(test);
+                mock(test.to_string(), 7, 16, 17,"symencrypted", "private", "public", 1);
+            }
+
+            #(#[test_case(TESTS[N])])*
+            fn mock_symencrypted_all_(test: &str) {
+                crate::native_tests::init_binary();
+                crate::native_tests::mv_test_(test);
+                mock(test.to_string(), 7, 16, 18,"symencrypted", "symencrypted", "symencrypted", 1);
+            }
+
+            #(#[test_case(TESTS[N])])*
+            fn mock_signed_input_(test: &str) {
+                crate::native_tests::init_binary();
+                crate::native_tests::mv_test_(test);
+                mock_signed(test.to_string(), 7, 16, 18, "public");
+            }
+
+            #(#[test_case(TESTS[N])])*
+            fn mock_member_input_(test: &str) {
+                crate::native_tests::init_binary();
+                crate::native_tests::mv_test_(test);
+                mock_member(test.to_string(), 7, 16, 18, "public");
+            }
+
             #(#[test_case(TESTS[N])])*
             fn mock_encrypted_input_params_(test: &str) {
                 crate::native_tests::init_binary();
@@ -426,6 +457,14 @@ mod native_tests {
                 kzg_prove_and_verify(test.to_string(), 17, "safe", "private", "private", "encrypted");
             }
 
+            #(#[test_case(TESTS[N])])*
+            fn kzg_prove_and_decrypt_symencrypted_input(test: &str) {
+                crate::native_tests::init_binary();
+                crate::native_tests::init_params_17();
+                crate::native_tests::mv_test_(test);
+                kzg_prove_and_decrypt(test.to_string(), 7, 16, 17);
+            }
+
             #(#[test_case(TESTS[N])])*
             fn kzg_fuzz_(test: &str) {
                 crate::native_tests::init_binary();
@@ -741,6 +780,201 @@ mod native_tests {
         assert!(status.success());
     }
 
+    // Mock prove with signed inputs, signing them with a fresh Schnorr keypair
+    fn mock_signed(
+        example_name: String,
+        scale: usize,
+        bits: usize,
+        logrows: usize,
+        output_visibility: &str,
+    ) {
+        let test_dir = TEST_DIR.path().to_str().unwrap();
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-settings",
+                "-M",
+                format!("{}/{}/network.onnx", test_dir, example_name).as_str(),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+                &format!("--bits={}", bits),
+                &format!("--logrows={}", logrows),
+                &format!("--scale={}", scale),
+                "--input-visibility=signed",
+                "--param-visibility=private",
+                &format!("--output-visibility={}", output_visibility),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-schnorr-keypair",
+                "-O",
+                &format!("{}/{}/schnorr_keypair.json", test_dir, example_name),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "sign-inputs",
+                "-D",
+                &format!("{}/{}/input.json", test_dir, example_name),
+                "-M",
+                &format!("{}/{}/network.onnx", test_dir, example_name),
+                "-S",
+                &format!("{}/{}/settings.json", test_dir, example_name),
+                &format!(
+                    "--keypair-path={}/{}/schnorr_keypair.json",
+                    test_dir, example_name
+                ),
+                "-O",
+                &format!("{}/{}/signatures.json", test_dir, example_name),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-witness",
+                "-D",
+                &format!("{}/{}/input.json", test_dir, example_name),
+                "-M",
+                &format!("{}/{}/network.onnx", test_dir, example_name),
+                "-O",
+                &format!("{}/{}/witness.json", test_dir, example_name),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+                &format!(
+                    "--input-signatures-path={}/{}/signatures.json",
+                    test_dir, example_name
+                ),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "mock",
+                "-W",
+                format!("{}/{}/witness.json", test_dir, example_name).as_str(),
+                "-M",
+                format!("{}/{}/network.onnx", test_dir, example_name).as_str(),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+    }
+
+    // Mock prove with member inputs, building a dataset from the inputs (and a dummy row) they are rows of
+    fn mock_member(
+        example_name: String,
+        scale: usize,
+        bits: usize,
+        logrows: usize,
+        output_visibility: &str,
+    ) {
+        let test_dir = TEST_DIR.path().to_str().unwrap();
+
+        let data =
+            GraphInput::from_path(format!("{}/{}/input.json", test_dir, example_name).into())
+                .expect("failed to load input data");
+        let mut rows = data.input_data.clone();
+        rows.push(vec![0.0]);
+        let depth = rows.len().next_power_of_two().trailing_zeros();
+        std::fs::write(
+            format!("{}/{}/dataset.json", test_dir, example_name),
+            serde_json::to_string(&rows).unwrap(),
+        )
+        .expect("failed to save dataset");
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-settings",
+                "-M",
+                format!("{}/{}/network.onnx", test_dir, example_name).as_str(),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+                &format!("--bits={}", bits),
+                &format!("--logrows={}", logrows),
+                &format!("--scale={}", scale),
+                "--input-visibility=member",
+                "--param-visibility=private",
+                &format!("--output-visibility={}", output_visibility),
+                &format!("--membership-depth={}", depth),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "build-dataset-tree",
+                "-D",
+                &format!("{}/{}/dataset.json", test_dir, example_name),
+                "-S",
+                &format!("{}/{}/settings.json", test_dir, example_name),
+                "-O",
+                &format!("{}/{}/dataset_tree.json", test_dir, example_name),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-witness",
+                "-D",
+                &format!("{}/{}/input.json", test_dir, example_name),
+                "-M",
+                &format!("{}/{}/network.onnx", test_dir, example_name),
+                "-O",
+                &format!("{}/{}/witness.json", test_dir, example_name),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+                &format!(
+                    "--dataset-tree-path={}/{}/dataset_tree.json",
+                    test_dir, example_name
+                ),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "mock",
+                "-W",
+                format!("{}/{}/witness.json", test_dir, example_name).as_str(),
+                "-M",
+                format!("{}/{}/network.onnx", test_dir, example_name).as_str(),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+    }
+
     // Mock prove (fast, but does not cover some potential issues)
     fn render_circuit(example_name: String) {
         let test_dir = TEST_DIR.path().to_str().unwrap();
@@ -1241,6 +1475,183 @@ mod native_tests {
         assert!(status.success());
     }
 
+    // prove-serialize-verify with symencrypted inputs, then decrypt them from the proof with the shared key
+    fn kzg_prove_and_decrypt(example_name: String, scale: u32, bits: usize, logrows: usize) {
+        let test_dir = TEST_DIR.path().to_str().unwrap();
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-settings",
+                "-M",
+                format!("{}/{}/network.onnx", test_dir, example_name).as_str(),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+                &format!("--bits={}", bits),
+                &format!("--logrows={}", logrows),
+                &format!("--scale={}", scale),
+                "--input-visibility=symencrypted",
+                "--param-visibility=private",
+                "--output-visibility=public",
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-cipher-key",
+                "-O",
+                &format!("{}/{}/cipher_key.json", test_dir, example_name),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-witness",
+                "-D",
+                &format!("{}/{}/input.json", test_dir, example_name),
+                "-M",
+                &format!("{}/{}/network.onnx", test_dir, example_name),
+                "-O",
+                &format!("{}/{}/witness.json", test_dir, example_name),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+                &format!(
+                    "--cipher-key-path={}/{}/cipher_key.json",
+                    test_dir, example_name
+                ),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "setup",
+                "-M",
+                format!("{}/{}/network.onnx", test_dir, example_name).as_str(),
+                "--pk-path",
+                &format!("{}/{}/key.pk", test_dir, example_name),
+                "--vk-path",
+                &format!("{}/{}/key.vk", test_dir, example_name),
+                &format!("--srs-path={}/kzg{}.srs", test_dir, logrows),
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "prove",
+                "-W",
+                format!("{}/{}/witness.json", test_dir, example_name).as_str(),
+                "-M",
+                format!("{}/{}/network.onnx", test_dir, example_name).as_str(),
+                "--proof-path",
+                &format!("{}/{}/proof.pf", test_dir, example_name),
+                "--pk-path",
+                &format!("{}/{}/key.pk", test_dir, example_name),
+                &format!("--srs-path={}/kzg{}.srs", test_dir, logrows),
+                "--transcript=blake",
+                "--strategy=single",
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "verify",
+                &format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                ),
+                "--proof-path",
+                &format!("{}/{}/proof.pf", test_dir, example_name),
+                "--vk-path",
+                &format!("{}/{}/key.vk", test_dir, example_name),
+                &format!("--srs-path={}/kzg{}.srs", test_dir, logrows),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "decrypt",
+                "--proof-path",
+                &format!("{}/{}/proof.pf", test_dir, example_name),
+                "-S",
+                &format!("{}/{}/settings.json", test_dir, example_name),
+                "--key-path",
+                &format!("{}/{}/cipher_key.json", test_dir, example_name),
+                "-O",
+                &format!("{}/{}/decrypted.json", test_dir, example_name),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        // the decrypted tensors are the quantized inputs
+        let data =
+            GraphInput::from_path(format!("{}/{}/input.json", test_dir, example_name).into())
+                .expect("failed to load input data");
+        let decrypted: Vec<Vec<i128>> = serde_json::from_str(
+            &std::fs::read_to_string(format!("{}/{}/decrypted.json", test_dir, example_name))
+                .expect("failed to read decrypted values"),
+        )
+        .expect("failed to parse decrypted values");
+        let quantized: Vec<Vec<i128>> = data
+            .input_data
+            .iter()
+            .map(|input| {
+                input
+                    .iter()
+                    .map(|x| ezkl_lib::graph::quantize_float(x, 0.0, scale).unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(decrypted, quantized);
+
+        // and a different key is rejected
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "gen-cipher-key",
+                "-O",
+                &format!("{}/{}/other_cipher_key.json", test_dir, example_name),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "decrypt",
+                "--proof-path",
+                &format!("{}/{}/proof.pf", test_dir, example_name),
+                "-S",
+                &format!("{}/{}/settings.json", test_dir, example_name),
+                "--key-path",
+                &format!("{}/{}/other_cipher_key.json", test_dir, example_name),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(!status.success());
+    }
+
     // prove-serialize-verify, the usual full path
     fn kzg_fuzz(example_name: String, scale: usize, bits: usize, logrows: usize, transcript: &str) {
         let test_dir = TEST_DIR.path().to_str().unwrap();
@@ -2,7 +2,7 @@
 #[cfg(test)]
 mod wasm32 {
     use ezkl_lib::circuit::Tolerance;
-    use ezkl_lib::commands::RunArgs;
+    use ezkl_lib::commands::{HashMode, RunArgs};
     use ezkl_lib::graph::GraphSettings;
     use ezkl_lib::pfsys::Snarkbytes;
     use ezkl_lib::wasm::{
@@ -86,6 +86,9 @@ mod wasm32 {
             input_visibility: "private".into(),
             output_visibility: "public".into(),
             param_visibility: "private".into(),
+            hash_mode: HashMode::default(),
+            membership_depth: 0,
+            prove_accuracy: false,
             allocated_constraints: Some(1000), // assuming an arbitrary value here for the sake of the example
         };
 
@@ -142,6 +145,9 @@ mod wasm32 {
             input_visibility: "private".into(),
             output_visibility: "public".into(),
             param_visibility: "private".into(),
+            hash_mode: HashMode::default(),
+            membership_depth: 0,
+            prove_accuracy: false,
             allocated_constraints: Some(1000), // assuming an arbitrary value here for the sake of the example
         };
 